@@ -0,0 +1,3 @@
+-- Drop tables in reverse order
+DROP TABLE IF EXISTS photos;
+DROP TABLE IF EXISTS albums;
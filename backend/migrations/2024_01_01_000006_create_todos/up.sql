@@ -0,0 +1,22 @@
+-- Create todos table based on JSONPlaceholder schema
+CREATE TABLE todos (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    title VARCHAR NOT NULL,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_todos_user_id ON todos(user_id);
+CREATE INDEX idx_todos_user_id_completed ON todos(user_id, completed);
+
+-- Insert sample todos (based on JSONPlaceholder data)
+INSERT INTO todos (id, user_id, title, completed, created_at, updated_at) VALUES
+('d50e8400-e29b-41d4-a716-446655440001', '550e8400-e29b-41d4-a716-446655440001', 'delectus aut autem', FALSE, NOW(), NOW()),
+('d50e8400-e29b-41d4-a716-446655440002', '550e8400-e29b-41d4-a716-446655440001', 'quis ut nam facilis et officia qui', FALSE, NOW(), NOW()),
+('d50e8400-e29b-41d4-a716-446655440003', '550e8400-e29b-41d4-a716-446655440001', 'fugiat veniam minus', FALSE, NOW(), NOW()),
+('d50e8400-e29b-41d4-a716-446655440004', '550e8400-e29b-41d4-a716-446655440001', 'et porro tempora', TRUE, NOW(), NOW()),
+('d50e8400-e29b-41d4-a716-446655440005', '550e8400-e29b-41d4-a716-446655440002', 'suscipit repellat esse quibusdam voluptatem incidunt', FALSE, NOW(), NOW()),
+('d50e8400-e29b-41d4-a716-446655440006', '550e8400-e29b-41d4-a716-446655440002', 'distinctio vitae autem nihil ut molestias quo', TRUE, NOW(), NOW()),
+('d50e8400-e29b-41d4-a716-446655440007', '550e8400-e29b-41d4-a716-446655440003', 'suscipit qui totam', TRUE, NOW(), NOW());
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS idx_addresses_search_vector;
+DROP INDEX IF EXISTS idx_companies_search_vector;
+DROP INDEX IF EXISTS idx_users_search_vector;
+
+ALTER TABLE addresses DROP COLUMN IF EXISTS search_vector;
+ALTER TABLE companies DROP COLUMN IF EXISTS search_vector;
+ALTER TABLE users DROP COLUMN IF EXISTS search_vector;
@@ -0,0 +1,23 @@
+-- Full-text search documents for `GET /users?q=`.
+-- Generated columns cannot reference other tables, so each table carries the
+-- part of the document it owns and queries concatenate them across the joins.
+-- The `simple` configuration keeps names and emails unstemmed; `@` and `.` are
+-- turned into spaces so that every part of an email is searchable.
+ALTER TABLE users ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', name), 'A') ||
+    setweight(to_tsvector('simple', username), 'A') ||
+    setweight(to_tsvector('simple', translate(email, '@.', '  ')), 'B')
+) STORED;
+
+ALTER TABLE companies ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', name), 'B') ||
+    setweight(to_tsvector('simple', coalesce(catch_phrase, '')), 'C')
+) STORED;
+
+ALTER TABLE addresses ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', city), 'C')
+) STORED;
+
+CREATE INDEX idx_users_search_vector ON users USING GIN (search_vector);
+CREATE INDEX idx_companies_search_vector ON companies USING GIN (search_vector);
+CREATE INDEX idx_addresses_search_vector ON addresses USING GIN (search_vector);
@@ -0,0 +1,2 @@
+-- Keyset pagination (`GET /users?cursor=`) seeks on (created_at, id)
+CREATE INDEX idx_users_created_at_id ON users(created_at, id);
@@ -0,0 +1,14 @@
+-- Access tokens revoked before they expire (`POST /auth/logout`). Rows are
+-- only needed until `expires_at`, after which the token is rejected anyway.
+CREATE TABLE revoked_tokens (
+    jti UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
+
+-- `POST /auth/logout-all`: every access token issued before this instant is revoked
+ALTER TABLE auth_users ADD COLUMN tokens_revoked_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,18 @@
+-- Accounts that existed before verification was introduced count as verified.
+ALTER TABLE auth_users ADD COLUMN email_verified_at TIMESTAMP WITH TIME ZONE;
+UPDATE auth_users SET email_verified_at = created_at;
+
+-- Single-use verification tokens, stored as SHA-256 hashes. Each token is
+-- bound to the address it was sent to, so it cannot verify a later email change.
+CREATE TABLE email_verification_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    email VARCHAR NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_verification_tokens_user_id ON email_verification_tokens(user_id);
@@ -0,0 +1,6 @@
+-- admin > editor > viewer. Existing accounts become editors, which keeps what
+-- they could do before; only the seeded admin account is promoted.
+ALTER TABLE auth_users ADD COLUMN role VARCHAR NOT NULL DEFAULT 'editor'
+    CHECK (role IN ('admin', 'editor', 'viewer'));
+
+UPDATE auth_users SET role = 'admin' WHERE email = 'admin@example.com';
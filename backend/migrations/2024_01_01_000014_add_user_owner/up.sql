@@ -0,0 +1,5 @@
+-- The account that created a user profile. Only the owner or an admin may
+-- change or delete it; profiles created before this migration have no owner.
+ALTER TABLE users ADD COLUMN owner_id UUID REFERENCES auth_users(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_users_owner_id ON users(owner_id);
@@ -0,0 +1,14 @@
+-- Every password login attempt, used to throttle guessing per account and per
+-- client IP. `email` is lowercased and recorded even when no account has it.
+CREATE TABLE login_attempts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    email VARCHAR NOT NULL,
+    user_id UUID REFERENCES auth_users(id) ON DELETE SET NULL,
+    ip_address VARCHAR NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_login_attempts_email_created_at ON login_attempts(email, created_at);
+CREATE INDEX idx_login_attempts_ip_address_created_at ON login_attempts(ip_address, created_at);
@@ -0,0 +1,18 @@
+-- Personal API keys for scripts and other machine clients. A key reads
+-- `ck_<prefix>_<secret>`: the prefix is stored as is to find and show the key,
+-- the secret only as a SHA-256 hash. A key acts as its account, limited to
+-- `scopes`.
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    prefix VARCHAR NOT NULL UNIQUE,
+    secret_hash VARCHAR NOT NULL,
+    scopes VARCHAR[] NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id);
@@ -0,0 +1,52 @@
+-- OAuth 2.0 clients: other apps that get tokens for accounts of this backend.
+-- Confidential clients authenticate with a secret, stored as a SHA-256 hash;
+-- public clients (SPAs, mobile apps) have none and must use PKCE.
+CREATE TABLE oauth_clients (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    owner_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    client_secret_hash VARCHAR,
+    redirect_uris VARCHAR[] NOT NULL,
+    scopes VARCHAR[] NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_oauth_clients_owner_id ON oauth_clients(owner_id);
+
+-- Single-use codes from /oauth/authorize, exchanged at /oauth/token together
+-- with the PKCE verifier for `code_challenge` (S256). Stored as SHA-256 hashes.
+CREATE TABLE oauth_authorization_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    client_id UUID NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    code_hash VARCHAR NOT NULL UNIQUE,
+    redirect_uri VARCHAR NOT NULL,
+    scopes VARCHAR[] NOT NULL,
+    code_challenge VARCHAR NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_oauth_authorization_codes_user_id ON oauth_authorization_codes(user_id);
+
+-- Scopes a user has allowed a client; later authorizations within them skip
+-- the consent screen.
+CREATE TABLE oauth_consents (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    client_id UUID NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    scopes VARCHAR[] NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, client_id)
+);
+
+-- Refresh tokens issued to OAuth clients live next to the session ones, so
+-- logout-all and password changes revoke them too. They carry the client and
+-- the granted scopes; session refresh tokens have neither.
+ALTER TABLE refresh_tokens
+    ADD COLUMN client_id UUID REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    ADD COLUMN scopes VARCHAR[];
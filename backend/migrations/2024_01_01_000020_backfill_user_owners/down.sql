@@ -0,0 +1,2 @@
+-- Ownership assigned by the backfill cannot be told apart from real ownership
+SELECT 1;
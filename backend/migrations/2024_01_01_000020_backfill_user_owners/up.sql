@@ -0,0 +1,6 @@
+-- Profiles created before owners existed are given to the first admin account,
+-- which is who could modify them anyway. Profiles whose owner account is later
+-- deleted go back to having no owner and stay admin-only.
+UPDATE users
+SET owner_id = (SELECT id FROM auth_users WHERE role = 'admin' ORDER BY created_at, id LIMIT 1)
+WHERE owner_id IS NULL;
@@ -0,0 +1,9 @@
+-- `DELETE /auth/me`: the account row and its `tokens_revoked_at` are gone, but
+-- access tokens issued before the deletion must keep being rejected until
+-- they expire. Rows are only needed for the access token lifetime.
+CREATE TABLE deleted_accounts (
+    user_id UUID PRIMARY KEY,
+    deleted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_deleted_accounts_deleted_at ON deleted_accounts(deleted_at);
@@ -0,0 +1,5 @@
+ALTER TABLE addresses DROP CONSTRAINT addresses_user_id_key;
+ALTER TABLE companies DROP CONSTRAINT companies_user_id_key;
+
+CREATE INDEX idx_addresses_user_id ON addresses(user_id);
+CREATE INDEX idx_companies_user_id ON companies(user_id);
@@ -0,0 +1,11 @@
+-- A user has at most one address and one company, so joining them yields one
+-- row per user and pages over the join hold whole users. Duplicates left by
+-- earlier writes are dropped, keeping one row per user.
+DELETE FROM addresses a USING addresses b WHERE a.user_id = b.user_id AND a.id > b.id;
+DELETE FROM companies a USING companies b WHERE a.user_id = b.user_id AND a.id > b.id;
+
+DROP INDEX idx_addresses_user_id;
+DROP INDEX idx_companies_user_id;
+
+ALTER TABLE addresses ADD CONSTRAINT addresses_user_id_key UNIQUE (user_id);
+ALTER TABLE companies ADD CONSTRAINT companies_user_id_key UNIQUE (user_id);
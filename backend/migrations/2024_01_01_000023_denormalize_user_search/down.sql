@@ -0,0 +1,27 @@
+DROP TRIGGER addresses_search_vector ON addresses;
+DROP TRIGGER companies_search_vector ON companies;
+DROP TRIGGER users_search_vector ON users;
+DROP FUNCTION user_parts_search_vector_update();
+DROP FUNCTION users_search_vector_update();
+DROP FUNCTION user_search_vector(users);
+
+ALTER TABLE users DROP COLUMN search_vector;
+
+ALTER TABLE users ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', name), 'A') ||
+    setweight(to_tsvector('simple', username), 'A') ||
+    setweight(to_tsvector('simple', translate(email, '@.', '  ')), 'B')
+) STORED;
+
+ALTER TABLE companies ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', name), 'B') ||
+    setweight(to_tsvector('simple', coalesce(catch_phrase, '')), 'C')
+) STORED;
+
+ALTER TABLE addresses ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', city), 'C')
+) STORED;
+
+CREATE INDEX idx_users_search_vector ON users USING GIN (search_vector);
+CREATE INDEX idx_companies_search_vector ON companies USING GIN (search_vector);
+CREATE INDEX idx_addresses_search_vector ON addresses USING GIN (search_vector);
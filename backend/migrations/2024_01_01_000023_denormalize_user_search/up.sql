@@ -0,0 +1,67 @@
+-- Full-text search reads a single `users.search_vector`, so its GIN index
+-- serves `GET /users?q=` instead of concatenating per-table vectors across
+-- the joins. Company and address words are copied onto the user by triggers.
+DROP INDEX idx_users_search_vector;
+DROP INDEX idx_companies_search_vector;
+DROP INDEX idx_addresses_search_vector;
+
+ALTER TABLE users DROP COLUMN search_vector;
+ALTER TABLE companies DROP COLUMN search_vector;
+ALTER TABLE addresses DROP COLUMN search_vector;
+
+ALTER TABLE users ADD COLUMN search_vector TSVECTOR NOT NULL DEFAULT ''::tsvector;
+
+-- The same document as before: `simple` keeps names and emails unstemmed, and
+-- `@` and `.` become spaces so that every part of an email is searchable.
+CREATE FUNCTION user_search_vector(u users) RETURNS TSVECTOR AS $$
+    SELECT setweight(to_tsvector('simple', u.name), 'A') ||
+        setweight(to_tsvector('simple', u.username), 'A') ||
+        setweight(to_tsvector('simple', translate(u.email, '@.', '  ')), 'B') ||
+        coalesce((
+            SELECT setweight(to_tsvector('simple', c.name), 'B') ||
+                setweight(to_tsvector('simple', coalesce(c.catch_phrase, '')), 'C')
+            FROM companies c
+            WHERE c.user_id = u.id
+        ), ''::tsvector) ||
+        coalesce((
+            SELECT setweight(to_tsvector('simple', a.city), 'C')
+            FROM addresses a
+            WHERE a.user_id = u.id
+        ), ''::tsvector)
+$$ LANGUAGE SQL STABLE;
+
+CREATE FUNCTION users_search_vector_update() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector := user_search_vector(NEW);
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_search_vector
+    BEFORE INSERT OR UPDATE OF name, username, email ON users
+    FOR EACH ROW EXECUTE FUNCTION users_search_vector_update();
+
+-- Recomputes the owning user's document when its company or address changes
+CREATE FUNCTION user_parts_search_vector_update() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE users SET search_vector = user_search_vector(users) WHERE id = OLD.user_id;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        UPDATE users SET search_vector = user_search_vector(users) WHERE id = NEW.user_id;
+    END IF;
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER companies_search_vector
+    AFTER INSERT OR UPDATE OR DELETE ON companies
+    FOR EACH ROW EXECUTE FUNCTION user_parts_search_vector_update();
+
+CREATE TRIGGER addresses_search_vector
+    AFTER INSERT OR UPDATE OR DELETE ON addresses
+    FOR EACH ROW EXECUTE FUNCTION user_parts_search_vector_update();
+
+UPDATE users SET search_vector = user_search_vector(users);
+
+CREATE INDEX idx_users_search_vector ON users USING GIN (search_vector);
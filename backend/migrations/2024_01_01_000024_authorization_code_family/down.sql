@@ -0,0 +1,2 @@
+DROP INDEX idx_refresh_tokens_revoked_at;
+ALTER TABLE oauth_authorization_codes DROP COLUMN family_id;
@@ -0,0 +1,10 @@
+-- The session (refresh token family) an authorization code starts, so that
+-- reusing the code can revoke the tokens already issued from it
+-- (RFC 6749 section 4.1.2). Codes issued before this have no tokens to find.
+ALTER TABLE oauth_authorization_codes ADD COLUMN family_id UUID NOT NULL DEFAULT gen_random_uuid();
+ALTER TABLE oauth_authorization_codes ALTER COLUMN family_id DROP DEFAULT;
+
+-- Access tokens of sessions revoked within their lifetime are rejected too;
+-- the revocation list loads those sessions periodically.
+CREATE INDEX idx_refresh_tokens_revoked_at ON refresh_tokens(revoked_at)
+    WHERE revoked_at IS NOT NULL;
@@ -1,10 +1,42 @@
 use std::env;
 
+use crate::models::Role;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
     pub jwt_secret: String,
+    /// Directory of `<kid>.pem` RS256/EdDSA private keys (`JWT_KEYS_DIR`). Without it
+    /// access tokens are signed with HS256 and `jwt_secret`.
+    pub jwt_keys_dir: Option<String>,
+    /// Key new tokens are signed with (`JWT_SIGNING_KEY_ID`, default: the last kid in
+    /// sort order).
+    pub jwt_signing_kid: Option<String>,
+    /// Base64 of the 32-byte key TOTP secrets are encrypted with (`MFA_ENCRYPTION_KEY`).
+    /// Without it a key is derived from `jwt_secret`.
+    pub mfa_encryption_key: Option<String>,
+    /// Key keyset pagination cursors are signed with (`CURSOR_SECRET`). Without it a
+    /// key is derived from `jwt_secret`.
+    pub cursor_secret: Option<String>,
     pub port: u16,
+    /// Whether `GET` user routes are served without a token (`PUBLIC_READS`, default `true`).
+    pub public_reads: bool,
+    /// Whether accounts must verify their email before logging in
+    /// (`REQUIRE_EMAIL_VERIFICATION`, default `false`).
+    pub require_email_verification: bool,
+    /// Role given to newly registered accounts (`DEFAULT_ROLE`, default `editor`).
+    pub default_role: Role,
+    /// Frontend base URL used in links sent by email (`APP_URL`).
+    pub app_url: String,
+    /// SMTP relay (`SMTP_URL`); without it email goes to the file outbox.
+    pub smtp_url: Option<String>,
+    /// Sender address (`MAIL_FROM`).
+    pub mail_from: String,
+    /// File the outbox appends to when SMTP is not configured (`MAIL_OUTBOX`).
+    pub mail_outbox: String,
+    /// Whether to take the client IP from `X-Forwarded-For` (`TRUST_X_FORWARDED_FOR`,
+    /// default `false`). Enable only behind a reverse proxy that sets the header.
+    pub trust_forwarded_for: bool,
 }
 
 impl Config {
@@ -12,10 +44,34 @@ impl Config {
         Ok(Config {
             database_url: env::var("DATABASE_URL")?,
             jwt_secret: env::var("JWT_SECRET")?,
+            jwt_keys_dir: env::var("JWT_KEYS_DIR").ok().filter(|dir| !dir.is_empty()),
+            jwt_signing_kid: env::var("JWT_SIGNING_KEY_ID").ok().filter(|kid| !kid.is_empty()),
+            mfa_encryption_key: env::var("MFA_ENCRYPTION_KEY").ok().filter(|key| !key.is_empty()),
+            cursor_secret: env::var("CURSOR_SECRET").ok().filter(|secret| !secret.is_empty()),
             port: env::var("PORT")
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
                 .unwrap_or(8080),
+            public_reads: env::var("PUBLIC_READS")
+                .map(|value| !matches!(value.as_str(), "false" | "0"))
+                .unwrap_or(true),
+            require_email_verification: env::var("REQUIRE_EMAIL_VERIFICATION")
+                .map(|value| matches!(value.as_str(), "true" | "1"))
+                .unwrap_or(false),
+            default_role: env::var("DEFAULT_ROLE")
+                .ok()
+                .and_then(|role| role.parse().ok())
+                .unwrap_or(Role::Editor),
+            app_url: env::var("APP_URL")
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or_else(|_| "http://localhost".to_string()),
+            smtp_url: env::var("SMTP_URL").ok().filter(|url| !url.is_empty()),
+            mail_from: env::var("MAIL_FROM")
+                .unwrap_or_else(|_| "Cursor <no-reply@localhost>".to_string()),
+            mail_outbox: env::var("MAIL_OUTBOX").unwrap_or_else(|_| "outbox.jsonl".to_string()),
+            trust_forwarded_for: env::var("TRUST_X_FORWARDED_FOR")
+                .map(|value| matches!(value.as_str(), "true" | "1"))
+                .unwrap_or(false),
         })
     }
 } 
\ No newline at end of file
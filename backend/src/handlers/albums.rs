@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    handlers::resource::{validate, ListResponse, Resource},
+    models::{Album, CreateAlbumRequest, ErrorResponse, UpdateAlbumRequest, WithRelations},
+    query::{ListQuery, Relations},
+    services::album_service,
+};
+
+const ALBUMS: Resource = Resource {
+    name: "Album",
+    plural: "albums",
+    parent: "User",
+};
+
+pub async fn get_albums(
+    State(pool): State<DbPool>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Album>> {
+    ALBUMS.list(&params, album_service::get_all_albums(&pool, &params).await)
+}
+
+pub async fn get_user_albums(
+    State(pool): State<DbPool>,
+    Path(user_id): Path<Uuid>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Album>> {
+    let params = params.with_filter("user_id", user_id);
+    ALBUMS.list(&params, album_service::get_all_albums(&pool, &params).await)
+}
+
+pub async fn get_album(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    relations: Relations,
+) -> Result<Json<WithRelations<Album>>, (StatusCode, Json<ErrorResponse>)> {
+    ALBUMS.found(album_service::get_album_with_relations(&pool, id, &relations).await)
+}
+
+pub async fn create_album(
+    State(pool): State<DbPool>,
+    Json(payload): Json<CreateAlbumRequest>,
+) -> Result<Json<Album>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    ALBUMS.created(album_service::create_album(&pool, &payload).await)
+}
+
+pub async fn replace_album(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<CreateAlbumRequest>,
+) -> Result<Json<Album>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    ALBUMS.updated(album_service::replace_album(&pool, id, &payload).await)
+}
+
+pub async fn update_album(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateAlbumRequest>,
+) -> Result<Json<Album>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    ALBUMS.updated(album_service::update_album(&pool, id, &payload).await)
+}
+
+pub async fn delete_album(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    ALBUMS.deleted(album_service::delete_album(&pool, id).await)
+}
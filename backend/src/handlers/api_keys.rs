@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    database::DbPool,
+    handlers::auth::current_user_id,
+    models::{ApiKeyResponse, Claims, CreateApiKeyRequest, CreatedApiKeyResponse, ErrorResponse},
+    services::api_key_service,
+};
+
+pub async fn get_api_keys(
+    State(pool): State<DbPool>,
+    claims: Claims,
+) -> Result<Json<Vec<ApiKeyResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+
+    match api_key_service::get_api_keys(&pool, user_id).await {
+        Ok(api_keys) => Ok(Json(api_keys.into_iter().map(ApiKeyResponse::from).collect())),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to fetch API keys".to_string(),
+            }),
+        )),
+    }
+}
+
+/// Creates a key for the current account. The response is the only time the
+/// full key is shown.
+pub async fn create_api_key(
+    State(pool): State<DbPool>,
+    claims: Claims,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<Json<CreatedApiKeyResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    let user_id = current_user_id(&claims)?;
+    let mut scopes = payload.scopes;
+    scopes.sort_by_key(|scope| scope.as_str());
+    scopes.dedup();
+
+    match api_key_service::create_api_key(
+        &pool,
+        user_id,
+        &payload.name,
+        scopes,
+        payload.expires_in_days,
+    )
+    .await
+    {
+        Ok((api_key, key)) => Ok(Json(CreatedApiKeyResponse {
+            api_key: api_key.into(),
+            key,
+        })),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to create API key".to_string(),
+            }),
+        )),
+    }
+}
+
+/// Revokes one of the current account's keys; it stops working immediately.
+pub async fn delete_api_key(
+    State(pool): State<DbPool>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+
+    match api_key_service::delete_api_key(&pool, user_id, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "not_found".to_string(),
+                message: "API key not found".to_string(),
+            }),
+        )),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to delete API key".to_string(),
+            }),
+        )),
+    }
+}
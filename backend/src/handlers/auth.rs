@@ -1,24 +1,45 @@
-use axum::{extract::State, http::StatusCode, Json};
-use bcrypt::{hash, verify, DEFAULT_COST};
-use chrono::Utc;
-use jsonwebtoken::{encode, EncodingKey, Header};
-use std::env;
+use axum::{
+    extract::{Path, State},
+    http::{header::RETRY_AFTER, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
     database::DbPool,
+    keyring::Keyring,
+    mailer::Email,
+    middleware::client_ip::ClientIp,
     models::{
-        AuthResponse, AuthUser, AuthUserResponse, Claims, ErrorResponse, LoginRequest,
-        NewAuthUser, RegisterRequest,
+        AuthResponse, AuthUser, AuthUserResponse, ChangePasswordRequest, Claims, ErrorResponse,
+        LoginAttempt, LoginRequest, LoginResponse, MagicLinkConsumeRequest, MagicLinkRequest,
+        MfaChallengeResponse, NewAuthUser, NewLoginAttempt, NewRevokedToken,
+        PasswordResetConfirmRequest, PasswordResetRequest, RefreshRequest, RegisterRequest,
+        RegisterResponse, ResendVerificationRequest, UpdateProfileRequest, UpdateRoleRequest,
+        VerificationPendingResponse, VerifyEmailRequest,
+    },
+    password::Passwords,
+    query::{ListError, ListQuery},
+    services::{
+        auth_service::{self, RefreshOutcome, ACCESS_TOKEN_TTL_MINUTES},
+        login_attempt_service,
+        mfa_service::{self, MFA_CHALLENGE_TTL_MINUTES},
     },
-    services::auth_service,
+    state::AppState,
 };
 
+/// Creates the account and emails a verification link. Tokens are issued right
+/// away unless `REQUIRE_EMAIL_VERIFICATION` is on, in which case the response
+/// is `202` and the user has to verify first.
 pub async fn register(
-    State(pool): State<DbPool>,
+    State(state): State<AppState>,
     Json(payload): Json<RegisterRequest>,
-) -> Result<Json<AuthResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<(StatusCode, Json<RegisterResponse>), (StatusCode, Json<ErrorResponse>)> {
     // Validate input
     if let Err(errors) = payload.validate() {
         return Err((
@@ -31,67 +52,636 @@ pub async fn register(
     }
 
     // Check if user already exists
-    match auth_service::get_user_by_email(&pool, &payload.email).await {
-        Ok(_) => {
+    if let Ok(_existing) = auth_service::get_user_by_email(&state.pool, &payload.email).await {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "user_exists".to_string(),
+                message: "User with this email already exists".to_string(),
+            }),
+        ));
+    }
+
+    // Hash password
+    let password_hash = hash_password(&state.passwords, &payload.password).await?;
+
+    // Create user
+    let user = NewAuthUser {
+        id: Uuid::new_v4(),
+        name: payload.name,
+        email: payload.email.clone(),
+        password_hash,
+        role: state.config.default_role,
+    };
+
+    let created_user = match auth_service::create_user(&state.pool, &user).await {
+        Ok(created_user) => created_user,
+        Err(_) => {
             return Err((
-                StatusCode::CONFLICT,
+                StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
-                    error: "user_exists".to_string(),
-                    message: "User with this email already exists".to_string(),
+                    error: "create_error".to_string(),
+                    message: "Failed to create user".to_string(),
                 }),
             ));
         }
-        Err(_) => {} // User doesn't exist, continue
+    };
+
+    send_verification_email(&state, &created_user).await;
+
+    if state.config.require_email_verification {
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(RegisterResponse::VerificationPending(VerificationPendingResponse {
+                user: user_response(created_user),
+                email_verification_required: true,
+            })),
+        ));
     }
 
-    // Hash password
-    let password_hash = match hash(&payload.password, DEFAULT_COST) {
-        Ok(hash) => hash,
+    let tokens = issue_tokens(&state, created_user, Uuid::new_v4()).await?;
+    Ok((StatusCode::OK, Json(RegisterResponse::Authenticated(tokens))))
+}
+
+/// Checks the password and returns tokens, or a `202` MFA challenge when the
+/// account has TOTP enabled.
+pub async fn login(
+    State(state): State<AppState>,
+    ClientIp(ip_address): ClientIp,
+    Json(payload): Json<LoginRequest>,
+) -> Result<(StatusCode, Json<LoginResponse>), Response> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        )
+            .into_response());
+    }
+
+    let user = auth_service::get_user_by_email(&state.pool, &payload.email).await.ok();
+
+    // Throttle repeated failures for this account and this client; the attempt
+    // counts as failed until the password checks out
+    let user_id = user.as_ref().map(|user| user.id);
+    let attempt_id = start_login_attempt(&state, &payload.email, user_id, &ip_address).await?;
+
+    // Unknown emails are checked against a dummy hash so they take as long
+    let password_hash = user.as_ref().map(|user| user.password_hash.as_str());
+    let authenticated = verify_password(&state.passwords, &payload.password, password_hash).await;
+    let user = match user {
+        Some(user) if authenticated => user,
+        _ => {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "invalid_credentials".to_string(),
+                    message: "Invalid email or password".to_string(),
+                }),
+            )
+                .into_response());
+        }
+    };
+
+    // finish_login records the outcome from here on
+    discard_login_attempt(&state, attempt_id).await;
+
+    // Upgrade bcrypt or outdated Argon2 hashes now that the password is known
+    if state.passwords.needs_rehash(&user.password_hash) {
+        let rehashed = match hash_password(&state.passwords, &payload.password).await {
+            Ok(password_hash) => {
+                auth_service::update_password_hash(&state.pool, user.id, &password_hash)
+                    .await
+                    .map_err(|err| err.to_string())
+            }
+            Err(_) => Err("failed to hash password".to_string()),
+        };
+        if let Err(err) = rehashed {
+            tracing::error!("Failed to rehash password for {}: {}", user.id, err);
+        }
+    }
+
+    finish_login(&state, user, ip_address).await
+}
+
+/// Second half of a login once the account is known, shared by password and
+/// sign-in link logins: records the attempt, enforces email verification and
+/// returns tokens or an MFA challenge.
+pub(crate) async fn finish_login(
+    state: &AppState,
+    user: AuthUser,
+    ip_address: String,
+) -> Result<(StatusCode, Json<LoginResponse>), Response> {
+    // With TOTP enabled the attempt is recorded once the second factor is checked
+    let mfa_enabled = match mfa_service::is_totp_enabled(&state.pool, user.id).await {
+        Ok(enabled) => enabled,
         Err(_) => {
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
-                    error: "hash_error".to_string(),
-                    message: "Failed to hash password".to_string(),
+                    error: "database_error".to_string(),
+                    message: "Failed to check two-factor authentication".to_string(),
                 }),
-            ));
+            )
+                .into_response());
         }
     };
+    if !mfa_enabled {
+        record_login_attempt(state, &user.email, Some(user.id), ip_address, true).await;
+    }
 
-    // Create user
-    let user = NewAuthUser {
+    if state.config.require_email_verification && user.email_verified_at.is_none() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "email_not_verified".to_string(),
+                message: "Please verify your email address before logging in".to_string(),
+            }),
+        )
+            .into_response());
+    }
+
+    if mfa_enabled {
+        return match mfa_service::create_mfa_challenge(&state.pool, user.id).await {
+            Ok(mfa_token) => Ok((
+                StatusCode::ACCEPTED,
+                Json(LoginResponse::MfaRequired(MfaChallengeResponse {
+                    mfa_required: true,
+                    mfa_token,
+                    expires_in: MFA_CHALLENGE_TTL_MINUTES * 60,
+                })),
+            )),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "token_error".to_string(),
+                    message: "Failed to create MFA challenge".to_string(),
+                }),
+            )
+                .into_response()),
+        };
+    }
+
+    let response = issue_tokens(state, user, Uuid::new_v4())
+        .await
+        .map_err(IntoResponse::into_response)?;
+    Ok((StatusCode::OK, Json(LoginResponse::Authenticated(response))))
+}
+
+/// Rejects with `429` while `email` or the client is backing off after failed
+/// attempts; otherwise records a failed attempt and returns its id. Callers
+/// settle it with [`succeed_login_attempt`] or [`discard_login_attempt`].
+pub(crate) async fn start_login_attempt(
+    state: &AppState,
+    email: &str,
+    user_id: Option<Uuid>,
+    ip_address: &str,
+) -> Result<Uuid, Response> {
+    let attempt = NewLoginAttempt {
         id: Uuid::new_v4(),
-        name: payload.name,
-        email: payload.email.clone(),
-        password_hash,
+        email: email.to_lowercase(),
+        user_id,
+        ip_address: ip_address.to_string(),
+        succeeded: false,
     };
+    match login_attempt_service::start_attempt(&state.pool, &attempt).await {
+        Ok(None) => Ok(attempt.id),
+        Ok(Some(wait)) => Err(too_many_attempts(wait)),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to check login attempts".to_string(),
+            }),
+        )
+            .into_response()),
+    }
+}
+
+pub(crate) async fn succeed_login_attempt(state: &AppState, id: Uuid) {
+    if let Err(err) = login_attempt_service::mark_succeeded(&state.pool, id).await {
+        tracing::error!("Failed to record login attempt {}: {}", id, err);
+    }
+}
+
+/// Drops an attempt from [`start_login_attempt`] that is recorded otherwise.
+pub(crate) async fn discard_login_attempt(state: &AppState, id: Uuid) {
+    if let Err(err) = login_attempt_service::delete_attempt(&state.pool, id).await {
+        tracing::error!("Failed to discard login attempt {}: {}", id, err);
+    }
+}
+
+/// Records an attempt for throttling and the admin log; failures are logged.
+pub(crate) async fn record_login_attempt(
+    state: &AppState,
+    email: &str,
+    user_id: Option<Uuid>,
+    ip_address: String,
+    succeeded: bool,
+) {
+    let attempt = NewLoginAttempt {
+        id: Uuid::new_v4(),
+        email: email.to_lowercase(),
+        user_id,
+        ip_address,
+        succeeded,
+    };
+    if let Err(err) = login_attempt_service::record_login_attempt(&state.pool, &attempt).await {
+        tracing::error!("Failed to record login attempt for {}: {}", attempt.email, err);
+    }
+}
 
-    match auth_service::create_user(&pool, &user).await {
-        Ok(created_user) => {
-            let token = generate_jwt(&created_user)?;
-            Ok(Json(AuthResponse {
-                token,
-                user: AuthUserResponse {
-                    id: created_user.id,
-                    name: created_user.name,
-                    email: created_user.email,
-                    created_at: created_user.created_at,
-                },
-            }))
+/// 429 telling the client how many seconds to wait before the next attempt.
+fn too_many_attempts(wait: Duration) -> Response {
+    let seconds = (wait.num_milliseconds() + 999) / 1000;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, seconds.to_string())],
+        Json(ErrorResponse {
+            error: "too_many_attempts".to_string(),
+            message: format!("Too many failed login attempts. Try again in {} seconds", seconds),
+        }),
+    )
+        .into_response()
+}
+
+pub async fn get_login_attempts(
+    State(pool): State<DbPool>,
+    params: ListQuery,
+) -> Result<(HeaderMap, Json<Vec<LoginAttempt>>), (StatusCode, Json<ErrorResponse>)> {
+    match login_attempt_service::get_all_login_attempts(&pool, &params).await {
+        Ok(page) => Ok((params.response_headers(page.total_count), Json(page.items))),
+        Err(ListError::Query(err)) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_query".to_string(),
+                message: err.to_string(),
+            }),
+        )),
+        Err(ListError::Database(_)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to fetch login attempts".to_string(),
+            }),
+        )),
+    }
+}
+
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<AuthResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    let (user_id, family_id, refresh_token) =
+        match auth_service::rotate_refresh_token(&state.pool, &payload.refresh_token, None).await {
+            Ok(RefreshOutcome::Rotated {
+                user_id,
+                family_id,
+                refresh_token,
+                ..
+            }) => (user_id, family_id, refresh_token),
+            Ok(RefreshOutcome::Reused { family_id }) => {
+                tracing::warn!("Refresh token reuse detected; token family revoked");
+                state.revocations.revoke_family(family_id);
+                return Err((
+                    StatusCode::UNAUTHORIZED,
+                    Json(ErrorResponse {
+                        error: "token_reused".to_string(),
+                        message: "Refresh token was already used; please log in again".to_string(),
+                    }),
+                ));
+            }
+            Ok(RefreshOutcome::Invalid) => {
+                return Err((
+                    StatusCode::UNAUTHORIZED,
+                    Json(ErrorResponse {
+                        error: "invalid_token".to_string(),
+                        message: "Invalid or expired refresh token".to_string(),
+                    }),
+                ));
+            }
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "database_error".to_string(),
+                        message: "Failed to refresh token".to_string(),
+                    }),
+                ));
+            }
+        };
+
+    let user = match auth_service::get_user_by_id(&state.pool, user_id).await {
+        Ok(user) => user,
+        Err(_) => {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "invalid_token".to_string(),
+                    message: "Invalid or expired refresh token".to_string(),
+                }),
+            ));
+        }
+    };
+
+    Ok(Json(AuthResponse {
+        token: generate_jwt(&state.keys, &user, family_id)?,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+        user: user_response(user),
+    }))
+}
+
+/// Access and refresh tokens for a freshly authenticated user; the refresh
+/// token starts `family_id`.
+pub(crate) async fn issue_tokens(
+    state: &AppState,
+    user: AuthUser,
+    family_id: Uuid,
+) -> Result<AuthResponse, (StatusCode, Json<ErrorResponse>)> {
+    let token = generate_jwt(&state.keys, &user, family_id)?;
+    let refresh_token = match auth_service::create_refresh_token(&state.pool, user.id, family_id)
+        .await
+    {
+        Ok(refresh_token) => refresh_token,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "token_error".to_string(),
+                    message: "Failed to generate refresh token".to_string(),
+                }),
+            ));
+        }
+    };
+
+    Ok(AuthResponse {
+        token,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+        user: user_response(user),
+    })
+}
+
+fn user_response(user: AuthUser) -> AuthUserResponse {
+    AuthUserResponse {
+        id: user.id,
+        name: user.name,
+        email: user.email,
+        email_verified_at: user.email_verified_at,
+        role: user.role,
+        created_at: user.created_at,
+    }
+}
+
+/// Revokes the caller's access token and the refresh tokens of its session.
+pub async fn logout(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    let (Ok(jti), Ok(user_id), Ok(family_id)) = (
+        claims.jti.parse::<Uuid>(),
+        claims.sub.parse::<Uuid>(),
+        claims.sid.parse::<Uuid>(),
+    ) else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "unauthorized".to_string(),
+                message: "Invalid or expired token".to_string(),
+            }),
+        ));
+    };
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+
+    let revoked = NewRevokedToken {
+        jti,
+        user_id,
+        expires_at,
+    };
+    let result = match auth_service::revoke_token(&state.pool, &revoked).await {
+        Ok(()) => auth_service::revoke_refresh_family(&state.pool, family_id).await,
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(()) => {
+            state.revocations.revoke_token(jti, expires_at);
+            state.revocations.revoke_family(family_id);
+            Ok(StatusCode::NO_CONTENT)
         }
         Err(_) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
-                error: "create_error".to_string(),
-                message: "Failed to create user".to_string(),
+                error: "database_error".to_string(),
+                message: "Failed to log out".to_string(),
             }),
         )),
     }
 }
 
-pub async fn login(
+/// Revokes every access and refresh token of the caller, on all devices.
+pub async fn logout_all(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+
+    match auth_service::revoke_all_tokens(&state.pool, user_id).await {
+        Ok(cutoff) => {
+            state.revocations.revoke_all(user_id, cutoff);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to log out".to_string(),
+            }),
+        )),
+    }
+}
+
+/// Emails a single-use sign-in link. Always answers `202` so the endpoint does
+/// not reveal which addresses have accounts.
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    ClientIp(ip_address): ClientIp,
+    Json(payload): Json<MagicLinkRequest>,
+) -> Result<StatusCode, Response> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        )
+            .into_response());
+    }
+
+    let user = auth_service::get_user_by_email(&state.pool, &payload.email).await.ok();
+
+    // Requests count towards the login throttle as failed attempts until a link
+    // is used, for unknown addresses too, so they cannot flood an inbox
+    let user_id = user.as_ref().map(|user| user.id);
+    start_login_attempt(&state, &payload.email, user_id, &ip_address).await?;
+
+    let Some(user) = user else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+
+    let token = match auth_service::create_magic_link_token(&state.pool, user.id, &user.email).await
+    {
+        Ok(token) => token,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "token_error".to_string(),
+                    message: "Failed to create sign-in link".to_string(),
+                }),
+            )
+                .into_response());
+        }
+    };
+
+    let sign_in_url = format!("{}/magic-link?token={}", state.config.app_url, token);
+    if let Err(err) = state.mailer.send(&Email::magic_link(&user.email, &sign_in_url)).await {
+        tracing::error!("Failed to send sign-in link email: {}", err);
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Logs in with a token from a sign-in link. Responds like `login`, including
+/// the MFA challenge when the account has TOTP enabled.
+pub async fn consume_magic_link(
+    State(state): State<AppState>,
+    ClientIp(ip_address): ClientIp,
+    Json(payload): Json<MagicLinkConsumeRequest>,
+) -> Result<(StatusCode, Json<LoginResponse>), Response> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        )
+            .into_response());
+    }
+
+    let user = match auth_service::consume_magic_link_token(&state.pool, &payload.token).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "invalid_token".to_string(),
+                    message: "Invalid or expired sign-in link".to_string(),
+                }),
+            )
+                .into_response());
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to check sign-in link".to_string(),
+                }),
+            )
+                .into_response());
+        }
+    };
+
+    finish_login(&state, user, ip_address).await
+}
+
+/// Marks the address as verified with a token from the verification email.
+pub async fn verify_email(
     State(pool): State<DbPool>,
-    Json(payload): Json<LoginRequest>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<Json<AuthUserResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    match auth_service::verify_email(&pool, &payload.token).await {
+        Ok(Some(user)) => Ok(Json(user_response(user))),
+        Ok(None) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_token".to_string(),
+                message: "Invalid or expired verification token".to_string(),
+            }),
+        )),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "update_error".to_string(),
+                message: "Failed to verify email".to_string(),
+            }),
+        )),
+    }
+}
+
+/// Sends a new verification link to an unverified account. Always answers
+/// `202` so the endpoint does not reveal which addresses have accounts.
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    Json(payload): Json<ResendVerificationRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    if let Ok(user) = auth_service::get_user_by_email(&state.pool, &payload.email).await {
+        if user.email_verified_at.is_none() {
+            send_verification_email(&state, &user).await;
+        }
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Changes the caller's password. Every session, including the current one,
+/// is revoked, and a fresh pair of tokens is returned.
+pub async fn change_password(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<ChangePasswordRequest>,
 ) -> Result<Json<AuthResponse>, (StatusCode, Json<ErrorResponse>)> {
     // Validate input
     if let Err(errors) = payload.validate() {
@@ -104,61 +694,391 @@ pub async fn login(
         ));
     }
 
-    // Get user by email
-    let user = match auth_service::get_user_by_email(&pool, &payload.email).await {
+    let user_id = current_user_id(&claims)?;
+    let user = match auth_service::get_user_by_id(&state.pool, user_id).await {
         Ok(user) => user,
+        Err(DieselError::NotFound) => return Err(account_not_found()),
         Err(_) => {
             return Err((
-                StatusCode::UNAUTHORIZED,
+                StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
-                    error: "invalid_credentials".to_string(),
-                    message: "Invalid email or password".to_string(),
+                    error: "database_error".to_string(),
+                    message: "Failed to fetch account".to_string(),
                 }),
             ));
         }
     };
 
-    // Verify password
-    match verify(&payload.password, &user.password_hash) {
-        Ok(true) => {
-            let token = generate_jwt(&user)?;
-            Ok(Json(AuthResponse {
-                token,
-                user: AuthUserResponse {
-                    id: user.id,
-                    name: user.name,
-                    email: user.email,
-                    created_at: user.created_at,
-                },
-            }))
+    let password_hash = Some(user.password_hash.as_str());
+    if !verify_password(&state.passwords, &payload.current_password, password_hash).await {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_password".to_string(),
+                message: "Current password is incorrect".to_string(),
+            }),
+        ));
+    }
+
+    let password_hash = hash_password(&state.passwords, &payload.new_password).await?;
+    match auth_service::set_password(&state.pool, user_id, &password_hash).await {
+        Ok(cutoff) => state.revocations.revoke_all(user_id, cutoff),
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "update_error".to_string(),
+                    message: "Failed to change password".to_string(),
+                }),
+            ));
         }
+    }
+
+    Ok(Json(issue_tokens(&state, user, Uuid::new_v4()).await?))
+}
+
+/// Emails a password reset link. Always answers `202` so the endpoint does not
+/// reveal which addresses have accounts.
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    Json(payload): Json<PasswordResetRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    let user = match auth_service::get_user_by_email(&state.pool, &payload.email).await {
+        Ok(user) => user,
+        Err(_) => return Ok(StatusCode::ACCEPTED),
+    };
+
+    let token = match auth_service::create_password_reset_token(&state.pool, user.id).await {
+        Ok(token) => token,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "token_error".to_string(),
+                    message: "Failed to create reset token".to_string(),
+                }),
+            ));
+        }
+    };
+
+    let reset_url = format!("{}/reset-password?token={}", state.config.app_url, token);
+    if let Err(err) = state.mailer.send(&Email::password_reset(&user.email, &reset_url)).await {
+        tracing::error!("Failed to send password reset email: {}", err);
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Sets a new password with a token from the reset email and logs the account
+/// out everywhere.
+pub async fn confirm_password_reset(
+    State(state): State<AppState>,
+    Json(payload): Json<PasswordResetConfirmRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    let password_hash = hash_password(&state.passwords, &payload.new_password).await?;
+    match auth_service::reset_password(&state.pool, &payload.token, &password_hash).await {
+        Ok(Some((user_id, cutoff))) => {
+            state.revocations.revoke_all(user_id, cutoff);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(None) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_token".to_string(),
+                message: "Invalid or expired reset token".to_string(),
+            }),
+        )),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "update_error".to_string(),
+                message: "Failed to reset password".to_string(),
+            }),
+        )),
+    }
+}
+
+/// The account the access token was issued to.
+pub async fn me(
+    State(pool): State<DbPool>,
+    claims: Claims,
+) -> Result<Json<AuthUserResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+
+    match auth_service::get_user_by_id(&pool, user_id).await {
+        Ok(user) => Ok(Json(user_response(user))),
+        Err(DieselError::NotFound) => Err(account_not_found()),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "database_error".to_string(),
+                message: "Failed to fetch account".to_string(),
+            }),
+        )),
+    }
+}
+
+/// Updates the caller's profile. Changing the email address resets its
+/// verification and sends a new link to the new address.
+pub async fn update_me(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(mut payload): Json<UpdateProfileRequest>,
+) -> Result<Json<AuthUserResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    let user_id = current_user_id(&claims)?;
+    let current = match auth_service::get_user_by_id(&state.pool, user_id).await {
+        Ok(user) => user,
+        Err(DieselError::NotFound) => return Err(account_not_found()),
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to fetch account".to_string(),
+                }),
+            ));
+        }
+    };
+    if payload.email.as_deref() == Some(current.email.as_str()) {
+        payload.email = None;
+    }
+
+    match auth_service::update_user(&state.pool, user_id, &payload).await {
+        Ok(user) => {
+            if payload.email.is_some() {
+                send_verification_email(&state, &user).await;
+            }
+            Ok(Json(user_response(user)))
+        }
+        Err(DieselError::NotFound) => Err(account_not_found()),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "user_exists".to_string(),
+                message: "User with this email already exists".to_string(),
+            }),
+        )),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "update_error".to_string(),
+                message: "Failed to update account".to_string(),
+            }),
+        )),
+    }
+}
+
+/// Deletes the caller's account along with all of its sessions.
+pub async fn delete_me(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+
+    match auth_service::delete_user(&state.pool, user_id).await {
+        Ok(cutoff) => {
+            state.revocations.revoke_all(user_id, cutoff);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(DieselError::NotFound) => Err(account_not_found()),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "delete_error".to_string(),
+                message: "Failed to delete account".to_string(),
+            }),
+        )),
+    }
+}
+
+/// Emails a verification link for the user's current address. Failures are
+/// only logged: the account exists either way and the link can be resent.
+async fn send_verification_email(state: &AppState, user: &AuthUser) {
+    let token = auth_service::create_email_verification_token(&state.pool, user.id, &user.email);
+    let token = match token.await {
+        Ok(token) => token,
+        Err(err) => {
+            tracing::error!("Failed to create email verification token: {}", err);
+            return;
+        }
+    };
+
+    let verify_url = format!("{}/verify-email?token={}", state.config.app_url, token);
+    let email = Email::email_verification(&user.email, &verify_url);
+    if let Err(err) = state.mailer.send(&email).await {
+        tracing::error!("Failed to send verification email: {}", err);
+    }
+}
+
+/// Changes another account's role. Admin only; that account's access tokens
+/// are revoked, and the tokens it gets from its next refresh carry the new role.
+pub async fn update_role(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateRoleRequest>,
+) -> Result<Json<AuthUserResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if current_user_id(&claims)? == id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "own_role".to_string(),
+                message: "You cannot change your own role".to_string(),
+            }),
+        ));
+    }
+
+    match auth_service::update_role(&state.pool, id, payload.role).await {
+        Ok(user) => {
+            if let Some(cutoff) = user.tokens_revoked_at {
+                state.revocations.revoke_all(user.id, cutoff);
+            }
+            Ok(Json(user_response(user)))
+        }
+        Err(DieselError::NotFound) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "not_found".to_string(),
+                message: "User not found".to_string(),
+            }),
+        )),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "update_error".to_string(),
+                message: "Failed to update role".to_string(),
+            }),
+        )),
+    }
+}
+
+/// Hashes on the blocking pool, since Argon2 takes tens of milliseconds of CPU.
+async fn hash_password(
+    passwords: &Arc<Passwords>,
+    password: &str,
+) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    let passwords = passwords.clone();
+    let password = password.to_string();
+    match tokio::task::spawn_blocking(move || passwords.hash(&password)).await {
+        Ok(Ok(password_hash)) => Ok(password_hash),
         _ => Err((
-            StatusCode::UNAUTHORIZED,
+            StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
-                error: "invalid_credentials".to_string(),
-                message: "Invalid email or password".to_string(),
+                error: "hash_error".to_string(),
+                message: "Failed to hash password".to_string(),
             }),
         )),
     }
 }
 
-fn generate_jwt(user: &AuthUser) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
-    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+/// Checks `password` against `password_hash` on the blocking pool. Without a
+/// hash (no such account) a dummy one is checked, which takes as long.
+async fn verify_password(
+    passwords: &Arc<Passwords>,
+    password: &str,
+    password_hash: Option<&str>,
+) -> bool {
+    let passwords = passwords.clone();
+    let password = password.to_string();
+    let password_hash = password_hash.map(str::to_string);
+    tokio::task::spawn_blocking(move || match password_hash {
+        Some(password_hash) => matches!(passwords.verify(&password, &password_hash), Ok(true)),
+        None => passwords.verify_dummy(&password),
+    })
+    .await
+    .unwrap_or(false)
+}
+
+pub(crate) fn current_user_id(claims: &Claims) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+    claims.sub.parse::<Uuid>().map_err(|_| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "unauthorized".to_string(),
+                message: "Invalid or expired token".to_string(),
+            }),
+        )
+    })
+}
+
+/// The token is valid but its account has since been deleted.
+pub(crate) fn account_not_found() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(ErrorResponse {
+            error: "unauthorized".to_string(),
+            message: "Account no longer exists".to_string(),
+        }),
+    )
+}
+
+/// Signs an access token for `user` belonging to the session `family_id`.
+fn generate_jwt(
+    keys: &Keyring,
+    user: &AuthUser,
+    family_id: Uuid,
+) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    sign_access_token(keys, &access_token_claims(user, family_id))
+}
+
+/// Claims of a full-access token for `user` in session `family_id`, valid for
+/// [`ACCESS_TOKEN_TTL_MINUTES`].
+pub(crate) fn access_token_claims(user: &AuthUser, family_id: Uuid) -> Claims {
     let now = Utc::now();
-    let exp = (now + chrono::Duration::hours(24)).timestamp() as usize;
+    let exp = (now + chrono::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).timestamp() as usize;
 
-    let claims = Claims {
+    Claims {
         sub: user.id.to_string(),
         email: user.email.clone(),
+        jti: Uuid::new_v4().to_string(),
+        sid: family_id.to_string(),
+        role: user.role,
         exp,
         iat: now.timestamp() as usize,
-    };
+        iat_micros: Some(now.timestamp_micros()),
+        scopes: None,
+        client_id: None,
+    }
+}
 
-    match encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(jwt_secret.as_bytes()),
-    ) {
+pub(crate) fn sign_access_token(
+    keys: &Keyring,
+    claims: &Claims,
+) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    match keys.sign(claims) {
         Ok(token) => Ok(token),
         Err(_) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    handlers::resource::{validate, ListResponse, Resource},
+    models::{Comment, CreateCommentRequest, ErrorResponse, UpdateCommentRequest, WithRelations},
+    query::{ListQuery, Relations},
+    services::comment_service,
+};
+
+const COMMENTS: Resource = Resource {
+    name: "Comment",
+    plural: "comments",
+    parent: "Post",
+};
+
+pub async fn get_comments(
+    State(pool): State<DbPool>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Comment>> {
+    COMMENTS.list(&params, comment_service::get_all_comments(&pool, &params).await)
+}
+
+pub async fn get_post_comments(
+    State(pool): State<DbPool>,
+    Path(post_id): Path<Uuid>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Comment>> {
+    let params = params.with_filter("post_id", post_id);
+    COMMENTS.list(&params, comment_service::get_all_comments(&pool, &params).await)
+}
+
+pub async fn get_comment(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    relations: Relations,
+) -> Result<Json<WithRelations<Comment>>, (StatusCode, Json<ErrorResponse>)> {
+    COMMENTS.found(comment_service::get_comment_with_relations(&pool, id, &relations).await)
+}
+
+pub async fn create_comment(
+    State(pool): State<DbPool>,
+    Json(payload): Json<CreateCommentRequest>,
+) -> Result<Json<Comment>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    COMMENTS.created(comment_service::create_comment(&pool, &payload).await)
+}
+
+pub async fn replace_comment(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<CreateCommentRequest>,
+) -> Result<Json<Comment>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    COMMENTS.updated(comment_service::replace_comment(&pool, id, &payload).await)
+}
+
+pub async fn update_comment(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateCommentRequest>,
+) -> Result<Json<Comment>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    COMMENTS.updated(comment_service::update_comment(&pool, id, &payload).await)
+}
+
+pub async fn delete_comment(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    COMMENTS.deleted(comment_service::delete_comment(&pool, id).await)
+}
@@ -0,0 +1,18 @@
+use axum::{
+    extract::State,
+    http::header::{HeaderValue, CACHE_CONTROL},
+    response::IntoResponse,
+    Json,
+};
+use std::sync::Arc;
+
+use crate::keyring::Keyring;
+
+/// Public keys that verify our access tokens, matched by the token's `kid`.
+/// Empty while tokens are signed with the HS256 secret.
+pub async fn jwks(State(keys): State<Arc<Keyring>>) -> impl IntoResponse {
+    (
+        [(CACHE_CONTROL, HeaderValue::from_static("public, max-age=300"))],
+        Json(keys.jwks()),
+    )
+}
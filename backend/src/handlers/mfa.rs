@@ -0,0 +1,312 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::{
+        account_not_found, current_user_id, discard_login_attempt, issue_tokens,
+        start_login_attempt, succeed_login_attempt,
+    },
+    middleware::client_ip::ClientIp,
+    mfa,
+    models::{
+        AuthResponse, Claims, ErrorResponse, MfaCodeRequest, MfaVerifyRequest,
+        RecoveryCodesResponse, TotpEnrollmentResponse,
+    },
+    services::{auth_service, mfa_service},
+    state::AppState,
+};
+
+/// Starts TOTP enrollment with a fresh secret. Logins are unaffected until the
+/// secret is confirmed with a code; calling this again replaces it.
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<TotpEnrollmentResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+    let user = match auth_service::get_user_by_id(&state.pool, user_id).await {
+        Ok(user) => user,
+        Err(diesel::result::Error::NotFound) => return Err(account_not_found()),
+        Err(_) => return Err(database_error("Failed to fetch account")),
+    };
+
+    let secret = mfa::generate_totp_secret();
+    let (secret_base32, otpauth_uri) =
+        mfa::totp_enrollment(&secret, &user.email).map_err(|_| mfa_error())?;
+    let secret_encrypted = state.mfa_cipher.encrypt(&secret).map_err(|_| mfa_error())?;
+
+    match mfa_service::start_totp_enrollment(&state.pool, user_id, &secret_encrypted).await {
+        Ok(true) => Ok(Json(TotpEnrollmentResponse {
+            secret: secret_base32,
+            otpauth_uri,
+        })),
+        Ok(false) => Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "mfa_already_enabled".to_string(),
+                message: "Two-factor authentication is already enabled".to_string(),
+            }),
+        )),
+        Err(_) => Err(database_error("Failed to store TOTP secret")),
+    }
+}
+
+/// Enables TOTP once the authenticator produces a valid code and returns the
+/// recovery codes. They are shown only this once.
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    let user_id = current_user_id(&claims)?;
+    let credential = match mfa_service::get_totp_credential(&state.pool, user_id).await {
+        Ok(Some(credential)) if credential.confirmed_at.is_none() => credential,
+        Ok(_) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "mfa_not_pending".to_string(),
+                    message: "Start TOTP enrollment before confirming it".to_string(),
+                }),
+            ));
+        }
+        Err(_) => return Err(database_error("Failed to fetch TOTP secret")),
+    };
+
+    let secret = state
+        .mfa_cipher
+        .decrypt(&credential.secret_encrypted)
+        .map_err(|_| mfa_error())?;
+    let Some(step) = mfa::verify_totp(&secret, &payload.code, Utc::now()) else {
+        return Err(invalid_code());
+    };
+
+    let recovery_codes = mfa::generate_recovery_codes();
+    let hashes = recovery_code_hashes(&recovery_codes);
+    match mfa_service::confirm_totp(&state.pool, user_id, step, &hashes).await {
+        Ok(true) => Ok(Json(RecoveryCodesResponse { recovery_codes })),
+        Ok(false) => Err(invalid_code()),
+        Err(_) => Err(database_error("Failed to enable two-factor authentication")),
+    }
+}
+
+/// Turns TOTP off. Takes a current TOTP or recovery code so a stolen access
+/// token alone cannot remove the second factor.
+pub async fn disable_totp(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    let user_id = current_user_id(&claims)?;
+    if !check_second_factor(&state, user_id, &payload.code).await? {
+        return Err(invalid_code());
+    }
+
+    match mfa_service::disable_totp(&state.pool, user_id).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(_) => Err(database_error("Failed to disable two-factor authentication")),
+    }
+}
+
+/// Replaces the recovery codes, e.g. after most have been used up.
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+
+    let user_id = current_user_id(&claims)?;
+    if !check_second_factor(&state, user_id, &payload.code).await? {
+        return Err(invalid_code());
+    }
+
+    let recovery_codes = mfa::generate_recovery_codes();
+    let hashes = recovery_code_hashes(&recovery_codes);
+    match mfa_service::replace_recovery_codes(&state.pool, user_id, &hashes).await {
+        Ok(()) => Ok(Json(RecoveryCodesResponse { recovery_codes })),
+        Err(_) => Err(database_error("Failed to store recovery codes")),
+    }
+}
+
+/// Second step of a login with TOTP enabled: trades the challenge token from
+/// `/auth/login` and a TOTP or recovery code for tokens. Failed codes count
+/// towards the login throttle like wrong passwords.
+pub async fn verify_mfa(
+    State(state): State<AppState>,
+    ClientIp(ip_address): ClientIp,
+    Json(payload): Json<MfaVerifyRequest>,
+) -> Result<Json<AuthResponse>, Response> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        )
+            .into_response());
+    }
+
+    let user_id = match mfa_service::find_mfa_challenge(&state.pool, &payload.mfa_token).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => return Err(invalid_challenge().into_response()),
+        Err(_) => {
+            return Err(database_error("Failed to check MFA challenge").into_response());
+        }
+    };
+    let user = match auth_service::get_user_by_id(&state.pool, user_id).await {
+        Ok(user) => user,
+        Err(_) => return Err(invalid_challenge().into_response()),
+    };
+
+    let attempt_id = start_login_attempt(&state, &user.email, Some(user.id), &ip_address).await?;
+
+    let verified = match check_second_factor(&state, user.id, &payload.code).await {
+        Ok(verified) => verified,
+        Err(err) => {
+            discard_login_attempt(&state, attempt_id).await;
+            return Err(err.into_response());
+        }
+    };
+    if !verified {
+        return Err(invalid_code().into_response());
+    }
+    succeed_login_attempt(&state, attempt_id).await;
+
+    match mfa_service::consume_mfa_challenge(&state.pool, &payload.mfa_token).await {
+        Ok(true) => {}
+        Ok(false) => return Err(invalid_challenge().into_response()),
+        Err(_) => {
+            return Err(database_error("Failed to check MFA challenge").into_response());
+        }
+    }
+
+    let response = issue_tokens(&state, user, Uuid::new_v4())
+        .await
+        .map_err(IntoResponse::into_response)?;
+    Ok(Json(response))
+}
+
+/// Checks a TOTP code, or failing the TOTP format a recovery code, against the
+/// user's enabled second factor. Accepted codes are used up.
+async fn check_second_factor(
+    state: &AppState,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, (StatusCode, Json<ErrorResponse>)> {
+    let credential = match mfa_service::get_totp_credential(&state.pool, user_id).await {
+        Ok(Some(credential)) if credential.confirmed_at.is_some() => credential,
+        Ok(_) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "mfa_not_enabled".to_string(),
+                    message: "Two-factor authentication is not enabled".to_string(),
+                }),
+            ));
+        }
+        Err(_) => return Err(database_error("Failed to fetch TOTP secret")),
+    };
+
+    let used = if mfa::is_totp_code(code) {
+        let secret = state
+            .mfa_cipher
+            .decrypt(&credential.secret_encrypted)
+            .map_err(|_| mfa_error())?;
+        match mfa::verify_totp(&secret, code, Utc::now()) {
+            Some(step) => mfa_service::use_totp_step(&state.pool, user_id, step).await,
+            None => Ok(false),
+        }
+    } else {
+        let code_hash = auth_service::hash_token(&mfa::normalize_recovery_code(code));
+        mfa_service::use_recovery_code(&state.pool, user_id, &code_hash).await
+    };
+
+    used.map_err(|_| database_error("Failed to check code"))
+}
+
+fn recovery_code_hashes(codes: &[String]) -> Vec<String> {
+    codes
+        .iter()
+        .map(|code| auth_service::hash_token(&mfa::normalize_recovery_code(code)))
+        .collect()
+}
+
+fn invalid_code() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(ErrorResponse {
+            error: "invalid_code".to_string(),
+            message: "Invalid or already used code".to_string(),
+        }),
+    )
+}
+
+fn invalid_challenge() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(ErrorResponse {
+            error: "invalid_token".to_string(),
+            message: "Invalid or expired MFA token, log in again".to_string(),
+        }),
+    )
+}
+
+fn mfa_error() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: "mfa_error".to_string(),
+            message: "Failed to process the TOTP secret".to_string(),
+        }),
+    )
+}
+
+fn database_error(message: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: "database_error".to_string(),
+            message: message.to_string(),
+        }),
+    )
+}
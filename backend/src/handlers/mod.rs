@@ -1,3 +1,13 @@
+pub mod albums;
+pub mod api_keys;
 pub mod auth;
+pub mod comments;
 pub mod health;
-pub mod users; 
\ No newline at end of file
+pub mod jwks;
+pub mod mfa;
+pub mod oauth;
+pub mod photos;
+pub mod posts;
+mod resource;
+pub mod todos;
+pub mod users;
@@ -0,0 +1,654 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{
+        header::{AUTHORIZATION, CACHE_CONTROL},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use diesel::result::Error as DieselError;
+use serde::Serialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    database::DbPool,
+    handlers::auth::{access_token_claims, current_user_id, sign_access_token},
+    models::{
+        AuthUser, AuthorizationPromptResponse, AuthorizeDecisionRequest, AuthorizeRedirectResponse,
+        AuthorizeRequest, Claims, CreateOAuthClientRequest, CreatedOAuthClientResponse,
+        ErrorResponse, IntrospectionRequest, IntrospectionResponse, OAuthClient,
+        OAuthClientResponse, OAuthConsentResponse, OAuthErrorResponse, Scope, TokenRequest,
+        TokenResponse,
+    },
+    oauth::{
+        basic_credentials, format_scopes, is_code_challenge, is_valid_redirect_uri,
+        redirect_with, requested_scopes, verify_code_challenge,
+    },
+    services::{
+        auth_service::{self, token_matches, RefreshOutcome, ACCESS_TOKEN_TTL_MINUTES},
+        oauth_service::{self, AuthorizationCodeOutcome},
+    },
+    state::AppState,
+};
+
+/// Registers a client. The response is the only time a confidential client's
+/// secret is shown.
+pub async fn create_oauth_client(
+    State(pool): State<DbPool>,
+    claims: Claims,
+    Json(payload): Json<CreateOAuthClientRequest>,
+) -> Result<Json<CreatedOAuthClientResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Validation failed: {:?}", errors),
+            }),
+        ));
+    }
+    if let Some(uri) = payload.redirect_uris.iter().find(|uri| !is_valid_redirect_uri(uri)) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: format!("Invalid redirect URI: {}", uri),
+            }),
+        ));
+    }
+    if payload.public && payload.redirect_uris.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "validation_error".to_string(),
+                message: "Public clients need at least one redirect URI".to_string(),
+            }),
+        ));
+    }
+
+    let owner_id = current_user_id(&claims)?;
+    let mut scopes = payload.scopes;
+    scopes.sort_by_key(|scope| scope.as_str());
+    scopes.dedup();
+    let mut redirect_uris = payload.redirect_uris;
+    redirect_uris.dedup();
+
+    match oauth_service::create_client(
+        &pool,
+        owner_id,
+        &payload.name,
+        redirect_uris,
+        scopes,
+        payload.public,
+    )
+    .await
+    {
+        Ok((client, client_secret)) => Ok(Json(CreatedOAuthClientResponse {
+            client: client.into(),
+            client_secret,
+        })),
+        Err(_) => Err(database_error("Failed to create OAuth client")),
+    }
+}
+
+pub async fn get_oauth_clients(
+    State(pool): State<DbPool>,
+) -> Result<Json<Vec<OAuthClientResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    match oauth_service::get_clients(&pool).await {
+        Ok(clients) => Ok(Json(clients.into_iter().map(OAuthClientResponse::from).collect())),
+        Err(_) => Err(database_error("Failed to fetch OAuth clients")),
+    }
+}
+
+/// Deletes a client; its refresh tokens stop working immediately, access
+/// tokens already issued to it when they expire.
+pub async fn delete_oauth_client(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    match oauth_service::delete_client(&pool, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "not_found".to_string(),
+                message: "OAuth client not found".to_string(),
+            }),
+        )),
+        Err(_) => Err(database_error("Failed to delete OAuth client")),
+    }
+}
+
+/// Checks an authorization request for the consent screen and says whether
+/// the user still has to approve it.
+pub async fn authorize(
+    State(pool): State<DbPool>,
+    claims: Claims,
+    Query(request): Query<AuthorizeRequest>,
+) -> Result<Json<AuthorizationPromptResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+    let (client, scopes) = check_authorize_request(&pool, &request).await?;
+
+    let consent = match oauth_service::get_consent(&pool, user_id, client.id).await {
+        Ok(consent) => consent,
+        Err(_) => return Err(database_error("Failed to fetch consent")),
+    };
+    let consent_required = consent
+        .is_none_or(|consent| !scopes.iter().all(|scope| consent.scopes.contains(scope)));
+
+    Ok(Json(AuthorizationPromptResponse {
+        client_id: client.id,
+        client_name: client.name,
+        scopes,
+        consent_required,
+    }))
+}
+
+/// Records the user's decision on an authorization request and returns where
+/// to redirect: with an authorization code if approved, `access_denied`
+/// otherwise.
+pub async fn authorize_decision(
+    State(pool): State<DbPool>,
+    claims: Claims,
+    Json(payload): Json<AuthorizeDecisionRequest>,
+) -> Result<Json<AuthorizeRedirectResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+    let request = payload.request;
+    let (client, scopes) = check_authorize_request(&pool, &request).await?;
+
+    let code = if payload.approve {
+        if oauth_service::grant_consent(&pool, user_id, client.id, &scopes).await.is_err() {
+            return Err(database_error("Failed to record consent"));
+        }
+        match oauth_service::create_authorization_code(
+            &pool,
+            client.id,
+            user_id,
+            &request.redirect_uri,
+            scopes,
+            request.code_challenge.as_deref().unwrap_or_default(),
+        )
+        .await
+        {
+            Ok(code) => Some(code),
+            Err(_) => return Err(database_error("Failed to create authorization code")),
+        }
+    } else {
+        None
+    };
+
+    let mut params = vec![match &code {
+        Some(code) => ("code", code.as_str()),
+        None => ("error", "access_denied"),
+    }];
+    if let Some(state) = &request.state {
+        params.push(("state", state.as_str()));
+    }
+
+    match redirect_with(&request.redirect_uri, &params) {
+        Some(redirect_to) => Ok(Json(AuthorizeRedirectResponse { redirect_to })),
+        None => Err(authorize_error("invalid_redirect_uri", "Invalid redirect URI")),
+    }
+}
+
+/// The apps the current user has allowed to act for them.
+pub async fn get_oauth_consents(
+    State(pool): State<DbPool>,
+    claims: Claims,
+) -> Result<Json<Vec<OAuthConsentResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+
+    match oauth_service::get_consents(&pool, user_id).await {
+        Ok(consents) => Ok(Json(
+            consents
+                .into_iter()
+                .map(|(consent, client_name)| OAuthConsentResponse {
+                    client_id: consent.client_id,
+                    client_name,
+                    scopes: consent.scopes,
+                    created_at: consent.created_at,
+                    updated_at: consent.updated_at,
+                })
+                .collect(),
+        )),
+        Err(_) => Err(database_error("Failed to fetch consents")),
+    }
+}
+
+/// Withdraws access from an app: its refresh tokens for the current user stop
+/// working and it has to ask for consent again.
+pub async fn delete_oauth_consent(
+    State(pool): State<DbPool>,
+    claims: Claims,
+    Path(client_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = current_user_id(&claims)?;
+
+    match oauth_service::revoke_consent(&pool, user_id, client_id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "not_found".to_string(),
+                message: "Consent not found".to_string(),
+            }),
+        )),
+        Err(_) => Err(database_error("Failed to revoke consent")),
+    }
+}
+
+/// The token endpoint (RFC 6749 section 3.2) for the `authorization_code`,
+/// `client_credentials` and `refresh_token` grants.
+pub async fn token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(payload): Form<TokenRequest>,
+) -> Result<Response, Response> {
+    let client = authenticate_client(
+        &state.pool,
+        &headers,
+        payload.client_id.as_deref(),
+        payload.client_secret.as_deref(),
+    )
+    .await?;
+
+    let response = match payload.grant_type.as_str() {
+        "authorization_code" => authorization_code_grant(&state, &client, &payload).await?,
+        "client_credentials" => client_credentials_grant(&state, &client, &payload).await?,
+        "refresh_token" => refresh_token_grant(&state, &client, &payload).await?,
+        _ => {
+            return Err(oauth_error(
+                StatusCode::BAD_REQUEST,
+                "unsupported_grant_type",
+                "grant_type must be authorization_code, client_credentials or refresh_token",
+            ));
+        }
+    };
+
+    Ok(no_store(response))
+}
+
+/// Token introspection (RFC 7662) for resource servers, which authenticate as
+/// confidential clients. Access tokens of any client are described; refresh
+/// tokens only to the client holding them.
+pub async fn introspect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(payload): Form<IntrospectionRequest>,
+) -> Result<Response, Response> {
+    let client = authenticate_client(
+        &state.pool,
+        &headers,
+        payload.client_id.as_deref(),
+        payload.client_secret.as_deref(),
+    )
+    .await?;
+    if client.client_secret_hash.is_none() {
+        return Err(oauth_error(
+            StatusCode::UNAUTHORIZED,
+            "invalid_client",
+            "Public clients cannot introspect tokens",
+        ));
+    }
+
+    if let Ok(claims) = state.keys.verify::<Claims>(&payload.token) {
+        if state.revocations.is_revoked(&claims) {
+            return Ok(no_store(IntrospectionResponse::default()));
+        }
+        return Ok(no_store(IntrospectionResponse {
+            active: true,
+            scope: claims.scopes.as_deref().map(format_scopes),
+            client_id: claims.client_id,
+            username: Some(claims.email),
+            token_type: Some("Bearer".to_string()),
+            exp: Some(claims.exp as i64),
+            iat: Some(claims.iat as i64),
+            sub: Some(claims.sub),
+            jti: Some(claims.jti),
+        }));
+    }
+
+    let refresh_token = match oauth_service::find_refresh_token(&state.pool, &payload.token).await
+    {
+        Ok(Some(refresh_token)) if refresh_token.client_id == Some(client.id) => refresh_token,
+        Ok(_) => return Ok(no_store(IntrospectionResponse::default())),
+        Err(_) => return Err(server_error()),
+    };
+    let username = auth_service::get_user_by_id(&state.pool, refresh_token.user_id)
+        .await
+        .ok()
+        .map(|user| user.email);
+
+    Ok(no_store(IntrospectionResponse {
+        active: true,
+        scope: refresh_token.scopes.as_deref().map(format_scopes),
+        client_id: Some(client.id.to_string()),
+        username,
+        token_type: None,
+        exp: Some(refresh_token.expires_at.timestamp()),
+        iat: Some(refresh_token.created_at.timestamp()),
+        sub: Some(refresh_token.user_id.to_string()),
+        jti: None,
+    }))
+}
+
+/// Redeems an authorization code. The code is used up even if the rest of
+/// the request is wrong; redeeming it again revokes the tokens issued from it
+/// (RFC 6749 section 4.1.2).
+async fn authorization_code_grant(
+    state: &AppState,
+    client: &OAuthClient,
+    payload: &TokenRequest,
+) -> Result<TokenResponse, Response> {
+    let (Some(code), Some(redirect_uri), Some(code_verifier)) = (
+        payload.code.as_deref(),
+        payload.redirect_uri.as_deref(),
+        payload.code_verifier.as_deref(),
+    ) else {
+        return Err(oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "code, redirect_uri and code_verifier are required",
+        ));
+    };
+
+    let code = match oauth_service::consume_authorization_code(&state.pool, code).await {
+        Ok(AuthorizationCodeOutcome::Consumed(code))
+            if code.client_id == client.id
+                && code.redirect_uri == redirect_uri
+                && verify_code_challenge(code_verifier, &code.code_challenge) =>
+        {
+            code
+        }
+        Ok(AuthorizationCodeOutcome::Reused { family_id }) => {
+            tracing::warn!("Authorization code reuse detected; tokens issued from it revoked");
+            state.revocations.revoke_family(family_id);
+            return Err(invalid_grant("Authorization code was already used"));
+        }
+        Ok(_) => return Err(invalid_grant("Invalid or expired authorization code")),
+        Err(_) => return Err(server_error()),
+    };
+    let user = grant_user(state, code.user_id).await?;
+
+    let access_token = client_access_token(state, &user, code.family_id, client, &code.scopes)
+        .ok_or_else(server_error)?;
+    let refresh_token = match oauth_service::create_refresh_token(
+        &state.pool,
+        user.id,
+        code.family_id,
+        client.id,
+        code.scopes.clone(),
+    )
+    .await
+    {
+        Ok(refresh_token) => refresh_token,
+        Err(_) => return Err(server_error()),
+    };
+
+    Ok(token_response(access_token, Some(refresh_token), &code.scopes))
+}
+
+/// Issues a token acting as the client's owner. Only confidential clients
+/// can use it, and no refresh token is issued.
+async fn client_credentials_grant(
+    state: &AppState,
+    client: &OAuthClient,
+    payload: &TokenRequest,
+) -> Result<TokenResponse, Response> {
+    if client.client_secret_hash.is_none() {
+        return Err(oauth_error(
+            StatusCode::BAD_REQUEST,
+            "unauthorized_client",
+            "Public clients cannot use the client_credentials grant",
+        ));
+    }
+    let Some(scopes) = requested_scopes(payload.scope.as_deref(), &client.scopes) else {
+        return Err(invalid_scope());
+    };
+    let user = grant_user(state, client.owner_id).await?;
+
+    let access_token = client_access_token(state, &user, Uuid::new_v4(), client, &scopes)
+        .ok_or_else(server_error)?;
+    Ok(token_response(access_token, None, &scopes))
+}
+
+/// Rotates one of the client's refresh tokens. `scope` may narrow the new
+/// access token; the refresh token keeps the scopes originally granted.
+async fn refresh_token_grant(
+    state: &AppState,
+    client: &OAuthClient,
+    payload: &TokenRequest,
+) -> Result<TokenResponse, Response> {
+    let Some(token) = payload.refresh_token.as_deref() else {
+        return Err(oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "refresh_token is required",
+        ));
+    };
+
+    // Check a narrowed scope before the token is used up
+    if payload.scope.is_some() {
+        match oauth_service::find_refresh_token(&state.pool, token).await {
+            Ok(Some(current)) if current.client_id == Some(client.id) => {
+                let granted = current.scopes.unwrap_or_default();
+                if requested_scopes(payload.scope.as_deref(), &granted).is_none() {
+                    return Err(invalid_scope());
+                }
+            }
+            Ok(_) => {}
+            Err(_) => return Err(server_error()),
+        }
+    }
+
+    let (user_id, family_id, refresh_token, granted) =
+        match auth_service::rotate_refresh_token(&state.pool, token, Some(client.id)).await {
+            Ok(RefreshOutcome::Rotated {
+                user_id,
+                family_id,
+                refresh_token,
+                scopes,
+            }) => (user_id, family_id, refresh_token, scopes.unwrap_or_default()),
+            Ok(RefreshOutcome::Reused { family_id }) => {
+                tracing::warn!("OAuth refresh token reuse detected; token family revoked");
+                state.revocations.revoke_family(family_id);
+                return Err(invalid_grant("Refresh token was already used"));
+            }
+            Ok(RefreshOutcome::Invalid) => {
+                return Err(invalid_grant("Invalid or expired refresh token"));
+            }
+            Err(_) => return Err(server_error()),
+        };
+
+    // Scopes the client has since lost are no longer granted
+    let allowed: Vec<Scope> =
+        granted.into_iter().filter(|scope| client.scopes.contains(scope)).collect();
+    let Some(scopes) = requested_scopes(payload.scope.as_deref(), &allowed) else {
+        return Err(invalid_scope());
+    };
+    let user = grant_user(state, user_id).await?;
+
+    let access_token = client_access_token(state, &user, family_id, client, &scopes)
+        .ok_or_else(server_error)?;
+    Ok(token_response(access_token, Some(refresh_token), &scopes))
+}
+
+/// The client making a token or introspection request, from HTTP Basic or
+/// the form body. Confidential clients must present their secret.
+async fn authenticate_client(
+    pool: &DbPool,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<OAuthClient, Response> {
+    let invalid_client = || {
+        oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", "Client authentication failed")
+    };
+
+    let basic = match headers.get(AUTHORIZATION) {
+        Some(header) => match header.to_str().ok().and_then(basic_credentials) {
+            Some(credentials) => Some(credentials),
+            None => return Err(invalid_client()),
+        },
+        None => None,
+    };
+    let (client_id, client_secret) = match &basic {
+        Some((id, secret)) => (Some(id.as_str()), Some(secret.as_str())),
+        None => (client_id, client_secret),
+    };
+
+    let Some(client_id) = client_id.and_then(|client_id| client_id.parse::<Uuid>().ok()) else {
+        return Err(invalid_client());
+    };
+    let client = match oauth_service::get_client(pool, client_id).await {
+        Ok(Some(client)) => client,
+        Ok(None) => return Err(invalid_client()),
+        Err(_) => return Err(server_error()),
+    };
+
+    let authenticated = match (&client.client_secret_hash, client_secret) {
+        (Some(secret_hash), Some(secret)) => token_matches(secret, secret_hash),
+        (None, None) => true,
+        _ => false,
+    };
+    if !authenticated {
+        return Err(invalid_client());
+    }
+
+    Ok(client)
+}
+
+/// Validates an authorization request against the client's registration and
+/// returns the client with the scopes requested.
+async fn check_authorize_request(
+    pool: &DbPool,
+    request: &AuthorizeRequest,
+) -> Result<(OAuthClient, Vec<Scope>), (StatusCode, Json<ErrorResponse>)> {
+    let client = match request.client_id.parse::<Uuid>() {
+        Ok(client_id) => match oauth_service::get_client(pool, client_id).await {
+            Ok(client) => client,
+            Err(_) => return Err(database_error("Failed to fetch OAuth client")),
+        },
+        Err(_) => None,
+    };
+    let Some(client) = client else {
+        return Err(authorize_error("invalid_client", "Unknown client"));
+    };
+
+    if !client.redirect_uris.contains(&request.redirect_uri) {
+        return Err(authorize_error(
+            "invalid_redirect_uri",
+            "redirect_uri is not registered for this client",
+        ));
+    }
+    if request.response_type != "code" {
+        return Err(authorize_error("unsupported_response_type", "response_type must be code"));
+    }
+    let pkce = request.code_challenge_method.as_deref() == Some("S256")
+        && request.code_challenge.as_deref().is_some_and(is_code_challenge);
+    if !pkce {
+        return Err(authorize_error("invalid_request", "PKCE with the S256 method is required"));
+    }
+    let Some(scopes) = requested_scopes(request.scope.as_deref(), &client.scopes) else {
+        return Err(authorize_error(
+            "invalid_scope",
+            "Requested scopes are invalid or not allowed for this client",
+        ));
+    };
+
+    Ok((client, scopes))
+}
+
+/// The account a grant acts for; deleted accounts make the grant invalid.
+async fn grant_user(state: &AppState, user_id: Uuid) -> Result<AuthUser, Response> {
+    match auth_service::get_user_by_id(&state.pool, user_id).await {
+        Ok(user) => Ok(user),
+        Err(DieselError::NotFound) => Err(invalid_grant("Account no longer exists")),
+        Err(_) => Err(server_error()),
+    }
+}
+
+/// Signs an access token for `user` that only carries `scopes` and names the
+/// client it was issued to, or `None` if signing fails.
+fn client_access_token(
+    state: &AppState,
+    user: &AuthUser,
+    family_id: Uuid,
+    client: &OAuthClient,
+    scopes: &[Scope],
+) -> Option<String> {
+    let claims = Claims {
+        scopes: Some(scopes.to_vec()),
+        client_id: Some(client.id.to_string()),
+        ..access_token_claims(user, family_id)
+    };
+
+    sign_access_token(&state.keys, &claims).ok()
+}
+
+fn token_response(
+    access_token: String,
+    refresh_token: Option<String>,
+    scopes: &[Scope],
+) -> TokenResponse {
+    TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+        refresh_token,
+        scope: format_scopes(scopes),
+    }
+}
+
+/// Token endpoint responses must not be cached (RFC 6749 section 5.1).
+fn no_store<T: Serialize>(body: T) -> Response {
+    ([(CACHE_CONTROL, "no-store")], Json(body)).into_response()
+}
+
+fn oauth_error(status: StatusCode, error: &str, description: &str) -> Response {
+    let body = OAuthErrorResponse {
+        error: error.to_string(),
+        error_description: description.to_string(),
+    };
+
+    (status, [(CACHE_CONTROL, "no-store")], Json(body)).into_response()
+}
+
+fn invalid_grant(description: &str) -> Response {
+    oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", description)
+}
+
+fn invalid_scope() -> Response {
+    oauth_error(
+        StatusCode::BAD_REQUEST,
+        "invalid_scope",
+        "Requested scopes are invalid or not granted",
+    )
+}
+
+fn server_error() -> Response {
+    oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error", "Failed to process request")
+}
+
+fn authorize_error(error: &str, message: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            message: message.to_string(),
+        }),
+    )
+}
+
+fn database_error(message: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: "database_error".to_string(),
+            message: message.to_string(),
+        }),
+    )
+}
@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    handlers::resource::{validate, ListResponse, Resource},
+    models::{CreatePhotoRequest, ErrorResponse, Photo, UpdatePhotoRequest, WithRelations},
+    query::{ListQuery, Relations},
+    services::photo_service,
+};
+
+const PHOTOS: Resource = Resource {
+    name: "Photo",
+    plural: "photos",
+    parent: "Album",
+};
+
+pub async fn get_photos(
+    State(pool): State<DbPool>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Photo>> {
+    PHOTOS.list(&params, photo_service::get_all_photos(&pool, &params).await)
+}
+
+pub async fn get_album_photos(
+    State(pool): State<DbPool>,
+    Path(album_id): Path<Uuid>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Photo>> {
+    let params = params.with_filter("album_id", album_id);
+    PHOTOS.list(&params, photo_service::get_all_photos(&pool, &params).await)
+}
+
+pub async fn get_photo(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    relations: Relations,
+) -> Result<Json<WithRelations<Photo>>, (StatusCode, Json<ErrorResponse>)> {
+    PHOTOS.found(photo_service::get_photo_with_relations(&pool, id, &relations).await)
+}
+
+pub async fn create_photo(
+    State(pool): State<DbPool>,
+    Json(payload): Json<CreatePhotoRequest>,
+) -> Result<Json<Photo>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    PHOTOS.created(photo_service::create_photo(&pool, &payload).await)
+}
+
+pub async fn replace_photo(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<CreatePhotoRequest>,
+) -> Result<Json<Photo>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    PHOTOS.updated(photo_service::replace_photo(&pool, id, &payload).await)
+}
+
+pub async fn update_photo(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdatePhotoRequest>,
+) -> Result<Json<Photo>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    PHOTOS.updated(photo_service::update_photo(&pool, id, &payload).await)
+}
+
+pub async fn delete_photo(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    PHOTOS.deleted(photo_service::delete_photo(&pool, id).await)
+}
@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    handlers::resource::{validate, ListResponse, Resource},
+    models::{CreatePostRequest, ErrorResponse, Post, UpdatePostRequest, WithRelations},
+    query::{ListQuery, Relations},
+    services::post_service,
+};
+
+const POSTS: Resource = Resource {
+    name: "Post",
+    plural: "posts",
+    parent: "User",
+};
+
+pub async fn get_posts(
+    State(pool): State<DbPool>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Post>> {
+    POSTS.list(&params, post_service::get_all_posts(&pool, &params).await)
+}
+
+pub async fn get_user_posts(
+    State(pool): State<DbPool>,
+    Path(user_id): Path<Uuid>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Post>> {
+    let params = params.with_filter("user_id", user_id);
+    POSTS.list(&params, post_service::get_all_posts(&pool, &params).await)
+}
+
+pub async fn get_post(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    relations: Relations,
+) -> Result<Json<WithRelations<Post>>, (StatusCode, Json<ErrorResponse>)> {
+    POSTS.found(post_service::get_post_with_relations(&pool, id, &relations).await)
+}
+
+pub async fn create_post(
+    State(pool): State<DbPool>,
+    Json(payload): Json<CreatePostRequest>,
+) -> Result<Json<Post>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    POSTS.created(post_service::create_post(&pool, &payload).await)
+}
+
+pub async fn replace_post(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<CreatePostRequest>,
+) -> Result<Json<Post>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    POSTS.updated(post_service::replace_post(&pool, id, &payload).await)
+}
+
+pub async fn update_post(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdatePostRequest>,
+) -> Result<Json<Post>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    POSTS.updated(post_service::update_post(&pool, id, &payload).await)
+}
+
+pub async fn delete_post(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    POSTS.deleted(post_service::delete_post(&pool, id).await)
+}
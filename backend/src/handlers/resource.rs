@@ -0,0 +1,134 @@
+//! Responses shared by the json-server style resource handlers: posts,
+//! comments, albums, photos and todos.
+
+use axum::{
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use validator::Validate;
+
+use crate::{
+    models::ErrorResponse,
+    query::{ListError, ListPage, ListQuery},
+};
+
+/// A page of a list with its pagination headers, or an error response.
+pub(crate) type ListResponse<T> =
+    Result<(HeaderMap, Json<Vec<T>>), (StatusCode, Json<ErrorResponse>)>;
+
+/// How a resource is named in its error messages.
+pub(crate) struct Resource {
+    /// `"Post"`
+    pub name: &'static str,
+    /// `"posts"`
+    pub plural: &'static str,
+    /// The resource its foreign key points to, e.g. `"User"` for posts.
+    pub parent: &'static str,
+}
+
+impl Resource {
+    pub(crate) fn list<T>(
+        &self,
+        params: &ListQuery,
+        result: Result<ListPage<T>, ListError>,
+    ) -> ListResponse<T> {
+        match result {
+            Ok(page) => Ok((params.response_headers(page.total_count), Json(page.items))),
+            Err(ListError::Query(err)) => Err(invalid_query(err.to_string())),
+            Err(ListError::Database(_)) => Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "database_error",
+                format!("Failed to fetch {}", self.plural),
+            )),
+        }
+    }
+
+    pub(crate) fn found<T>(
+        &self,
+        result: Result<T, ListError>,
+    ) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)> {
+        match result {
+            Ok(item) => Ok(Json(item)),
+            Err(ListError::Query(err)) => Err(invalid_query(err.to_string())),
+            Err(_) => Err(self.not_found(format!("{} not found", self.name))),
+        }
+    }
+
+    pub(crate) fn created<T>(
+        &self,
+        result: Result<T, DieselError>,
+    ) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)> {
+        match result {
+            Ok(item) => Ok(Json(item)),
+            Err(DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)) => {
+                Err(self.invalid_reference())
+            }
+            Err(_) => Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "create_error",
+                format!("Failed to create {}", self.name.to_lowercase()),
+            )),
+        }
+    }
+
+    /// Response to `PUT` and `PATCH`.
+    pub(crate) fn updated<T>(
+        &self,
+        result: Result<T, DieselError>,
+    ) -> Result<Json<T>, (StatusCode, Json<ErrorResponse>)> {
+        match result {
+            Ok(item) => Ok(Json(item)),
+            Err(DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)) => {
+                Err(self.invalid_reference())
+            }
+            Err(_) => Err(self.not_found(format!("{} not found or update failed", self.name))),
+        }
+    }
+
+    pub(crate) fn deleted(
+        &self,
+        result: Result<(), DieselError>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        match result {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(_) => Err(self.not_found(format!("{} not found", self.name))),
+        }
+    }
+
+    fn not_found(&self, message: String) -> (StatusCode, Json<ErrorResponse>) {
+        error(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    fn invalid_reference(&self) -> (StatusCode, Json<ErrorResponse>) {
+        error(
+            StatusCode::BAD_REQUEST,
+            "invalid_reference",
+            format!("{} does not exist", self.parent),
+        )
+    }
+}
+
+pub(crate) fn validate<T: Validate>(payload: &T) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    payload.validate().map_err(|errors| {
+        error(
+            StatusCode::BAD_REQUEST,
+            "validation_error",
+            format!("Validation failed: {:?}", errors),
+        )
+    })
+}
+
+fn invalid_query(message: String) -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::BAD_REQUEST, "invalid_query", message)
+}
+
+fn error(status: StatusCode, error: &str, message: String) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            message,
+        }),
+    )
+}
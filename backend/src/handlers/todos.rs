@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    handlers::resource::{validate, ListResponse, Resource},
+    models::{CreateTodoRequest, ErrorResponse, Todo, UpdateTodoRequest, WithRelations},
+    query::{ListQuery, Relations},
+    services::todo_service,
+};
+
+const TODOS: Resource = Resource {
+    name: "Todo",
+    plural: "todos",
+    parent: "User",
+};
+
+pub async fn get_todos(
+    State(pool): State<DbPool>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Todo>> {
+    TODOS.list(&params, todo_service::get_all_todos(&pool, &params).await)
+}
+
+pub async fn get_user_todos(
+    State(pool): State<DbPool>,
+    Path(user_id): Path<Uuid>,
+    params: ListQuery,
+) -> ListResponse<WithRelations<Todo>> {
+    let params = params.with_filter("user_id", user_id);
+    TODOS.list(&params, todo_service::get_all_todos(&pool, &params).await)
+}
+
+pub async fn get_todo(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    relations: Relations,
+) -> Result<Json<WithRelations<Todo>>, (StatusCode, Json<ErrorResponse>)> {
+    TODOS.found(todo_service::get_todo_with_relations(&pool, id, &relations).await)
+}
+
+pub async fn create_todo(
+    State(pool): State<DbPool>,
+    Json(payload): Json<CreateTodoRequest>,
+) -> Result<Json<Todo>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    TODOS.created(todo_service::create_todo(&pool, &payload).await)
+}
+
+pub async fn replace_todo(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<CreateTodoRequest>,
+) -> Result<Json<Todo>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    TODOS.updated(todo_service::replace_todo(&pool, id, &payload).await)
+}
+
+pub async fn update_todo(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateTodoRequest>,
+) -> Result<Json<Todo>, (StatusCode, Json<ErrorResponse>)> {
+    // Validate input
+    validate(&payload)?;
+
+    TODOS.updated(todo_service::update_todo(&pool, id, &payload).await)
+}
+
+pub async fn delete_todo(
+    State(pool): State<DbPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    TODOS.deleted(todo_service::delete_todo(&pool, id).await)
+}
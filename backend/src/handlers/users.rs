@@ -1,23 +1,42 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     Json,
 };
+use diesel::result::Error as DieselError;
+use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
     database::DbPool,
-    models::{CreateUserRequest, ErrorResponse, UpdateUserRequest, User},
+    models::{
+        Claims, CreateUserRequest, ErrorResponse, Permission, UpdateUserRequest, User,
+        WithRelations,
+    },
+    query::{CursorKey, ListError, ListQuery, Relations},
     services::user_service,
 };
 
 pub async fn get_users(
     State(pool): State<DbPool>,
-) -> Result<Json<Vec<User>>, (StatusCode, Json<ErrorResponse>)> {
-    match user_service::get_all_users(&pool).await {
-        Ok(users) => Ok(Json(users)),
-        Err(_) => Err((
+    State(cursor_key): State<Arc<CursorKey>>,
+    params: ListQuery,
+) -> Result<(HeaderMap, Json<Vec<WithRelations<User>>>), (StatusCode, Json<ErrorResponse>)> {
+    match user_service::get_all_users(&pool, &params, &cursor_key).await {
+        Ok(page) => {
+            let mut headers = params.response_headers(page.total_count);
+            headers.extend(params.cursor_headers(page.next_cursor.as_deref()));
+            Ok((headers, Json(page.items)))
+        }
+        Err(ListError::Query(err)) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_query".to_string(),
+                message: err.to_string(),
+            }),
+        )),
+        Err(ListError::Database(_)) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: "database_error".to_string(),
@@ -30,9 +49,17 @@ pub async fn get_users(
 pub async fn get_user(
     State(pool): State<DbPool>,
     Path(id): Path<Uuid>,
-) -> Result<Json<User>, (StatusCode, Json<ErrorResponse>)> {
-    match user_service::get_user_by_id(&pool, id).await {
+    relations: Relations,
+) -> Result<Json<WithRelations<User>>, (StatusCode, Json<ErrorResponse>)> {
+    match user_service::get_user_with_relations(&pool, id, &relations).await {
         Ok(user) => Ok(Json(user)),
+        Err(ListError::Query(err)) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_query".to_string(),
+                message: err.to_string(),
+            }),
+        )),
         Err(_) => Err((
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
@@ -43,8 +70,10 @@ pub async fn get_user(
     }
 }
 
+/// Creates a user owned by the calling account.
 pub async fn create_user(
     State(pool): State<DbPool>,
+    claims: Claims,
     Json(payload): Json<CreateUserRequest>,
 ) -> Result<Json<User>, (StatusCode, Json<ErrorResponse>)> {
     // Validate input
@@ -58,7 +87,9 @@ pub async fn create_user(
         ));
     }
 
-    match user_service::create_user(&pool, &payload).await {
+    let owner_id = caller_id(&claims)?;
+
+    match user_service::create_user(&pool, &payload, owner_id).await {
         Ok(user) => Ok(Json(user)),
         Err(_) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -70,8 +101,10 @@ pub async fn create_user(
     }
 }
 
+/// Replaces a user. Only its owner or an admin may do this.
 pub async fn update_user(
     State(pool): State<DbPool>,
+    claims: Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateUserRequest>,
 ) -> Result<Json<User>, (StatusCode, Json<ErrorResponse>)> {
@@ -86,6 +119,8 @@ pub async fn update_user(
         ));
     }
 
+    authorize_owner(&pool, &claims, id).await?;
+
     match user_service::update_user(&pool, id, &payload).await {
         Ok(user) => Ok(Json(user)),
         Err(_) => Err((
@@ -112,4 +147,61 @@ pub async fn delete_user(
             }),
         )),
     }
-} 
\ No newline at end of file
+}
+
+/// Allows the caller to modify user `id` if they own it or may manage any user.
+/// Profiles without an owner can only be modified by the latter.
+async fn authorize_owner(
+    pool: &DbPool,
+    claims: &Claims,
+    id: Uuid,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let owner_id = match user_service::get_user_owner(pool, id).await {
+        Ok(owner_id) => owner_id,
+        Err(DieselError::NotFound) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "not_found".to_string(),
+                    message: "User not found".to_string(),
+                }),
+            ));
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "database_error".to_string(),
+                    message: "Failed to fetch user".to_string(),
+                }),
+            ));
+        }
+    };
+
+    if claims.role.has_permission(Permission::ManageAnyUser) {
+        return Ok(());
+    }
+    if owner_id == Some(caller_id(claims)?) {
+        return Ok(());
+    }
+
+    Err((
+        StatusCode::FORBIDDEN,
+        Json(ErrorResponse {
+            error: "forbidden".to_string(),
+            message: "Only the owner or an admin can modify this user".to_string(),
+        }),
+    ))
+}
+
+fn caller_id(claims: &Claims) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+    claims.sub.parse().map_err(|_| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "unauthorized".to_string(),
+                message: "Invalid or expired token".to_string(),
+            }),
+        )
+    })
+}
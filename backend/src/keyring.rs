@@ -0,0 +1,215 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::{Error as JwtError, ErrorKind},
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::config::Config;
+
+#[derive(Debug, Error)]
+pub enum KeyringError {
+    #[error("failed to read keys: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("malformed PEM in key {kid}: {source}")]
+    Pem { kid: String, source: pem::PemError },
+    #[error("key {0} is not an RSA or Ed25519 private key")]
+    UnsupportedKey(String),
+    #[error("invalid key {kid}: {source}")]
+    Jwt { kid: String, source: JwtError },
+    #[error("no *.pem keys found in {0}")]
+    Empty(PathBuf),
+    #[error("signing key {0} is not in the keyring")]
+    UnknownSigningKey(String),
+}
+
+struct Key {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+/// Keys access tokens are signed and verified with.
+///
+/// A keyring loaded from disk holds RS256 and EdDSA keys named by their `kid`.
+/// New tokens are signed with one of them, while every key in the ring still
+/// verifies, so tokens signed before a rotation stay valid until they expire.
+/// Without a key directory the ring falls back to a single HS256 secret.
+pub struct Keyring {
+    keys: Vec<Key>,
+    signing: usize,
+}
+
+impl Keyring {
+    /// Signs and verifies with the shared `secret`; tokens carry no `kid` and
+    /// the JWKS is empty.
+    pub fn hs256(secret: &[u8]) -> Self {
+        Keyring {
+            keys: vec![Key {
+                kid: None,
+                algorithm: Algorithm::HS256,
+                encoding: EncodingKey::from_secret(secret),
+                decoding: DecodingKey::from_secret(secret),
+                jwk: None,
+            }],
+            signing: 0,
+        }
+    }
+
+    /// Builds a ring from PKCS#8 (or PKCS#1 RSA) private keys in PEM form,
+    /// given as `(kid, pem)` pairs. Signs with `signing_kid`, or with the key
+    /// whose kid sorts last when `None`.
+    pub fn from_pem(
+        keys: Vec<(String, Vec<u8>)>,
+        signing_kid: Option<&str>,
+    ) -> Result<Self, KeyringError> {
+        let mut keys = keys
+            .into_iter()
+            .map(|(kid, pem)| load_key(kid, &pem))
+            .collect::<Result<Vec<_>, _>>()?;
+        keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+
+        let signing = match signing_kid {
+            Some(signing_kid) => keys
+                .iter()
+                .position(|key| key.kid.as_deref() == Some(signing_kid))
+                .ok_or_else(|| KeyringError::UnknownSigningKey(signing_kid.to_string()))?,
+            None => keys.len().saturating_sub(1),
+        };
+
+        Ok(Keyring { keys, signing })
+    }
+
+    /// Loads every `<kid>.pem` file in `dir`.
+    pub fn load(dir: impl AsRef<Path>, signing_kid: Option<&str>) -> Result<Self, KeyringError> {
+        let dir = dir.as_ref();
+        let mut keys = Vec::new();
+
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("pem") {
+                continue;
+            }
+            if let Some(kid) = path.file_stem().and_then(|stem| stem.to_str()) {
+                keys.push((kid.to_string(), std::fs::read(&path)?));
+            }
+        }
+
+        if keys.is_empty() {
+            return Err(KeyringError::Empty(dir.to_path_buf()));
+        }
+        Keyring::from_pem(keys, signing_kid)
+    }
+
+    /// The keys in `JWT_KEYS_DIR` when set, otherwise HS256 with `JWT_SECRET`.
+    pub fn from_config(config: &Config) -> Result<Self, KeyringError> {
+        match &config.jwt_keys_dir {
+            Some(dir) => Keyring::load(dir, config.jwt_signing_kid.as_deref()),
+            None => Ok(Keyring::hs256(config.jwt_secret.as_bytes())),
+        }
+    }
+
+    /// The kid new tokens are signed with, if the ring uses kids.
+    pub fn signing_kid(&self) -> Option<&str> {
+        self.keys[self.signing].kid.as_deref()
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, JwtError> {
+        let key = &self.keys[self.signing];
+        let mut header = Header::new(key.algorithm);
+        header.kid = key.kid.clone();
+
+        encode(&header, claims, &key.encoding)
+    }
+
+    /// Decodes `token` with the key named by its `kid` header, checking that
+    /// the token's algorithm is the one that key is used with.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T, JwtError> {
+        let header = decode_header(token)?;
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.kid == header.kid)
+            .ok_or_else(|| JwtError::from(ErrorKind::InvalidSignature))?;
+
+        Ok(decode(token, &key.decoding, &Validation::new(key.algorithm))?.claims)
+    }
+
+    /// Public halves of the asymmetric keys, served as `/.well-known/jwks.json`.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+fn load_key(kid: String, pem_bytes: &[u8]) -> Result<Key, KeyringError> {
+    let parsed = pem::parse(pem_bytes).map_err(|source| KeyringError::Pem {
+        kid: kid.clone(),
+        source,
+    })?;
+    let der = parsed.contents();
+
+    let rsa = match parsed.tag() {
+        "PRIVATE KEY" => RsaKeyPair::from_pkcs8(der).ok(),
+        "RSA PRIVATE KEY" => RsaKeyPair::from_der(der).ok(),
+        _ => None,
+    };
+    let ed25519 = match parsed.tag() {
+        "PRIVATE KEY" => Ed25519KeyPair::from_pkcs8_maybe_unchecked(der).ok(),
+        _ => None,
+    };
+
+    let (algorithm, key_algorithm, parameters, encoding) = if let Some(key_pair) = rsa {
+        let public = RsaPublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+        let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n: URL_SAFE_NO_PAD.encode(public.n),
+            e: URL_SAFE_NO_PAD.encode(public.e),
+        });
+        (Algorithm::RS256, KeyAlgorithm::RS256, parameters, EncodingKey::from_rsa_pem(pem_bytes))
+    } else if let Some(key_pair) = ed25519 {
+        let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+        });
+        (Algorithm::EdDSA, KeyAlgorithm::EdDSA, parameters, Ok(EncodingKey::from_ed_der(der)))
+    } else {
+        return Err(KeyringError::UnsupportedKey(kid));
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.clone()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+    let invalid = |source| KeyringError::Jwt {
+        kid: kid.clone(),
+        source,
+    };
+    let encoding = encoding.map_err(invalid)?;
+    let decoding = DecodingKey::from_jwk(&jwk).map_err(invalid)?;
+
+    Ok(Key {
+        kid: Some(kid),
+        algorithm,
+        encoding,
+        decoding,
+        jwk: Some(jwk),
+    })
+}
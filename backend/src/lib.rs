@@ -1,7 +1,15 @@
 pub mod config;
 pub mod database;
 pub mod handlers;
+pub mod keyring;
+pub mod mailer;
+pub mod mfa;
 pub mod middleware;
 pub mod models;
+pub mod oauth;
+pub mod password;
+pub mod query;
+pub mod revocation;
 pub mod schema;
-pub mod services; 
\ No newline at end of file
+pub mod services;
+pub mod state; 
\ No newline at end of file
@@ -1,28 +1,33 @@
 use axum::{
     http::{
-        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-        HeaderValue, Method,
+        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, LINK, RETRY_AFTER},
+        HeaderName, HeaderValue, Method,
     },
-    routing::{delete, get, post, put},
+    middleware,
+    routing::{delete, get, patch, post, put},
     Router,
 };
-use std::env;
+use std::net::SocketAddr;
 use tower::ServiceBuilder;
 use tower_http::{
-    cors::{Any, CorsLayer},
+    cors::CorsLayer,
     trace::TraceLayer,
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-mod config;
-mod database;
-mod handlers;
-mod middleware;
-mod models;
-mod schema;
-mod services;
-
-use database::create_pool;
+use cursor_backend::{
+    config::Config,
+    database::create_pool,
+    handlers,
+    keyring::Keyring,
+    mailer,
+    mfa::SecretCipher,
+    middleware::auth::{
+        auth_middleware, require_permission, require_scope, require_session, API_KEY_HEADER,
+    },
+    models::{Permission, Scope},
+    state::AppState,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -37,10 +42,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Load environment variables
     dotenvy::dotenv().ok();
+    let config = Config::from_env()?;
 
     // Create database connection pool
     let pool = create_pool().await?;
 
+    // Outgoing email: SMTP when configured, otherwise the local outbox file
+    let mailer = mailer::from_config(&config)?;
+
+    // Access token signing keys: the JWT_KEYS_DIR keyring, or HS256 with JWT_SECRET
+    let keys = Keyring::from_config(&config)?;
+    match keys.signing_kid() {
+        Some(kid) => tracing::info!("Signing access tokens with key {}", kid),
+        None => tracing::warn!("JWT_KEYS_DIR is not set, signing access tokens with HS256"),
+    }
+
+    // Key TOTP secrets are encrypted with
+    let mfa_cipher = SecretCipher::from_config(&config)?;
+    if config.mfa_encryption_key.is_none() {
+        tracing::warn!("MFA_ENCRYPTION_KEY is not set, deriving the TOTP key from JWT_SECRET");
+    }
+    if config.cursor_secret.is_none() {
+        tracing::warn!("CURSOR_SECRET is not set, deriving the cursor key from JWT_SECRET");
+    }
+
+    // Load token revocations and keep them in sync with other instances
+    let state = AppState::new(pool, mailer, keys, mfa_cipher, config.clone()).await?;
+    state.revocations.clone().spawn_sync(state.pool.clone());
+
     // Configure CORS
     let cors = CorsLayer::new()
         .allow_origin(vec![
@@ -48,39 +77,191 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "http://localhost:80".parse::<HeaderValue>()?,
             "http://localhost".parse::<HeaderValue>()?,
         ])
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
         .allow_credentials(true)
-        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
+        .allow_headers([
+            AUTHORIZATION,
+            ACCEPT,
+            CONTENT_TYPE,
+            HeaderName::from_static(API_KEY_HEADER),
+        ])
+        .expose_headers([
+            LINK,
+            HeaderName::from_static("x-total-count"),
+            HeaderName::from_static("x-next-cursor"),
+            RETRY_AFTER,
+        ]);
+
+    // User routes: writes always require a token, reads only when PUBLIC_READS=false.
+    // Each write additionally checks the caller's role for a permission, and API keys
+    // need the scope of the route.
+    let require_auth = middleware::from_fn_with_state(state.clone(), auth_middleware);
+    let permission =
+        |permission: Permission| middleware::from_fn_with_state(permission, require_permission);
+    let scope = |scope: Scope| middleware::from_fn_with_state(scope, require_scope);
+    let user_reads = Router::new()
+        .route("/users", get(handlers::users::get_users))
+        .route("/users/:id", get(handlers::users::get_user))
+        .route("/users/:id/posts", get(handlers::posts::get_user_posts))
+        .route("/users/:id/albums", get(handlers::albums::get_user_albums))
+        .route("/users/:id/todos", get(handlers::todos::get_user_todos));
+    let user_reads = if config.public_reads {
+        user_reads
+    } else {
+        user_reads
+            .route_layer(scope(Scope::UsersRead))
+            .route_layer(require_auth.clone())
+    };
+    let user_writes = Router::new()
+        .route(
+            "/users",
+            post(handlers::users::create_user)
+                .route_layer(permission(Permission::CreateUsers)),
+        )
+        .route(
+            "/users/:id",
+            put(handlers::users::update_user)
+                .route_layer(permission(Permission::UpdateUsers)),
+        )
+        .route(
+            "/users/:id",
+            delete(handlers::users::delete_user)
+                .route_layer(permission(Permission::DeleteUsers)),
+        )
+        .route_layer(scope(Scope::UsersWrite))
+        .route_layer(require_auth.clone());
+    // Sessions, passwords, 2FA, API keys and OAuth consents can only be managed after
+    // logging in
+    let session_routes = Router::new()
+        .route("/auth/logout", post(handlers::auth::logout))
+        .route("/auth/logout-all", post(handlers::auth::logout_all))
+        .route("/auth/me", patch(handlers::auth::update_me))
+        .route("/auth/me", delete(handlers::auth::delete_me))
+        .route("/auth/password", post(handlers::auth::change_password))
+        .route("/auth/mfa/totp", post(handlers::mfa::enroll_totp))
+        .route("/auth/mfa/totp", delete(handlers::mfa::disable_totp))
+        .route("/auth/mfa/totp/confirm", post(handlers::mfa::confirm_totp))
+        .route("/auth/mfa/recovery-codes", post(handlers::mfa::regenerate_recovery_codes))
+        .route("/auth/api-keys", get(handlers::api_keys::get_api_keys))
+        .route("/auth/api-keys", post(handlers::api_keys::create_api_key))
+        .route("/auth/api-keys/:id", delete(handlers::api_keys::delete_api_key))
+        .route("/oauth/authorize", get(handlers::oauth::authorize))
+        .route("/oauth/authorize", post(handlers::oauth::authorize_decision))
+        .route("/oauth/consents", get(handlers::oauth::get_oauth_consents))
+        .route("/oauth/consents/:client_id", delete(handlers::oauth::delete_oauth_consent))
+        .route_layer(middleware::from_fn(require_session))
+        .route_layer(require_auth.clone());
+    let admin_routes = Router::new()
+        .route(
+            "/auth/users/:id/role",
+            put(handlers::auth::update_role).route_layer(permission(Permission::ManageRoles)),
+        )
+        .route(
+            "/auth/login-attempts",
+            get(handlers::auth::get_login_attempts)
+                .route_layer(permission(Permission::ViewLoginAttempts)),
+        )
+        .route(
+            "/oauth/clients",
+            get(handlers::oauth::get_oauth_clients)
+                .post(handlers::oauth::create_oauth_client)
+                .route_layer(permission(Permission::ManageOAuthClients)),
+        )
+        .route(
+            "/oauth/clients/:id",
+            delete(handlers::oauth::delete_oauth_client)
+                .route_layer(permission(Permission::ManageOAuthClients)),
+        )
+        .route_layer(scope(Scope::Admin))
+        .route_layer(require_auth.clone());
+    let account_routes = Router::new()
+        .route("/auth/me", get(handlers::auth::me))
+        .route_layer(require_auth);
 
     // Build our application with routes
     let app = Router::new()
         // Health check
         .route("/health", get(handlers::health::health_check))
+        // Public keys for verifying access tokens
+        .route("/.well-known/jwks.json", get(handlers::jwks::jwks))
         // Auth routes
         .route("/auth/register", post(handlers::auth::register))
         .route("/auth/login", post(handlers::auth::login))
-        // User routes
-        .route("/users", get(handlers::users::get_users))
-        .route("/users", post(handlers::users::create_user))
-        .route("/users/:id", get(handlers::users::get_user))
-        .route("/users/:id", put(handlers::users::update_user))
-        .route("/users/:id", delete(handlers::users::delete_user))
+        .route("/auth/refresh", post(handlers::auth::refresh))
+        .route("/auth/mfa/verify", post(handlers::mfa::verify_mfa))
+        .route("/auth/magic-link", post(handlers::auth::request_magic_link))
+        .route("/auth/magic-link/consume", post(handlers::auth::consume_magic_link))
+        .route("/auth/password-reset", post(handlers::auth::request_password_reset))
+        .route("/auth/password-reset/confirm", post(handlers::auth::confirm_password_reset))
+        .route("/auth/verify-email", post(handlers::auth::verify_email))
+        .route("/auth/verify-email/resend", post(handlers::auth::resend_verification))
+        // OAuth 2.0 endpoints for client applications
+        .route("/oauth/token", post(handlers::oauth::token))
+        .route("/oauth/introspect", post(handlers::oauth::introspect))
+        .merge(session_routes)
+        .merge(admin_routes)
+        .merge(account_routes)
+        .merge(user_reads)
+        .merge(user_writes)
+        // Post routes
+        .route("/posts", get(handlers::posts::get_posts))
+        .route("/posts", post(handlers::posts::create_post))
+        .route("/posts/:id", get(handlers::posts::get_post))
+        .route("/posts/:id", put(handlers::posts::replace_post))
+        .route("/posts/:id", patch(handlers::posts::update_post))
+        .route("/posts/:id", delete(handlers::posts::delete_post))
+        .route("/posts/:id/comments", get(handlers::comments::get_post_comments))
+        // Comment routes
+        .route("/comments", get(handlers::comments::get_comments))
+        .route("/comments", post(handlers::comments::create_comment))
+        .route("/comments/:id", get(handlers::comments::get_comment))
+        .route("/comments/:id", put(handlers::comments::replace_comment))
+        .route("/comments/:id", patch(handlers::comments::update_comment))
+        .route("/comments/:id", delete(handlers::comments::delete_comment))
+        // Album routes
+        .route("/albums", get(handlers::albums::get_albums))
+        .route("/albums", post(handlers::albums::create_album))
+        .route("/albums/:id", get(handlers::albums::get_album))
+        .route("/albums/:id", put(handlers::albums::replace_album))
+        .route("/albums/:id", patch(handlers::albums::update_album))
+        .route("/albums/:id", delete(handlers::albums::delete_album))
+        .route("/albums/:id/photos", get(handlers::photos::get_album_photos))
+        // Photo routes
+        .route("/photos", get(handlers::photos::get_photos))
+        .route("/photos", post(handlers::photos::create_photo))
+        .route("/photos/:id", get(handlers::photos::get_photo))
+        .route("/photos/:id", put(handlers::photos::replace_photo))
+        .route("/photos/:id", patch(handlers::photos::update_photo))
+        .route("/photos/:id", delete(handlers::photos::delete_photo))
+        // Todo routes
+        .route("/todos", get(handlers::todos::get_todos))
+        .route("/todos", post(handlers::todos::create_todo))
+        .route("/todos/:id", get(handlers::todos::get_todo))
+        .route("/todos/:id", put(handlers::todos::replace_todo))
+        .route("/todos/:id", patch(handlers::todos::update_todo))
+        .route("/todos/:id", delete(handlers::todos::delete_todo))
         // Middleware
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
                 .layer(cors),
         )
-        .with_state(pool);
+        .with_state(state);
 
     // Start server
-    let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    let addr = format!("0.0.0.0:{}", port);
+    let addr = format!("0.0.0.0:{}", config.port);
     
     tracing::info!("Starting server on {}", addr);
     
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    // Connect info gives login throttling the client address
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
@@ -0,0 +1,155 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use rand::{Rng, RngCore};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use totp_rs::{Algorithm, TotpUrlError, TOTP};
+
+use crate::config::Config;
+
+/// Issuer shown by authenticator apps next to the account email.
+pub const TOTP_ISSUER: &str = "Cursor";
+pub const TOTP_DIGITS: usize = 6;
+pub const TOTP_STEP_SECONDS: u64 = 30;
+/// Codes from this many steps before or after the current one are accepted,
+/// to allow for clock drift.
+pub const TOTP_SKEW_STEPS: i64 = 1;
+/// 160 bits, the HMAC-SHA1 block size recommended by RFC 4226.
+const TOTP_SECRET_BYTES: usize = 20;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+#[derive(Debug, Error)]
+pub enum MfaError {
+    #[error("MFA_ENCRYPTION_KEY must be 32 bytes of base64")]
+    InvalidKey,
+    #[error("failed to encrypt or decrypt the TOTP secret")]
+    Cipher,
+    #[error("malformed encrypted secret: {0}")]
+    Encoding(#[from] base64::DecodeError),
+    #[error("invalid TOTP parameters: {0}")]
+    Totp(#[from] TotpUrlError),
+}
+
+/// Encrypts TOTP secrets at rest with AES-256-GCM.
+pub struct SecretCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SecretCipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        SecretCipher {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    /// Uses `MFA_ENCRYPTION_KEY` (base64 of 32 bytes) when set. Otherwise the
+    /// key is derived from `JWT_SECRET`, which is only fit for development:
+    /// rotating the secret would make enrolled authenticators unusable.
+    pub fn from_config(config: &Config) -> Result<Self, MfaError> {
+        let key: [u8; 32] = match &config.mfa_encryption_key {
+            Some(key) => STANDARD
+                .decode(key)
+                .map_err(|_| MfaError::InvalidKey)?
+                .try_into()
+                .map_err(|_| MfaError::InvalidKey)?,
+            None => Sha256::new()
+                .chain_update(b"mfa-secret-encryption:")
+                .chain_update(config.jwt_secret.as_bytes())
+                .finalize()
+                .into(),
+        };
+
+        Ok(SecretCipher::new(&key))
+    }
+
+    /// Base64 of a random nonce followed by the ciphertext.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<String, MfaError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| MfaError::Cipher)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(STANDARD.encode(sealed))
+    }
+
+    pub fn decrypt(&self, sealed: &str) -> Result<Vec<u8>, MfaError> {
+        let sealed = STANDARD.decode(sealed)?;
+        if sealed.len() < 12 {
+            return Err(MfaError::Cipher);
+        }
+        let (nonce, ciphertext) = sealed.split_at(12);
+
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| MfaError::Cipher)
+    }
+}
+
+pub fn generate_totp_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; TOTP_SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+fn totp(secret: &[u8], account: &str) -> Result<TOTP, MfaError> {
+    Ok(TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP_SECONDS,
+        secret.to_vec(),
+        Some(TOTP_ISSUER.to_string()),
+        account.to_string(),
+    )?)
+}
+
+/// The base32 secret and `otpauth://totp/...` URI for an authenticator app.
+pub fn totp_enrollment(secret: &[u8], account: &str) -> Result<(String, String), MfaError> {
+    let totp = totp(secret, account)?;
+    Ok((totp.get_secret_base32(), totp.get_url()))
+}
+
+/// The time step `code` is valid for at `now`, within [`TOTP_SKEW_STEPS`] of
+/// the current one, or `None` if it matches none of them.
+pub fn verify_totp(secret: &[u8], code: &str, now: DateTime<Utc>) -> Option<i64> {
+    let totp = totp(secret, "").ok()?;
+    let current = now.timestamp() / TOTP_STEP_SECONDS as i64;
+
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .find(|step| totp.check(code, (*step as u64) * TOTP_STEP_SECONDS))
+}
+
+/// Whether `code` has the shape of a TOTP code rather than a recovery code.
+pub fn is_totp_code(code: &str) -> bool {
+    code.len() == TOTP_DIGITS && code.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Fresh recovery codes like `k3x9q-7mf2a`, 50 random bits each.
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())])
+                .map(char::from)
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+/// Recovery codes are compared case-insensitively, ignoring dashes and spaces.
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
@@ -0,0 +1,48 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use crate::config::Config;
+
+/// Address of the client making the request, used to key login throttling.
+///
+/// Taken from the first `X-Forwarded-For` entry when `TRUST_X_FORWARDED_FOR`
+/// is set (only do that behind a proxy that overwrites the header), otherwise
+/// from the socket peer. Falls back to `"unknown"` when neither is available,
+/// e.g. when the router is driven directly in tests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIp(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+
+        let forwarded = config
+            .trust_forwarded_for
+            .then(|| parts.headers.get("x-forwarded-for"))
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(str::trim)
+            .filter(|ip| !ip.is_empty());
+        if let Some(ip) = forwarded {
+            return Ok(ClientIp(ip.to_string()));
+        }
+
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+        Ok(ClientIp(peer.unwrap_or_else(|| "unknown".to_string())))
+    }
+}
@@ -1,6 +1,14 @@
 use chrono::{DateTime, Utc};
-use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    prelude::*,
+    serialize::{self, IsNull, Output, ToSql},
+    sql_types::Varchar,
+};
 use serde::{Deserialize, Serialize};
+use std::{io::Write, str::FromStr};
 use uuid::Uuid;
 use validator::Validate;
 
@@ -16,6 +24,9 @@ pub struct User {
     pub company: Option<Company>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// The auth account that owns this profile. `None` once that account is
+    /// deleted, after which only admins can modify the profile.
+    pub owner_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -44,6 +55,198 @@ pub struct Company {
     pub bs: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::posts)]
+pub struct Post {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub title: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::comments)]
+pub struct Comment {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::albums)]
+pub struct Album {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::photos)]
+pub struct Photo {
+    pub id: Uuid,
+    pub album_id: Uuid,
+    pub title: String,
+    pub url: String,
+    pub thumbnail_url: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::todos)]
+pub struct Todo {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub title: String,
+    pub completed: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Role of an account, ordered from least to most privileged.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, AsExpression,
+    FromSqlRow,
+)]
+#[diesel(sql_type = Varchar)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Admin,
+}
+
+/// An action guarded by [`crate::middleware::auth::require_permission`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    CreateUsers,
+    UpdateUsers,
+    DeleteUsers,
+    /// Update users owned by another account, or no account.
+    ManageAnyUser,
+    ManageRoles,
+    ViewLoginAttempts,
+    ManageOAuthClients,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn permissions(self) -> &'static [Permission] {
+        match self {
+            Role::Viewer => &[],
+            Role::Editor => &[Permission::CreateUsers, Permission::UpdateUsers],
+            Role::Admin => &[
+                Permission::CreateUsers,
+                Permission::UpdateUsers,
+                Permission::DeleteUsers,
+                Permission::ManageAnyUser,
+                Permission::ManageRoles,
+                Permission::ViewLoginAttempts,
+                Permission::ManageOAuthClients,
+            ],
+        }
+    }
+
+    pub fn has_permission(self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "viewer" => Ok(Role::Viewer),
+            "editor" => Ok(Role::Editor),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("unknown role: {}", other)),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for Role {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for Role {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        Ok(value.parse()?)
+    }
+}
+
+/// What an API key or OAuth access token may be used for. Both act with their
+/// account's role, so a scope never grants more than the role does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Varchar)]
+pub enum Scope {
+    /// Reading users when `PUBLIC_READS=false`.
+    #[serde(rename = "users:read")]
+    UsersRead,
+    /// Creating, updating and deleting users.
+    #[serde(rename = "users:write")]
+    UsersWrite,
+    /// Managing roles and reading the login attempt log.
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::UsersRead => "users:read",
+            Scope::UsersWrite => "users:write",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "users:read" => Ok(Scope::UsersRead),
+            "users:write" => Ok(Scope::UsersWrite),
+            "admin" => Ok(Scope::Admin),
+            other => Err(format!("unknown scope: {}", other)),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for Scope {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for Scope {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        Ok(value.parse()?)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Queryable)]
 #[diesel(table_name = crate::schema::auth_users)]
 pub struct AuthUser {
@@ -53,6 +256,9 @@ pub struct AuthUser {
     pub password_hash: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub tokens_revoked_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub role: Role,
 }
 
 #[derive(Debug, Insertable)]
@@ -62,6 +268,239 @@ pub struct NewAuthUser {
     pub name: String,
     pub email: String,
     pub password_hash: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::refresh_tokens)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub family_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// The OAuth client the token was issued to; `None` for login sessions.
+    pub client_id: Option<Uuid>,
+    /// Scopes granted to the OAuth client.
+    pub scopes: Option<Vec<Scope>>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::refresh_tokens)]
+pub struct NewRefreshToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub family_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub client_id: Option<Uuid>,
+    pub scopes: Option<Vec<Scope>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::login_attempts)]
+pub struct LoginAttempt {
+    pub id: Uuid,
+    pub email: String,
+    pub user_id: Option<Uuid>,
+    pub ip_address: String,
+    pub succeeded: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::login_attempts)]
+pub struct NewLoginAttempt {
+    pub id: Uuid,
+    pub email: String,
+    pub user_id: Option<Uuid>,
+    pub ip_address: String,
+    pub succeeded: bool,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::email_verification_tokens)]
+pub struct NewEmailVerificationToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub email: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::magic_link_tokens)]
+pub struct NewMagicLinkToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub email: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::password_reset_tokens)]
+pub struct NewPasswordResetToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::totp_credentials)]
+pub struct TotpCredential {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// Base64 of the AES-GCM nonce followed by the encrypted secret.
+    pub secret_encrypted: String,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::totp_credentials)]
+pub struct NewTotpCredential {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub secret_encrypted: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::mfa_recovery_codes)]
+pub struct NewMfaRecoveryCode {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::mfa_challenges)]
+pub struct NewMfaChallenge {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::api_keys)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub secret_hash: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::api_keys)]
+pub struct NewApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub secret_hash: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::oauth_clients)]
+pub struct OAuthClient {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub name: String,
+    /// `None` for public clients, which cannot keep a secret.
+    pub client_secret_hash: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::oauth_clients)]
+pub struct NewOAuthClient {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub name: String,
+    pub client_secret_hash: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::oauth_authorization_codes)]
+pub struct OAuthAuthorizationCode {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<Scope>,
+    /// Base64url SHA-256 of the PKCE code verifier.
+    pub code_challenge: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Token family started when the code is redeemed.
+    pub family_id: Uuid,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::oauth_authorization_codes)]
+pub struct NewOAuthAuthorizationCode {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<Scope>,
+    pub code_challenge: String,
+    pub expires_at: DateTime<Utc>,
+    pub family_id: Uuid,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::oauth_consents)]
+pub struct OAuthConsent {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::oauth_consents)]
+pub struct NewOAuthConsent {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::revoked_tokens)]
+pub struct NewRevokedToken {
+    pub jti: Uuid,
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
 }
 
 // Request/Response DTOs
@@ -121,6 +560,99 @@ pub struct CreateCompanyRequest {
     pub bs: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreatePostRequest {
+    pub user_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub title: String,
+    #[validate(length(min = 1))]
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdatePostRequest {
+    pub user_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 255))]
+    pub title: Option<String>,
+    #[validate(length(min = 1))]
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateCommentRequest {
+    pub post_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    #[validate(email)]
+    pub email: String,
+    #[validate(length(min = 1))]
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateCommentRequest {
+    pub post_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 255))]
+    pub name: Option<String>,
+    #[validate(email)]
+    pub email: Option<String>,
+    #[validate(length(min = 1))]
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateAlbumRequest {
+    pub user_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateAlbumRequest {
+    pub user_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 255))]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreatePhotoRequest {
+    pub album_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub title: String,
+    #[validate(url)]
+    pub url: String,
+    #[validate(url)]
+    pub thumbnail_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdatePhotoRequest {
+    pub album_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 255))]
+    pub title: Option<String>,
+    #[validate(url)]
+    pub url: Option<String>,
+    #[validate(url)]
+    pub thumbnail_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateTodoRequest {
+    pub user_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub title: String,
+    #[serde(default)]
+    pub completed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateTodoRequest {
+    pub user_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 255))]
+    pub title: Option<String>,
+    pub completed: Option<bool>,
+}
+
 // Auth DTOs
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct RegisterRequest {
@@ -140,9 +672,168 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct RefreshRequest {
+    #[validate(length(min = 1))]
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1))]
+    pub current_password: String,
+    #[validate(length(min = 6, max = 100))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PasswordResetRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PasswordResetConfirmRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+    #[validate(length(min = 6, max = 100))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct MagicLinkRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct MagicLinkConsumeRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ResendVerificationRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+/// A TOTP code, or a recovery code where the endpoint accepts one.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct MfaCodeRequest {
+    #[validate(length(min = 1, max = 32))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct MfaVerifyRequest {
+    #[validate(length(min = 1))]
+    pub mfa_token: String,
+    #[validate(length(min = 1, max = 32))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateProfileRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(email)]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub scopes: Vec<Scope>,
+    /// Omit for a key that does not expire.
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateOAuthClientRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    /// Where `/oauth/authorize` may send users back to, matched exactly.
+    /// Clients that only use `client_credentials` need none.
+    #[validate(length(max = 10))]
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    /// The most a token issued to the client can be granted.
+    #[validate(length(min = 1))]
+    pub scopes: Vec<Scope>,
+    /// Public clients get no secret and can only use the authorization code
+    /// grant with PKCE.
+    #[serde(default)]
+    pub public: bool,
+}
+
+/// The parameters of an authorization request (RFC 6749 section 4.1.1) with
+/// PKCE (RFC 7636), as the consent screen receives them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// The user's answer on the consent screen for an [`AuthorizeRequest`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthorizeDecisionRequest {
+    #[serde(flatten)]
+    pub request: AuthorizeRequest,
+    pub approve: bool,
+}
+
+/// Form body of `POST /oauth/token`. Which fields are needed depends on
+/// `grant_type`; clients may authenticate with HTTP Basic instead of
+/// `client_id` and `client_secret`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Form body of `POST /oauth/introspect` (RFC 7662).
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthResponse {
+    /// Short-lived access token for `Authorization: Bearer`.
     pub token: String,
+    /// Opaque single-use token for `POST /auth/refresh`.
+    pub refresh_token: String,
+    /// Lifetime of `token` in seconds.
+    pub expires_in: i64,
     pub user: AuthUserResponse,
 }
 
@@ -151,7 +842,201 @@ pub struct AuthUserResponse {
     pub id: Uuid,
     pub name: String,
     pub email: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Returned by `POST /auth/register` when accounts must verify their email
+/// before they can log in.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerificationPendingResponse {
+    pub user: AuthUserResponse,
+    pub email_verification_required: bool,
+}
+
+/// Returned by `POST /auth/login` instead of tokens when the account has TOTP
+/// enabled; `mfa_token` is exchanged at `POST /auth/mfa/verify`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    /// Lifetime of `mfa_token` in seconds.
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
+/// The new secret from `POST /auth/mfa/totp`, both raw (base32) and as a URI
+/// for authenticator apps. Shown once; it is stored encrypted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// An API key as listed by `GET /auth/api-keys`, without its secret.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(api_key: ApiKey) -> Self {
+        ApiKeyResponse {
+            id: api_key.id,
+            name: api_key.name,
+            prefix: api_key.prefix,
+            scopes: api_key.scopes,
+            expires_at: api_key.expires_at,
+            last_used_at: api_key.last_used_at,
+            created_at: api_key.created_at,
+        }
+    }
+}
+
+/// Returned once by `POST /auth/api-keys`; only a hash of `key` is stored.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedApiKeyResponse {
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+    pub key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthClientResponse {
+    pub client_id: Uuid,
+    pub name: String,
+    pub owner_id: Uuid,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<Scope>,
+    pub public: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<OAuthClient> for OAuthClientResponse {
+    fn from(client: OAuthClient) -> Self {
+        OAuthClientResponse {
+            client_id: client.id,
+            name: client.name,
+            owner_id: client.owner_id,
+            redirect_uris: client.redirect_uris,
+            scopes: client.scopes,
+            public: client.client_secret_hash.is_none(),
+            created_at: client.created_at,
+        }
+    }
+}
+
+/// Returned once by `POST /oauth/clients`; only a hash of the secret is kept.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedOAuthClientResponse {
+    #[serde(flatten)]
+    pub client: OAuthClientResponse,
+    pub client_secret: Option<String>,
+}
+
+/// What the consent screen shows for a valid authorization request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthorizationPromptResponse {
+    pub client_id: Uuid,
+    pub client_name: String,
+    pub scopes: Vec<Scope>,
+    /// `false` when the user already allowed these scopes, so the request can
+    /// be approved without asking again.
+    pub consent_required: bool,
+}
+
+/// Where to send the user's browser after a decision: the client's redirect
+/// URI with either `code` or `error`, plus `state`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthorizeRedirectResponse {
+    pub redirect_to: String,
+}
+
+/// Successful `POST /oauth/token` response (RFC 6749 section 5.1).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    pub scope: String,
+}
+
+/// RFC 7662 introspection response; only `active` is set for tokens that are
+/// not active.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+}
+
+/// An app the current user has allowed to act for them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthConsentResponse {
+    pub client_id: Uuid,
+    pub client_name: String,
+    pub scopes: Vec<Scope>,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RegisterResponse {
+    Authenticated(AuthResponse),
+    VerificationPending(VerificationPendingResponse),
+}
+
+/// A resource with the records requested via `_embed`/`_expand` flattened into it.
+#[derive(Debug, Serialize)]
+pub struct WithRelations<T> {
+    #[serde(flatten)]
+    pub item: T,
+    #[serde(flatten)]
+    pub related: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<T> From<T> for WithRelations<T> {
+    fn from(item: T) -> Self {
+        WithRelations {
+            item,
+            related: serde_json::Map::new(),
+        }
+    }
 }
 
 // Error responses
@@ -161,11 +1046,41 @@ pub struct ErrorResponse {
     pub message: String,
 }
 
+/// Error body of the OAuth token and introspection endpoints (RFC 6749
+/// section 5.2), which clients expect instead of [`ErrorResponse`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthErrorResponse {
+    pub error: String,
+    pub error_description: String,
+}
+
 // JWT Claims
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String, // user id
     pub email: String,
+    pub jti: String, // token id, for revocation
+    pub sid: String, // session: the refresh token family the token was issued with
+    pub role: Role,
     pub exp: usize,  // expiration time
     pub iat: usize,  // issued at
+    /// Issue time in microseconds, the precision revocation cutoffs are
+    /// stored with. Absent from tokens issued before it was introduced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat_micros: Option<i64>,
+    /// Set when the caller authenticated with an API key or OAuth access
+    /// token, which only reaches routes requiring one of these scopes. `None`
+    /// for login sessions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<Scope>>,
+    /// The OAuth client an access token was issued to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
+
+impl Claims {
+    /// Whether the caller may use routes requiring `scope`.
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.as_ref().is_none_or(|scopes| scopes.contains(&scope))
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,102 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::models::Scope;
+
+/// How long an authorization code can be exchanged at `/oauth/token`.
+pub const AUTHORIZATION_CODE_TTL_MINUTES: i64 = 10;
+
+/// Joins scopes into the space-separated form OAuth uses on the wire.
+pub fn format_scopes(scopes: &[Scope]) -> String {
+    scopes.iter().map(|scope| scope.as_str()).collect::<Vec<_>>().join(" ")
+}
+
+/// Parses a space-separated `scope` parameter, dropping duplicates.
+pub fn parse_scopes(scope: &str) -> Result<Vec<Scope>, String> {
+    let mut scopes = Vec::new();
+    for name in scope.split(' ').filter(|name| !name.is_empty()) {
+        let scope = name.parse::<Scope>()?;
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+
+    Ok(scopes)
+}
+
+/// The scopes a request asks for: all of `allowed` when `requested` is
+/// omitted, otherwise the requested ones if `allowed` covers them. `None`
+/// means invalid or not allowed.
+pub fn requested_scopes(requested: Option<&str>, allowed: &[Scope]) -> Option<Vec<Scope>> {
+    let scopes = match requested {
+        Some(requested) => parse_scopes(requested).ok()?,
+        None => allowed.to_vec(),
+    };
+
+    if scopes.is_empty() || !scopes.iter().all(|scope| allowed.contains(scope)) {
+        return None;
+    }
+    Some(scopes)
+}
+
+/// Whether `challenge` looks like a base64url SHA-256 digest, the only PKCE
+/// method (`S256`) accepted.
+pub fn is_code_challenge(challenge: &str) -> bool {
+    challenge.len() == 43
+        && challenge
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+/// Checks a PKCE code verifier against the `S256` challenge from the
+/// authorization request (RFC 7636 section 4.6).
+pub fn verify_code_challenge(verifier: &str, challenge: &str) -> bool {
+    let valid_verifier = (43..=128).contains(&verifier.len())
+        && verifier
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"-._~".contains(&byte));
+
+    valid_verifier && URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) == challenge
+}
+
+/// Redirect URIs must be absolute and without a fragment, and use HTTPS unless
+/// they point at the local machine.
+pub fn is_valid_redirect_uri(uri: &str) -> bool {
+    let Ok(url) = Url::parse(uri) else {
+        return false;
+    };
+    let local = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+
+    url.fragment().is_none()
+        && match url.scheme() {
+            "https" => true,
+            "http" => local,
+            _ => false,
+        }
+}
+
+/// `redirect_uri` with `params` appended to its query string.
+pub fn redirect_with(redirect_uri: &str, params: &[(&str, &str)]) -> Option<String> {
+    let mut url = Url::parse(redirect_uri).ok()?;
+    url.query_pairs_mut().extend_pairs(params);
+    Some(url.into())
+}
+
+/// Client id and secret from an `Authorization: Basic` header, which OAuth
+/// form-encodes before base64 (RFC 6749 section 2.3.1).
+pub fn basic_credentials(header: &str) -> Option<(String, String)> {
+    let encoded = header.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    let decode = |value: &str| {
+        url::form_urlencoded::parse(format!("v={}", value).as_bytes())
+            .next()
+            .map(|(_, value)| value.into_owned())
+    };
+
+    Some((decode(client_id)?, decode(client_secret)?))
+}
@@ -0,0 +1,187 @@
+use argon2::{
+    password_hash::{
+        rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString,
+    },
+    Algorithm, Argon2, Params, Version,
+};
+use bcrypt::HashParts;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PasswordError {
+    #[error("argon2 error: {0}")]
+    Argon2(#[from] argon2::password_hash::Error),
+    #[error("bcrypt error: {0}")]
+    Bcrypt(#[from] bcrypt::BcryptError),
+    #[error("unrecognized password hash format")]
+    UnknownFormat,
+}
+
+/// One password hashing algorithm. Handlers go through [`Passwords`], which
+/// picks the hasher that produced a stored hash.
+pub trait PasswordHasher: Send + Sync {
+    /// Whether `hash` was produced by this algorithm.
+    fn recognizes(&self, hash: &str) -> bool;
+    fn hash(&self, password: &str) -> Result<String, PasswordError>;
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError>;
+    /// Whether `hash` uses weaker parameters than this hasher is configured with.
+    fn needs_rehash(&self, hash: &str) -> bool;
+}
+
+/// Argon2id with the crate's default parameters (19 MiB, 2 iterations, 1 lane)
+/// unless others are given.
+pub struct Argon2Hasher {
+    params: Params,
+}
+
+impl Argon2Hasher {
+    pub fn new(params: Params) -> Self {
+        Argon2Hasher { params }
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+impl Default for Argon2Hasher {
+    fn default() -> Self {
+        Argon2Hasher::new(Params::default())
+    }
+}
+
+impl PasswordHasher for Argon2Hasher {
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$argon2")
+    }
+
+    fn hash(&self, password: &str) -> Result<String, PasswordError> {
+        let salt = SaltString::generate(&mut OsRng);
+        Ok(self.argon2().hash_password(password.as_bytes(), &salt)?.to_string())
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError> {
+        // Algorithm, version and parameters are read from the hash itself
+        let parsed = PasswordHash::new(hash)?;
+        match self.argon2().verify_password(password.as_bytes(), &parsed) {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&parsed) else {
+            return true;
+        };
+
+        parsed.algorithm != Algorithm::Argon2id.ident()
+            || parsed.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+    }
+}
+
+/// bcrypt, which accounts were hashed with before Argon2id.
+pub struct BcryptHasher {
+    cost: u32,
+}
+
+impl BcryptHasher {
+    pub fn new(cost: u32) -> Self {
+        BcryptHasher { cost }
+    }
+}
+
+impl Default for BcryptHasher {
+    fn default() -> Self {
+        BcryptHasher::new(bcrypt::DEFAULT_COST)
+    }
+}
+
+impl PasswordHasher for BcryptHasher {
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$2")
+    }
+
+    fn hash(&self, password: &str) -> Result<String, PasswordError> {
+        Ok(bcrypt::hash(password, self.cost)?)
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError> {
+        Ok(bcrypt::verify(password, hash)?)
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        hash.parse::<HashParts>().map_or(true, |parts| parts.get_cost() < self.cost)
+    }
+}
+
+/// Hashes new passwords with the current algorithm and still verifies hashes
+/// made by older ones, so stored hashes can be upgraded on the next login.
+pub struct Passwords {
+    current: Box<dyn PasswordHasher>,
+    legacy: Vec<Box<dyn PasswordHasher>>,
+    /// Hash checked for unknown accounts, made on first use.
+    dummy_hash: OnceLock<String>,
+}
+
+impl Passwords {
+    pub fn new(current: Box<dyn PasswordHasher>, legacy: Vec<Box<dyn PasswordHasher>>) -> Self {
+        Passwords {
+            current,
+            legacy,
+            dummy_hash: OnceLock::new(),
+        }
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String, PasswordError> {
+        self.current.hash(password)
+    }
+
+    pub fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError> {
+        self.hasher_for(hash)
+            .ok_or(PasswordError::UnknownFormat)?
+            .verify(password, hash)
+    }
+
+    /// Checks `password` against a throwaway hash of the current algorithm and
+    /// always fails. Logins for unknown accounts go through this so they take as
+    /// long as a wrong password and do not reveal which emails are registered.
+    pub fn verify_dummy(&self, password: &str) -> bool {
+        let hash = self
+            .dummy_hash
+            .get_or_init(|| self.current.hash("not a password").unwrap_or_default());
+        let _ = self.current.verify(password, hash);
+        false
+    }
+
+    /// Whether `hash` should be replaced by [`Passwords::hash`] once the
+    /// password is known: it was made by a legacy algorithm or with outdated
+    /// parameters.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        !self.current.recognizes(hash) || self.current.needs_rehash(hash)
+    }
+
+    fn hasher_for(&self, hash: &str) -> Option<&dyn PasswordHasher> {
+        std::iter::once(&self.current)
+            .chain(&self.legacy)
+            .find(|hasher| hasher.recognizes(hash))
+            .map(|hasher| hasher.as_ref())
+    }
+}
+
+/// Argon2id for new hashes, bcrypt accepted for existing ones.
+impl Default for Passwords {
+    fn default() -> Self {
+        Passwords::new(
+            Box::new(Argon2Hasher::default()),
+            vec![Box::new(BcryptHasher::default())],
+        )
+    }
+}
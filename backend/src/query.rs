@@ -0,0 +1,812 @@
+//! json-server style query parameters for list endpoints.
+//!
+//! Handlers extract a [`ListQuery`] from the request; services compile it into
+//! a boxed Diesel query through the `*_condition!` macros below, so filtering
+//! and pagination always happen in Postgres rather than in memory.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, HeaderMap, HeaderValue, StatusCode},
+    Json,
+};
+use diesel::{
+    dsl,
+    expression::{expression_types::NotSelectable, BoxableExpression},
+    pg::Pg,
+    query_dsl::methods::{LimitDsl, OffsetDsl},
+    sql_types::{Bool, Nullable},
+    ExpressionMethods, NullableExpressionMethods,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    models::{ErrorResponse, WithRelations},
+};
+
+/// A type-erased `WHERE` fragment that can be applied to a boxed query over `QS`.
+pub type BoxedCondition<QS> = Box<dyn BoxableExpression<QS, Pg, SqlType = Nullable<Bool>>>;
+
+/// A type-erased `ORDER BY` term for a boxed query over `QS`.
+pub type BoxedOrder<QS> = Box<dyn BoxableExpression<QS, Pg, SqlType = NotSelectable>>;
+
+/// Page size used when `_page` is given without `_limit`, as in json-server.
+pub const DEFAULT_PAGE_LIMIT: i64 = 10;
+
+/// Largest page a single request can ask for; bigger `_limit`, `limit` and
+/// `_start`/`_end` ranges are clamped to it.
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gte,
+    Lte,
+    Like,
+}
+
+impl FilterOp {
+    /// Splits a query key such as `id_ne` into its field name and operator.
+    pub fn split_key(key: &str) -> (&str, FilterOp) {
+        const SUFFIXES: [(&str, FilterOp); 4] = [
+            ("_gte", FilterOp::Gte),
+            ("_lte", FilterOp::Lte),
+            ("_ne", FilterOp::Ne),
+            ("_like", FilterOp::Like),
+        ];
+
+        for (suffix, op) in SUFFIXES {
+            if let Some(field) = key.strip_suffix(suffix) {
+                if !field.is_empty() {
+                    return (field, op);
+                }
+            }
+        }
+
+        (key, FilterOp::Eq)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FilterOp::Eq => "eq",
+            FilterOp::Ne => "ne",
+            FilterOp::Gte => "gte",
+            FilterOp::Lte => "lte",
+            FilterOp::Like => "like",
+        }
+    }
+}
+
+/// One field/operator pair from the query string. Repeated keys are collected
+/// into `values` and matched as alternatives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldFilter {
+    pub field: String,
+    pub op: FilterOp,
+    pub values: Vec<String>,
+}
+
+/// One `_sort` field with its matching `_order` direction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+/// Related records requested with `_embed` (children) and `_expand` (parents).
+/// Both accept repeated keys and comma-separated names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Relations {
+    pub embed: Vec<String>,
+    pub expand: Vec<String>,
+}
+
+impl Relations {
+    fn push(names: &mut Vec<String>, value: &str) {
+        names.extend(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string),
+        );
+    }
+
+    /// Rejects relation names the resource does not know about.
+    pub fn validate(
+        &self,
+        embeds: &'static [&'static str],
+        expands: &'static [&'static str],
+    ) -> Result<(), QueryError> {
+        for (param, names, allowed) in [
+            ("_embed", &self.embed, embeds),
+            ("_expand", &self.expand, expands),
+        ] {
+            if let Some(name) = names.iter().find(|name| !allowed.contains(&name.as_str())) {
+                return Err(QueryError::UnknownRelation {
+                    param,
+                    name: name.clone(),
+                    allowed,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn embeds(&self, name: &str) -> bool {
+        self.embed.iter().any(|embed| embed == name)
+    }
+
+    pub fn expands(&self, name: &str) -> bool {
+        self.expand.iter().any(|expand| expand == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pagination {
+    /// `_page`/`_limit`: 1-based pages of `limit` rows.
+    Page { page: i64, limit: i64 },
+    /// `_start`/`_end` (or `_start`/`_limit`): the `[start, end)` slice of the
+    /// ordered result; `end` is `None` when the slice is open-ended.
+    Slice { start: i64, end: Option<i64> },
+}
+
+impl Pagination {
+    /// Saturates instead of overflowing; [`ListQuery::from_pairs`] rejects
+    /// pages whose offset does not fit.
+    pub fn offset(self) -> i64 {
+        match self {
+            Pagination::Page { page, limit } => (page - 1).saturating_mul(limit),
+            Pagination::Slice { start, .. } => start,
+        }
+    }
+
+    pub fn limit(self) -> Option<i64> {
+        match self {
+            Pagination::Page { limit, .. } => Some(limit),
+            Pagination::Slice { start, end } => end.map(|end| (end - start).max(0)),
+        }
+    }
+}
+
+/// `cursor`/`limit`: keyset pagination over `(created_at, id)`. Unlike
+/// [`Pagination`] it never skips rows, so it stays fast on large tables and
+/// stable while rows are being inserted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyset {
+    /// The raw `cursor` token; `None` starts from the beginning.
+    pub cursor: Option<String>,
+    pub limit: i64,
+}
+
+/// Position after the last row of a keyset page. Encoded as an opaque token
+/// signed with the [`CursorKey`], so clients cannot forge or edit it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn encode(&self, secret: &[u8]) -> String {
+        let payload = format!("{}:{}", self.created_at.timestamp_micros(), self.id);
+        let signature = cursor_mac(secret, payload.as_bytes()).finalize().into_bytes();
+
+        format!("{}.{}", URL_SAFE_NO_PAD.encode(payload), URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    pub fn decode(token: &str, secret: &[u8]) -> Result<Self, QueryError> {
+        let invalid = || QueryError::InvalidParameter {
+            name: "cursor".to_string(),
+            value: token.to_string(),
+        };
+
+        let (payload, signature) = token.split_once('.').ok_or_else(invalid)?;
+        let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| invalid())?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+        cursor_mac(secret, &payload)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+
+        let payload = String::from_utf8(payload).map_err(|_| invalid())?;
+        let (micros, id) = payload.split_once(':').ok_or_else(invalid)?;
+        let micros = micros.parse().map_err(|_| invalid())?;
+
+        Ok(Cursor {
+            created_at: DateTime::from_timestamp_micros(micros).ok_or_else(invalid)?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+fn cursor_mac(secret: &[u8], payload: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    mac
+}
+
+/// The key cursors are signed with.
+#[derive(Clone)]
+pub struct CursorKey(Vec<u8>);
+
+impl CursorKey {
+    pub fn new(secret: &[u8]) -> Self {
+        CursorKey(secret.to_vec())
+    }
+
+    /// Uses `CURSOR_SECRET` when set. Otherwise the key is derived from
+    /// `JWT_SECRET`, so cursors are never signed with the access token key.
+    pub fn from_config(config: &Config) -> Self {
+        match &config.cursor_secret {
+            Some(secret) => CursorKey::new(secret.as_bytes()),
+            None => CursorKey(
+                Sha256::new()
+                    .chain_update(b"pagination-cursor:")
+                    .chain_update(config.jwt_secret.as_bytes())
+                    .finalize()
+                    .to_vec(),
+            ),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListQuery {
+    pub filters: Vec<FieldFilter>,
+    pub sort: Vec<SortKey>,
+    pub pagination: Option<Pagination>,
+    pub keyset: Option<Keyset>,
+    pub relations: Relations,
+    /// Free-text search from `q`, for resources that support it.
+    pub search: Option<String>,
+    path: String,
+    pairs: Vec<(String, String)>,
+}
+
+impl ListQuery {
+    pub fn from_pairs(pairs: Vec<(String, String)>) -> Result<Self, QueryError> {
+        let mut query = ListQuery::default();
+        let mut page = None;
+        let mut limit = None;
+        let mut start = None;
+        let mut end = None;
+        let mut sort_fields = None;
+        let mut sort_orders = None;
+        let mut cursor = None;
+        let mut cursor_limit = None;
+
+        for (key, value) in &pairs {
+            match key.as_str() {
+                "_sort" => sort_fields = Some(value.as_str()),
+                "_order" => sort_orders = Some(value.as_str()),
+                "_embed" => Relations::push(&mut query.relations.embed, value),
+                "_expand" => Relations::push(&mut query.relations.expand, value),
+                "_page" => page = Some(parse_param(key, value, 1)?),
+                "_limit" => limit = Some(parse_param(key, value, 1)?.min(MAX_PAGE_LIMIT)),
+                "_start" => start = Some(parse_param(key, value, 0)?),
+                "_end" => end = Some(parse_param(key, value, 0)?),
+                "cursor" => cursor = Some(value.clone()).filter(|cursor| !cursor.is_empty()),
+                "limit" => cursor_limit = Some(parse_param(key, value, 1)?.min(MAX_PAGE_LIMIT)),
+                "q" => query.search = Some(value.trim().to_string()).filter(|q| !q.is_empty()),
+                // Other `_`-prefixed keys are json-server control parameters, not fields
+                key if key.starts_with('_') => {}
+                key => {
+                    let (field, op) = FilterOp::split_key(key);
+                    query.push_filter(field, op, value.clone());
+                }
+            }
+        }
+
+        if let Some(sort_fields) = sort_fields {
+            query.sort = parse_sort(sort_fields, sort_orders.unwrap_or_default())?;
+        }
+
+        query.pagination = match (page, start, end, limit) {
+            (Some(page), _, _, limit) => {
+                let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+                if (page - 1).checked_mul(limit).is_none() {
+                    return Err(QueryError::InvalidParameter {
+                        name: "_page".to_string(),
+                        value: page.to_string(),
+                    });
+                }
+                Some(Pagination::Page { page, limit })
+            }
+            (None, None, None, None) => None,
+            (None, start, end, limit) => {
+                let start = start.unwrap_or(0);
+                let end = match (end, limit) {
+                    (Some(end), _) => Some(end),
+                    (None, Some(limit)) => Some(start.checked_add(limit).ok_or_else(|| {
+                        QueryError::InvalidParameter {
+                            name: "_start".to_string(),
+                            value: start.to_string(),
+                        }
+                    })?),
+                    (None, None) => None,
+                };
+                let end = end.map(|end| end.min(start.saturating_add(MAX_PAGE_LIMIT)));
+                Some(Pagination::Slice { start, end })
+            }
+        };
+
+        if pairs.iter().any(|(key, _)| key == "cursor" || key == "limit") {
+            // Keyset pages follow creation order, so they cannot be combined
+            // with sorting or with offset pagination
+            let conflict = pairs
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .find(|key| ["_sort", "_page", "_start", "_end", "_limit"].contains(key));
+            if let Some(conflict) = conflict {
+                return Err(QueryError::ConflictingParameters {
+                    name: conflict.to_string(),
+                    other: "cursor",
+                });
+            }
+
+            query.keyset = Some(Keyset {
+                cursor,
+                limit: cursor_limit.unwrap_or(DEFAULT_PAGE_LIMIT),
+            });
+        }
+        query.pairs = pairs;
+
+        Ok(query)
+    }
+
+    /// Fails for resources that only support offset pagination.
+    pub fn reject_keyset(&self) -> Result<(), QueryError> {
+        match self.keyset {
+            Some(_) => Err(QueryError::UnsupportedParameter { name: "cursor" }),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds an equality filter, e.g. to scope a nested route to its parent.
+    pub fn with_filter(mut self, field: &str, value: impl ToString) -> Self {
+        self.push_filter(field, FilterOp::Eq, value.to_string());
+        self
+    }
+
+    fn push_filter(&mut self, field: &str, op: FilterOp, value: String) {
+        match self
+            .filters
+            .iter_mut()
+            .find(|filter| filter.field == field && filter.op == op)
+        {
+            Some(filter) => filter.values.push(value),
+            None => self.filters.push(FieldFilter {
+                field: field.to_string(),
+                op,
+                values: vec![value],
+            }),
+        }
+    }
+
+    /// `X-Total-Count` and, in page mode, an RFC 8288 `Link` header with
+    /// `first`/`prev`/`next`/`last` relations relative to the request path.
+    pub fn response_headers(&self, total_count: Option<i64>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let Some(total_count) = total_count else {
+            return headers;
+        };
+
+        headers.insert("x-total-count", HeaderValue::from(total_count));
+
+        if let Some(Pagination::Page { page, limit }) = self.pagination {
+            let last = (total_count / limit + i64::from(total_count % limit != 0)).max(1);
+            let mut links = vec![(1, "first")];
+            if page > 1 {
+                links.push(((page - 1).min(last), "prev"));
+            }
+            if page < last {
+                links.push((page + 1, "next"));
+            }
+            links.push((last, "last"));
+
+            let link = links
+                .into_iter()
+                .map(|(target, rel)| format!("<{}>; rel=\"{}\"", self.page_url(target), rel))
+                .collect::<Vec<_>>()
+                .join(", ");
+            if let Ok(value) = HeaderValue::from_str(&link) {
+                headers.insert("link", value);
+            }
+        }
+
+        headers
+    }
+
+    /// `X-Next-Cursor` and a `Link` header with the `next` relation for a
+    /// keyset page; empty on the last page.
+    pub fn cursor_headers(&self, next_cursor: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let Some(next_cursor) = next_cursor else {
+            return headers;
+        };
+
+        if let Ok(value) = HeaderValue::from_str(next_cursor) {
+            headers.insert("x-next-cursor", value);
+        }
+        let link = format!("<{}>; rel=\"next\"", self.url_with("cursor", next_cursor.to_string()));
+        if let Ok(value) = HeaderValue::from_str(&link) {
+            headers.insert("link", value);
+        }
+
+        headers
+    }
+
+    fn page_url(&self, page: i64) -> String {
+        self.url_with("_page", page.to_string())
+    }
+
+    /// The request URL with `key` set to `value`.
+    fn url_with(&self, key: &str, value: String) -> String {
+        let mut pairs: Vec<(&str, String)> = self
+            .pairs
+            .iter()
+            .filter(|(other, _)| other != key)
+            .map(|(key, value)| (key.as_str(), value.clone()))
+            .collect();
+        pairs.push((key, value));
+
+        let query = serde_urlencoded::to_string(&pairs).unwrap_or_default();
+        format!("{}?{}", self.path, query)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ListQuery {
+    type Rejection = (StatusCode, Json<ErrorResponse>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(pairs) = Query::<Vec<(String, String)>>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| invalid_query(rejection.body_text()))?;
+
+        let mut query = ListQuery::from_pairs(pairs).map_err(|err| invalid_query(err.to_string()))?;
+        query.path = parts.uri.path().to_string();
+
+        Ok(query)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Relations {
+    type Rejection = (StatusCode, Json<ErrorResponse>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(pairs) = Query::<Vec<(String, String)>>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| invalid_query(rejection.body_text()))?;
+
+        let mut relations = Relations::default();
+        for (key, value) in &pairs {
+            match key.as_str() {
+                "_embed" => Relations::push(&mut relations.embed, value),
+                "_expand" => Relations::push(&mut relations.expand, value),
+                _ => {}
+            }
+        }
+
+        Ok(relations)
+    }
+}
+
+fn invalid_query(message: String) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: "invalid_query".to_string(),
+            message,
+        }),
+    )
+}
+
+/// Pairs comma-separated `_sort` fields with `_order` directions by position;
+/// fields without a direction sort ascending.
+fn parse_sort(fields: &str, orders: &str) -> Result<Vec<SortKey>, QueryError> {
+    let mut orders = orders.split(',').map(str::trim);
+
+    fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            let order = orders.next().unwrap_or_default();
+            let descending = match order.to_ascii_lowercase().as_str() {
+                "" | "asc" => false,
+                "desc" => true,
+                _ => {
+                    return Err(QueryError::InvalidParameter {
+                        name: "_order".to_string(),
+                        value: order.to_string(),
+                    })
+                }
+            };
+
+            Ok(SortKey {
+                field: field.to_string(),
+                descending,
+            })
+        })
+        .collect()
+}
+
+fn parse_param(name: &str, raw: &str, min: i64) -> Result<i64, QueryError> {
+    match raw.parse::<i64>() {
+        Ok(value) if value >= min => Ok(value),
+        _ => Err(QueryError::InvalidParameter {
+            name: name.to_string(),
+            value: raw.to_string(),
+        }),
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum QueryError {
+    #[error("Unknown field `{field}`. Allowed fields: {}", allowed.join(", "))]
+    UnknownField {
+        field: String,
+        allowed: &'static [&'static str],
+    },
+    #[error("Invalid value `{value}` for field `{field}`")]
+    InvalidValue { field: String, value: String },
+    #[error("Operator `_{}` is not supported for field `{field}`", op.as_str())]
+    UnsupportedOperator { field: String, op: FilterOp },
+    #[error("Invalid value `{value}` for parameter `{name}`")]
+    InvalidParameter { name: String, value: String },
+    #[error("Parameter `{name}` cannot be combined with `{other}`")]
+    ConflictingParameters { name: String, other: &'static str },
+    #[error("Parameter `{name}` is not supported by this resource")]
+    UnsupportedParameter { name: &'static str },
+    #[error("Unknown sort field `{field}`. Allowed fields: {}", allowed.join(", "))]
+    UnknownSortField {
+        field: String,
+        allowed: &'static [&'static str],
+    },
+    #[error("Unknown `{param}` relation `{name}`. Allowed relations: {}", allowed.join(", "))]
+    UnknownRelation {
+        param: &'static str,
+        name: String,
+        allowed: &'static [&'static str],
+    },
+}
+
+impl QueryError {
+    pub fn unknown_field(field: &str, allowed: &'static [&'static str]) -> Self {
+        QueryError::UnknownField {
+            field: field.to_string(),
+            allowed,
+        }
+    }
+
+    pub fn unknown_sort_field(field: &str, allowed: &'static [&'static str]) -> Self {
+        QueryError::UnknownSortField {
+            field: field.to_string(),
+            allowed,
+        }
+    }
+}
+
+/// One page of a list endpoint; `total_count` is only computed when the
+/// request asked for pagination.
+#[derive(Debug)]
+pub struct ListPage<T> {
+    pub items: Vec<T>,
+    pub total_count: Option<i64>,
+    /// Token for the following keyset page, if there is one.
+    pub next_cursor: Option<String>,
+}
+
+/// Error returned by service functions that execute a [`ListQuery`].
+#[derive(Debug, Error)]
+pub enum ListError {
+    #[error(transparent)]
+    Query(#[from] QueryError),
+    #[error(transparent)]
+    Database(#[from] diesel::result::Error),
+}
+
+/// Attaches `children` to their parents under `name` as an array, matching
+/// `parent_id(item)` against `child_parent_id(child)`. Parents without
+/// children get an empty array, as in json-server.
+pub fn embed_children<T, C: Serialize>(
+    items: &mut [WithRelations<T>],
+    name: &str,
+    children: Vec<C>,
+    parent_id: impl Fn(&T) -> Uuid,
+    child_parent_id: impl Fn(&C) -> Uuid,
+) {
+    let mut grouped: HashMap<Uuid, Vec<Value>> = HashMap::new();
+    for child in children {
+        grouped
+            .entry(child_parent_id(&child))
+            .or_default()
+            .push(serde_json::to_value(child).unwrap_or_default());
+    }
+
+    for item in items {
+        let children = grouped.remove(&parent_id(&item.item)).unwrap_or_default();
+        item.related.insert(name.to_string(), Value::Array(children));
+    }
+}
+
+/// Attaches the parent referenced by `parent_ref(item)` under `name`.
+pub fn expand_parents<T, P: Serialize>(
+    items: &mut [WithRelations<T>],
+    name: &str,
+    parents: Vec<P>,
+    parent_ref: impl Fn(&T) -> Uuid,
+    parent_id: impl Fn(&P) -> Uuid,
+) {
+    let parents: HashMap<Uuid, Value> = parents
+        .into_iter()
+        .map(|parent| (parent_id(&parent), serde_json::to_value(parent).unwrap_or_default()))
+        .collect();
+
+    for item in items {
+        let parent = parents.get(&parent_ref(&item.item)).cloned().unwrap_or_default();
+        item.related.insert(name.to_string(), parent);
+    }
+}
+
+/// Distinct values of `key` across `items`, for a single `IN (...)` batch query.
+pub fn distinct_ids<T>(items: &[WithRelations<T>], key: impl Fn(&T) -> Uuid) -> Vec<Uuid> {
+    let mut ids: Vec<Uuid> = items.iter().map(|item| key(&item.item)).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Builds the [`BoxedOrder`] term sorting by `column` in the direction of `key`.
+/// The column is wrapped in `Nullable` (a no-op in SQL) so that columns of
+/// left-joined tables can be used too.
+pub fn sort_by<QS, C>(column: C, key: &SortKey) -> BoxedOrder<QS>
+where
+    C: NullableExpressionMethods,
+    dsl::Nullable<C>: ExpressionMethods,
+    dsl::Asc<dsl::Nullable<C>>: BoxableExpression<QS, Pg, SqlType = NotSelectable> + 'static,
+    dsl::Desc<dsl::Nullable<C>>: BoxableExpression<QS, Pg, SqlType = NotSelectable> + 'static,
+{
+    if key.descending {
+        Box::new(column.nullable().desc())
+    } else {
+        Box::new(column.nullable().asc())
+    }
+}
+
+/// Applies `OFFSET`/`LIMIT` for the requested pagination, if any.
+pub fn paginate<Q>(query: Q, pagination: Option<Pagination>) -> Q
+where
+    Q: OffsetDsl<Output = Q> + LimitDsl<Output = Q>,
+{
+    let Some(pagination) = pagination else {
+        return query;
+    };
+
+    let query = query.offset(pagination.offset());
+    match pagination.limit() {
+        Some(limit) => query.limit(limit),
+        None => query,
+    }
+}
+
+/// Parses a raw query value into the Rust type bound to a column.
+pub fn parse_value<T: std::str::FromStr>(field: &str, raw: &str) -> Result<T, QueryError> {
+    raw.parse().map_err(|_| QueryError::InvalidValue {
+        field: field.to_string(),
+        value: raw.to_string(),
+    })
+}
+
+/// Turns a `_like` value into a case-insensitive substring pattern, escaping
+/// the `LIKE` wildcards so the value is matched literally.
+pub fn like_pattern(raw: &str) -> String {
+    let escaped = raw
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Turns free text into a Postgres `tsquery` matching every word as a prefix,
+/// e.g. `roma crona` becomes `roma:* & crona:*`. Punctuation only separates
+/// words, so user input can never produce tsquery syntax.
+pub fn prefix_tsquery(raw: &str) -> Option<String> {
+    let terms: Vec<String> = raw
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("{}:*", word.to_lowercase()))
+        .collect();
+
+    (!terms.is_empty()).then(|| terms.join(" & "))
+}
+
+/// Combines per-value conditions: alternatives are OR'ed, except for `_ne`,
+/// where every listed value must differ.
+pub fn combine_conditions<QS: 'static>(
+    op: FilterOp,
+    conditions: Vec<BoxedCondition<QS>>,
+) -> BoxedCondition<QS> {
+    use diesel::BoolExpressionMethods;
+
+    conditions
+        .into_iter()
+        .reduce(|acc, next| match op {
+            FilterOp::Ne => Box::new(acc.and(next)),
+            _ => Box::new(acc.or(next)),
+        })
+        .expect("filters always carry at least one value")
+}
+
+/// Builds a [`BoxedCondition`] for a non-text column. Values are parsed into
+/// `$ty`; `_like` is rejected.
+macro_rules! value_condition {
+    ($column:expr, $filter:expr, $ty:ty) => {{
+        use diesel::{ExpressionMethods, NullableExpressionMethods};
+        use $crate::query::{combine_conditions, parse_value, BoxedCondition, FilterOp, QueryError};
+
+        let filter = $filter;
+        let mut conditions = Vec::with_capacity(filter.values.len());
+        for raw in &filter.values {
+            let value: $ty = parse_value(&filter.field, raw)?;
+            let condition: BoxedCondition<_> = match filter.op {
+                FilterOp::Eq => Box::new($column.eq(value).nullable()),
+                FilterOp::Ne => Box::new($column.ne(value).nullable()),
+                FilterOp::Gte => Box::new($column.ge(value).nullable()),
+                FilterOp::Lte => Box::new($column.le(value).nullable()),
+                FilterOp::Like => {
+                    return Err(QueryError::UnsupportedOperator {
+                        field: filter.field.clone(),
+                        op: filter.op,
+                    })
+                }
+            };
+            conditions.push(condition);
+        }
+        Ok(combine_conditions(filter.op, conditions))
+    }};
+}
+
+/// Builds a [`BoxedCondition`] for a (possibly nullable) text column.
+/// `_like` is a case-insensitive substring match.
+macro_rules! text_condition {
+    ($column:expr, $filter:expr) => {{
+        use diesel::{ExpressionMethods, NullableExpressionMethods, PgTextExpressionMethods};
+        use $crate::query::{combine_conditions, like_pattern, BoxedCondition, FilterOp};
+
+        let filter = $filter;
+        let mut conditions = Vec::with_capacity(filter.values.len());
+        for raw in &filter.values {
+            let value = raw.clone();
+            let condition: BoxedCondition<_> = match filter.op {
+                FilterOp::Eq => Box::new($column.eq(value).nullable()),
+                FilterOp::Ne => Box::new($column.ne(value).nullable()),
+                FilterOp::Gte => Box::new($column.ge(value).nullable()),
+                FilterOp::Lte => Box::new($column.le(value).nullable()),
+                FilterOp::Like => Box::new($column.ilike(like_pattern(&value)).nullable()),
+            };
+            conditions.push(condition);
+        }
+        Ok(combine_conditions(filter.op, conditions))
+    }};
+}
+
+pub(crate) use text_condition;
+pub(crate) use value_condition;
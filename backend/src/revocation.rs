@@ -0,0 +1,104 @@
+//! Server-side revocation of access tokens.
+//!
+//! Access tokens are checked on every request, so revocations are served from
+//! memory: [`RevocationList::sync`] loads them from Postgres at startup and
+//! periodically afterwards, while revocations made by this instance are
+//! applied to the cache immediately. Other instances see them after their next
+//! sync, at most [`SYNC_INTERVAL_SECS`] later.
+//!
+//! Access tokens name their session in `sid`, so revoking a session's refresh
+//! tokens revokes the access tokens issued in it as well.
+
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::Claims,
+    services::auth_service::{self, ACCESS_TOKEN_TTL_MINUTES},
+};
+
+/// How often revocations are reloaded from Postgres.
+pub const SYNC_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Default)]
+pub struct RevocationList {
+    /// Revoked token ids with their expiry.
+    tokens: RwLock<HashMap<Uuid, DateTime<Utc>>>,
+    /// Users whose tokens issued before the cutoff are all revoked.
+    cutoffs: RwLock<HashMap<Uuid, DateTime<Utc>>>,
+    /// Revoked sessions (refresh token families).
+    families: RwLock<HashSet<Uuid>>,
+}
+
+impl RevocationList {
+    pub fn is_revoked(&self, claims: &Claims) -> bool {
+        let (Ok(jti), Ok(user_id)) = (claims.jti.parse::<Uuid>(), claims.sub.parse::<Uuid>()) else {
+            return true;
+        };
+
+        if self.tokens.read().unwrap().contains_key(&jti) {
+            return true;
+        }
+        if claims
+            .sid
+            .parse::<Uuid>()
+            .is_ok_and(|family_id| self.families.read().unwrap().contains(&family_id))
+        {
+            return true;
+        }
+
+        // Tokens without `iat_micros` only have whole seconds, so the whole
+        // second of the cutoff is revoked for them.
+        self.cutoffs.read().unwrap().get(&user_id).is_some_and(|cutoff| match claims.iat_micros {
+            Some(iat_micros) => iat_micros <= cutoff.timestamp_micros(),
+            None => (claims.iat as i64) <= cutoff.timestamp(),
+        })
+    }
+
+    pub fn revoke_token(&self, jti: Uuid, expires_at: DateTime<Utc>) {
+        self.tokens.write().unwrap().insert(jti, expires_at);
+    }
+
+    pub fn revoke_all(&self, user_id: Uuid, cutoff: DateTime<Utc>) {
+        self.cutoffs.write().unwrap().insert(user_id, cutoff);
+    }
+
+    pub fn revoke_family(&self, family_id: Uuid) {
+        self.families.write().unwrap().insert(family_id);
+    }
+
+    /// Replaces the cache with the revocations stored in Postgres. Expired
+    /// tokens are dropped, and so are cutoffs and sessions revoked before any
+    /// live token was issued.
+    pub async fn sync(&self, pool: &DbPool) -> Result<(), diesel::result::Error> {
+        let tokens = auth_service::get_revoked_tokens(pool).await?;
+        let since = Utc::now() - Duration::minutes(ACCESS_TOKEN_TTL_MINUTES);
+        let cutoffs = auth_service::get_token_cutoffs(pool, since).await?;
+        let families = auth_service::get_revoked_families(pool, since).await?;
+
+        *self.tokens.write().unwrap() = tokens.into_iter().collect();
+        *self.cutoffs.write().unwrap() = cutoffs.into_iter().collect();
+        *self.families.write().unwrap() = families.into_iter().collect();
+
+        Ok(())
+    }
+
+    /// Keeps the cache in sync in the background for the life of the process.
+    pub fn spawn_sync(self: Arc<Self>, pool: DbPool) {
+        tokio::spawn(async move {
+            let period = std::time::Duration::from_secs(SYNC_INTERVAL_SECS);
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                if let Err(err) = self.sync(&pool).await {
+                    tracing::warn!("Failed to sync token revocations: {}", err);
+                }
+            }
+        });
+    }
+}
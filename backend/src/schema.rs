@@ -1,5 +1,20 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_keys (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Varchar,
+        prefix -> Varchar,
+        secret_hash -> Varchar,
+        scopes -> Array<Varchar>,
+        expires_at -> Nullable<Timestamptz>,
+        last_used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     addresses (id) {
         id -> Uuid,
@@ -13,6 +28,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    albums (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        title -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     auth_users (id) {
         id -> Uuid,
@@ -21,6 +46,21 @@ diesel::table! {
         password_hash -> Varchar,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        tokens_revoked_at -> Nullable<Timestamptz>,
+        email_verified_at -> Nullable<Timestamptz>,
+        role -> Varchar,
+    }
+}
+
+diesel::table! {
+    comments (id) {
+        id -> Uuid,
+        post_id -> Uuid,
+        name -> Varchar,
+        email -> Varchar,
+        body -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -34,6 +74,199 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    deleted_accounts (user_id) {
+        user_id -> Uuid,
+        deleted_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    email_verification_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        email -> Varchar,
+        token_hash -> Varchar,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    login_attempts (id) {
+        id -> Uuid,
+        email -> Varchar,
+        user_id -> Nullable<Uuid>,
+        ip_address -> Varchar,
+        succeeded -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    magic_link_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        email -> Varchar,
+        token_hash -> Varchar,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    mfa_challenges (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Varchar,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    mfa_recovery_codes (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Varchar,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    oauth_authorization_codes (id) {
+        id -> Uuid,
+        client_id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Varchar,
+        redirect_uri -> Varchar,
+        scopes -> Array<Varchar>,
+        code_challenge -> Varchar,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        family_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    oauth_clients (id) {
+        id -> Uuid,
+        owner_id -> Uuid,
+        name -> Varchar,
+        client_secret_hash -> Nullable<Varchar>,
+        redirect_uris -> Array<Varchar>,
+        scopes -> Array<Varchar>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    oauth_consents (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        client_id -> Uuid,
+        scopes -> Array<Varchar>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    password_reset_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Varchar,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    photos (id) {
+        id -> Uuid,
+        album_id -> Uuid,
+        title -> Varchar,
+        url -> Varchar,
+        thumbnail_url -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    posts (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        title -> Varchar,
+        body -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        family_id -> Uuid,
+        token_hash -> Varchar,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        client_id -> Nullable<Uuid>,
+        scopes -> Nullable<Array<Varchar>>,
+    }
+}
+
+diesel::table! {
+    revoked_tokens (jti) {
+        jti -> Uuid,
+        user_id -> Uuid,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    todos (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        title -> Varchar,
+        completed -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    totp_credentials (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        secret_encrypted -> Varchar,
+        confirmed_at -> Nullable<Timestamptz>,
+        last_used_step -> Nullable<Int8>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Uuid,
@@ -44,15 +277,57 @@ diesel::table! {
         website -> Nullable<Varchar>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        owner_id -> Nullable<Uuid>,
     }
 }
 
 diesel::joinable!(addresses -> users (user_id));
+diesel::joinable!(api_keys -> auth_users (user_id));
+diesel::joinable!(albums -> users (user_id));
+diesel::joinable!(comments -> posts (post_id));
 diesel::joinable!(companies -> users (user_id));
+diesel::joinable!(email_verification_tokens -> auth_users (user_id));
+diesel::joinable!(login_attempts -> auth_users (user_id));
+diesel::joinable!(magic_link_tokens -> auth_users (user_id));
+diesel::joinable!(mfa_challenges -> auth_users (user_id));
+diesel::joinable!(mfa_recovery_codes -> auth_users (user_id));
+diesel::joinable!(oauth_authorization_codes -> auth_users (user_id));
+diesel::joinable!(oauth_authorization_codes -> oauth_clients (client_id));
+diesel::joinable!(oauth_clients -> auth_users (owner_id));
+diesel::joinable!(oauth_consents -> auth_users (user_id));
+diesel::joinable!(oauth_consents -> oauth_clients (client_id));
+diesel::joinable!(password_reset_tokens -> auth_users (user_id));
+diesel::joinable!(photos -> albums (album_id));
+diesel::joinable!(posts -> users (user_id));
+diesel::joinable!(refresh_tokens -> auth_users (user_id));
+diesel::joinable!(refresh_tokens -> oauth_clients (client_id));
+diesel::joinable!(revoked_tokens -> auth_users (user_id));
+diesel::joinable!(todos -> users (user_id));
+diesel::joinable!(totp_credentials -> auth_users (user_id));
+diesel::joinable!(users -> auth_users (owner_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     addresses,
+    api_keys,
+    albums,
     auth_users,
+    comments,
     companies,
+    deleted_accounts,
+    email_verification_tokens,
+    login_attempts,
+    magic_link_tokens,
+    mfa_challenges,
+    mfa_recovery_codes,
+    oauth_authorization_codes,
+    oauth_clients,
+    oauth_consents,
+    password_reset_tokens,
+    photos,
+    posts,
+    refresh_tokens,
+    revoked_tokens,
+    todos,
+    totp_credentials,
     users,
 ); 
\ No newline at end of file
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::{Album, CreateAlbumRequest, UpdateAlbumRequest, WithRelations},
+    query::{
+        distinct_ids, embed_children, expand_parents, sort_by, text_condition, value_condition,
+        BoxedCondition, BoxedOrder, FieldFilter, ListError, QueryError, Relations, SortKey,
+    },
+    schema::albums,
+    services::{photo_service, resource::resource_queries, user_service},
+};
+
+const ALBUM_FIELDS: &[&str] = &["id", "user_id", "title", "created_at", "updated_at"];
+const ALBUM_EMBEDS: &[&str] = &["photos"];
+const ALBUM_EXPANDS: &[&str] = &["user"];
+
+fn album_condition(filter: &FieldFilter) -> Result<BoxedCondition<albums::table>, QueryError> {
+    match filter.field.as_str() {
+        "id" => value_condition!(albums::id, filter, Uuid),
+        "user_id" => value_condition!(albums::user_id, filter, Uuid),
+        "title" => text_condition!(albums::title, filter),
+        "created_at" => value_condition!(albums::created_at, filter, DateTime<Utc>),
+        "updated_at" => value_condition!(albums::updated_at, filter, DateTime<Utc>),
+        field => Err(QueryError::unknown_field(field, ALBUM_FIELDS)),
+    }
+}
+
+fn album_order(key: &SortKey) -> Result<BoxedOrder<albums::table>, QueryError> {
+    match key.field.as_str() {
+        "id" => Ok(sort_by(albums::id, key)),
+        "user_id" => Ok(sort_by(albums::user_id, key)),
+        "title" => Ok(sort_by(albums::title, key)),
+        "created_at" => Ok(sort_by(albums::created_at, key)),
+        "updated_at" => Ok(sort_by(albums::updated_at, key)),
+        field => Err(QueryError::unknown_sort_field(field, ALBUM_FIELDS)),
+    }
+}
+
+resource_queries! {
+    table: albums,
+    model: Album,
+    condition: album_condition,
+    order: album_order,
+    get_all: get_all_albums,
+    get_by_id: get_album_by_id,
+    get_with_relations: get_album_with_relations,
+    get_by_ids: get_albums_by_ids,
+    get_by_parent_ids: get_albums_by_user_ids(user_id),
+    delete: delete_album,
+}
+
+/// Attaches the `_embed`/`_expand` relations to each album, one batch query per relation.
+pub async fn with_relations(
+    pool: &DbPool,
+    albums: Vec<Album>,
+    relations: &Relations,
+) -> Result<Vec<WithRelations<Album>>, ListError> {
+    relations.validate(ALBUM_EMBEDS, ALBUM_EXPANDS)?;
+
+    let mut items: Vec<WithRelations<Album>> =
+        albums.into_iter().map(WithRelations::from).collect();
+
+    if relations.embeds("photos") {
+        let ids = distinct_ids(&items, |album| album.id);
+        let photos = photo_service::get_photos_by_album_ids(pool, &ids).await?;
+        embed_children(&mut items, "photos", photos, |album| album.id, |child| child.album_id);
+    }
+
+    if relations.expands("user") {
+        let ids = distinct_ids(&items, |album| album.user_id);
+        let users = user_service::get_users_by_ids(pool, &ids).await?;
+        expand_parents(&mut items, "user", users, |album| album.user_id, |parent| parent.id);
+    }
+
+    Ok(items)
+}
+
+pub async fn create_album(
+    pool: &DbPool,
+    album_data: &CreateAlbumRequest,
+) -> Result<Album, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    diesel::insert_into(albums::table)
+        .values((
+            albums::id.eq(Uuid::new_v4()),
+            albums::user_id.eq(album_data.user_id),
+            albums::title.eq(&album_data.title),
+            albums::created_at.eq(now),
+            albums::updated_at.eq(now),
+        ))
+        .returning(Album::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Replaces every writable field of an album (`PUT` semantics).
+pub async fn replace_album(
+    pool: &DbPool,
+    album_id: Uuid,
+    album_data: &CreateAlbumRequest,
+) -> Result<Album, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(albums::table.find(album_id))
+        .set((
+            albums::user_id.eq(album_data.user_id),
+            albums::title.eq(&album_data.title),
+            albums::updated_at.eq(Utc::now()),
+        ))
+        .returning(Album::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Updates only the fields present in the request (`PATCH` semantics).
+pub async fn update_album(
+    pool: &DbPool,
+    album_id: Uuid,
+    album_data: &UpdateAlbumRequest,
+) -> Result<Album, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(albums::table.find(album_id))
+        .set((
+            album_data.user_id.map(|user_id| albums::user_id.eq(user_id)),
+            album_data.title.as_ref().map(|title| albums::title.eq(title)),
+            albums::updated_at.eq(Utc::now()),
+        ))
+        .returning(Album::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
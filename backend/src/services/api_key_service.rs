@@ -0,0 +1,149 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::{ApiKey, AuthUser, NewApiKey, Scope},
+    schema::{api_keys, auth_users},
+    services::auth_service::{generate_token, hash_token},
+};
+
+/// Marks API keys, so they are easy to spot in code and secret scanners.
+pub const API_KEY_PREFIX: &str = "ck";
+
+const PREFIX_LENGTH: usize = 8;
+const PREFIX_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// `last_used_at` is written at most this often per key, so busy clients do
+/// not cause a write on every request.
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+/// A fresh key `ck_<prefix>_<secret>` with its prefix and secret. The prefix
+/// never contains `_`, so the key splits unambiguously.
+pub fn generate_api_key() -> (String, String, String) {
+    let mut rng = rand::thread_rng();
+    let prefix: String = (0..PREFIX_LENGTH)
+        .map(|_| char::from(PREFIX_ALPHABET[rng.gen_range(0..PREFIX_ALPHABET.len())]))
+        .collect();
+    let secret = generate_token();
+    let key = format!("{}_{}_{}", API_KEY_PREFIX, prefix, secret);
+
+    (prefix, secret, key)
+}
+
+/// Splits a presented key into prefix and secret, or `None` if it is not
+/// shaped like one of ours.
+pub fn parse_api_key(key: &str) -> Option<(&str, &str)> {
+    let rest = key.strip_prefix(API_KEY_PREFIX)?.strip_prefix('_')?;
+    let (prefix, secret) = rest.split_once('_')?;
+    if prefix.len() != PREFIX_LENGTH || secret.is_empty() {
+        return None;
+    }
+
+    Some((prefix, secret))
+}
+
+/// Stores a new key and returns it together with the full key, which is not
+/// kept and cannot be shown again.
+pub async fn create_api_key(
+    pool: &DbPool,
+    user_id: Uuid,
+    name: &str,
+    scopes: Vec<Scope>,
+    expires_in_days: Option<i64>,
+) -> Result<(ApiKey, String), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let (prefix, secret, key) = generate_api_key();
+
+    let api_key = diesel::insert_into(api_keys::table)
+        .values(&NewApiKey {
+            id: Uuid::new_v4(),
+            user_id,
+            name: name.to_string(),
+            prefix,
+            secret_hash: hash_token(&secret),
+            scopes,
+            expires_at: expires_in_days.map(|days| Utc::now() + Duration::days(days)),
+        })
+        .returning(ApiKey::as_returning())
+        .get_result(&mut conn)
+        .await?;
+
+    Ok((api_key, key))
+}
+
+/// The user's keys, newest first, including expired ones.
+pub async fn get_api_keys(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<Vec<ApiKey>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    api_keys::table
+        .filter(api_keys::user_id.eq(user_id))
+        .order(api_keys::created_at.desc())
+        .select(ApiKey::as_select())
+        .load(&mut conn)
+        .await
+}
+
+/// Deletes one of the user's keys. Returns `false` if the user has no such key.
+pub async fn delete_api_key(
+    pool: &DbPool,
+    user_id: Uuid,
+    api_key_id: Uuid,
+) -> Result<bool, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    let deleted = diesel::delete(
+        api_keys::table
+            .filter(api_keys::id.eq(api_key_id))
+            .filter(api_keys::user_id.eq(user_id)),
+    )
+    .execute(&mut conn)
+    .await?;
+
+    Ok(deleted > 0)
+}
+
+/// The key and its account for a presented key, or `None` if it is unknown,
+/// malformed or expired. Records the use in `last_used_at`.
+pub async fn authenticate_api_key(
+    pool: &DbPool,
+    key: &str,
+) -> Result<Option<(ApiKey, AuthUser)>, diesel::result::Error> {
+    let Some((prefix, secret)) = parse_api_key(key) else {
+        return Ok(None);
+    };
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    let found: Option<(ApiKey, AuthUser)> = api_keys::table
+        .inner_join(auth_users::table)
+        .filter(api_keys::prefix.eq(prefix))
+        .filter(api_keys::secret_hash.eq(hash_token(secret)))
+        .filter(api_keys::expires_at.is_null().or(api_keys::expires_at.gt(now)))
+        .select((ApiKey::as_select(), auth_users::all_columns))
+        .first(&mut conn)
+        .await
+        .optional()?;
+
+    if let Some((api_key, _)) = &found {
+        let stale = now - Duration::seconds(LAST_USED_RESOLUTION_SECONDS);
+        diesel::update(
+            api_keys::table.filter(api_keys::id.eq(api_key.id)).filter(
+                api_keys::last_used_at
+                    .is_null()
+                    .or(api_keys::last_used_at.lt(stale)),
+            ),
+        )
+        .set(api_keys::last_used_at.eq(now))
+        .execute(&mut conn)
+        .await?;
+    }
+
+    Ok(found)
+}
@@ -1,13 +1,82 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
 use crate::{
     database::DbPool,
-    models::{AuthUser, NewAuthUser},
-    schema::auth_users,
+    models::{
+        AuthUser, NewAuthUser, NewEmailVerificationToken, NewMagicLinkToken,
+        NewPasswordResetToken, NewRefreshToken, NewRevokedToken, RefreshToken, Role, Scope,
+        UpdateProfileRequest,
+    },
+    schema::{
+        auth_users, deleted_accounts, email_verification_tokens, magic_link_tokens,
+        password_reset_tokens, refresh_tokens, revoked_tokens,
+    },
 };
 
+/// Lifetime of access tokens; clients renew them with `POST /auth/refresh`.
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+
+/// How long a refresh token can be exchanged before the user has to log in again.
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// How long a password reset link stays valid.
+pub const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+
+/// How long a sign-in link stays valid.
+pub const MAGIC_LINK_TTL_MINUTES: i64 = 15;
+
+/// How long an email verification link stays valid.
+pub const EMAIL_VERIFICATION_TTL_HOURS: i64 = 24;
+
+/// Result of presenting a refresh token to [`rotate_refresh_token`].
+#[derive(Debug)]
+pub enum RefreshOutcome {
+    /// The token was valid; it is now used up and `refresh_token` replaces it.
+    Rotated {
+        user_id: Uuid,
+        family_id: Uuid,
+        refresh_token: String,
+        /// Scopes granted to the OAuth client the token belongs to.
+        scopes: Option<Vec<Scope>>,
+    },
+    /// The token had already been rotated, so it was stolen or replayed; its
+    /// whole family has been revoked.
+    Reused { family_id: Uuid },
+    /// Unknown, expired or revoked token, or one issued to another client.
+    Invalid,
+}
+
+/// A random, URL-safe token with 256 bits of entropy.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Hash under which an opaque token is stored. Tokens are random, so a fast
+/// unsalted hash is enough to make a leaked table useless.
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Whether `token` is the one stored as `token_hash`. The hashes are compared
+/// in constant time, for tokens that are looked up by something else first.
+pub fn token_matches(token: &str, token_hash: &str) -> bool {
+    hash_token(token).as_bytes().ct_eq(token_hash.as_bytes()).into()
+}
+
 pub async fn create_user(
     pool: &DbPool,
     user: &NewAuthUser,
@@ -42,4 +111,623 @@ pub async fn get_user_by_id(
         .filter(auth_users::id.eq(user_id))
         .first(&mut conn)
         .await
-} 
\ No newline at end of file
+}
+
+/// Updates only the profile fields present in the request (`PATCH` semantics).
+/// A new email address has to be verified again.
+pub async fn update_user(
+    pool: &DbPool,
+    user_id: Uuid,
+    user_data: &UpdateProfileRequest,
+) -> Result<AuthUser, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(auth_users::table.find(user_id))
+        .set((
+            user_data.name.as_ref().map(|name| auth_users::name.eq(name)),
+            user_data.email.as_ref().map(|email| {
+                (
+                    auth_users::email.eq(email),
+                    auth_users::email_verified_at.eq(None::<DateTime<Utc>>),
+                )
+            }),
+            auth_users::updated_at.eq(Utc::now()),
+        ))
+        .get_result(&mut conn)
+        .await
+}
+
+pub async fn update_role(
+    pool: &DbPool,
+    user_id: Uuid,
+    role: Role,
+) -> Result<AuthUser, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    // Access tokens carry the role, so the old ones are cut off; refresh tokens
+    // stay valid and pick up the new role
+    diesel::update(auth_users::table.find(user_id))
+        .set((
+            auth_users::role.eq(role),
+            auth_users::tokens_revoked_at.eq(now),
+            auth_users::updated_at.eq(now),
+        ))
+        .get_result(&mut conn)
+        .await
+}
+
+/// Deletes the account; its refresh and revoked tokens go with it. Returns
+/// the cutoff its remaining access tokens are revoked with.
+pub async fn delete_user(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<DateTime<Utc>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    conn.transaction(|conn| {
+        async move {
+            let deleted = diesel::delete(auth_users::table.find(user_id))
+                .execute(conn)
+                .await?;
+
+            if deleted == 0 {
+                return Err(diesel::result::Error::NotFound);
+            }
+
+            diesel::insert_into(deleted_accounts::table)
+                .values((
+                    deleted_accounts::user_id.eq(user_id),
+                    deleted_accounts::deleted_at.eq(now),
+                ))
+                .execute(conn)
+                .await?;
+
+            Ok(now)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Issues a refresh token in `family_id`; pass a new id to start a family at login.
+pub async fn create_refresh_token(
+    pool: &DbPool,
+    user_id: Uuid,
+    family_id: Uuid,
+) -> Result<String, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token = generate_token();
+
+    diesel::insert_into(refresh_tokens::table)
+        .values(&NewRefreshToken {
+            id: Uuid::new_v4(),
+            user_id,
+            family_id,
+            token_hash: hash_token(&token),
+            expires_at: Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+            client_id: None,
+            scopes: None,
+        })
+        .execute(&mut conn)
+        .await?;
+
+    Ok(token)
+}
+
+/// Exchanges a refresh token for its successor. The presented row is locked,
+/// so two concurrent requests with the same token cannot both rotate it.
+///
+/// `client_id` is the OAuth client presenting the token, or `None` for
+/// `/auth/refresh`; tokens issued to anyone else are [`RefreshOutcome::Invalid`].
+pub async fn rotate_refresh_token(
+    pool: &DbPool,
+    token: &str,
+    client_id: Option<Uuid>,
+) -> Result<RefreshOutcome, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token_hash = hash_token(token);
+
+    conn.transaction(|conn| {
+        async move {
+            let now = Utc::now();
+            let current: Option<RefreshToken> = refresh_tokens::table
+                .filter(refresh_tokens::token_hash.eq(&token_hash))
+                .select(RefreshToken::as_select())
+                .for_update()
+                .first(conn)
+                .await
+                .optional()?;
+
+            let current = match current {
+                Some(current) if current.revoked_at.is_none() && current.client_id == client_id => {
+                    current
+                }
+                _ => return Ok(RefreshOutcome::Invalid),
+            };
+
+            if current.used_at.is_some() {
+                diesel::update(
+                    refresh_tokens::table
+                        .filter(refresh_tokens::family_id.eq(current.family_id))
+                        .filter(refresh_tokens::revoked_at.is_null()),
+                )
+                .set((
+                    refresh_tokens::revoked_at.eq(now),
+                    refresh_tokens::updated_at.eq(now),
+                ))
+                .execute(conn)
+                .await?;
+
+                return Ok(RefreshOutcome::Reused {
+                    family_id: current.family_id,
+                });
+            }
+
+            if current.expires_at <= now {
+                return Ok(RefreshOutcome::Invalid);
+            }
+
+            diesel::update(refresh_tokens::table.find(current.id))
+                .set((
+                    refresh_tokens::used_at.eq(now),
+                    refresh_tokens::updated_at.eq(now),
+                ))
+                .execute(conn)
+                .await?;
+
+            let refresh_token = generate_token();
+            diesel::insert_into(refresh_tokens::table)
+                .values(&NewRefreshToken {
+                    id: Uuid::new_v4(),
+                    user_id: current.user_id,
+                    family_id: current.family_id,
+                    token_hash: hash_token(&refresh_token),
+                    expires_at: now + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+                    client_id: current.client_id,
+                    scopes: current.scopes.clone(),
+                })
+                .execute(conn)
+                .await?;
+
+            Ok(RefreshOutcome::Rotated {
+                user_id: current.user_id,
+                family_id: current.family_id,
+                refresh_token,
+                scopes: current.scopes,
+            })
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Revokes the refresh tokens of one session (`family_id`) that are still usable.
+pub async fn revoke_refresh_family(
+    pool: &DbPool,
+    family_id: Uuid,
+) -> Result<(), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    diesel::update(
+        refresh_tokens::table
+            .filter(refresh_tokens::family_id.eq(family_id))
+            .filter(refresh_tokens::revoked_at.is_null()),
+    )
+    .set((
+        refresh_tokens::revoked_at.eq(now),
+        refresh_tokens::updated_at.eq(now),
+    ))
+    .execute(&mut conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Adds an access token to the revocation list until it expires.
+pub async fn revoke_token(
+    pool: &DbPool,
+    revoked: &NewRevokedToken,
+) -> Result<(), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::insert_into(revoked_tokens::table)
+        .values(revoked)
+        .on_conflict_do_nothing()
+        .execute(&mut conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Revokes every access and refresh token of the user issued so far and
+/// returns the cutoff recorded on `auth_users`.
+pub async fn revoke_all_tokens(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<DateTime<Utc>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    conn.transaction(|conn| {
+        async move {
+            revoke_sessions(conn, user_id, now).await?;
+            Ok(now)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Replaces the password hash and, like [`revoke_all_tokens`], ends every
+/// session of the user; returns the revocation cutoff.
+pub async fn set_password(
+    pool: &DbPool,
+    user_id: Uuid,
+    password_hash: &str,
+) -> Result<DateTime<Utc>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    conn.transaction(|conn| {
+        async move {
+            diesel::update(auth_users::table.find(user_id))
+                .set(auth_users::password_hash.eq(password_hash))
+                .execute(conn)
+                .await?;
+            revoke_sessions(conn, user_id, now).await?;
+            Ok(now)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Replaces the stored hash of an unchanged password, e.g. after upgrading
+/// its algorithm. Unlike [`set_password`] this keeps existing sessions.
+pub async fn update_password_hash(
+    pool: &DbPool,
+    user_id: Uuid,
+    password_hash: &str,
+) -> Result<(), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(auth_users::table.find(user_id))
+        .set(auth_users::password_hash.eq(password_hash))
+        .execute(&mut conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Issues a password reset token for the user; only its hash is stored.
+pub async fn create_password_reset_token(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<String, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token = generate_token();
+
+    diesel::insert_into(password_reset_tokens::table)
+        .values(&NewPasswordResetToken {
+            id: Uuid::new_v4(),
+            user_id,
+            token_hash: hash_token(&token),
+            expires_at: Utc::now() + Duration::minutes(PASSWORD_RESET_TTL_MINUTES),
+        })
+        .execute(&mut conn)
+        .await?;
+
+    Ok(token)
+}
+
+/// Consumes a password reset token and sets the new password. Returns the
+/// user and the revocation cutoff, or `None` if the token is unknown, used
+/// or expired.
+pub async fn reset_password(
+    pool: &DbPool,
+    token: &str,
+    password_hash: &str,
+) -> Result<Option<(Uuid, DateTime<Utc>)>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token_hash = hash_token(token);
+
+    conn.transaction(|conn| {
+        async move {
+            let now = Utc::now();
+            let user_id: Option<Uuid> = password_reset_tokens::table
+                .filter(password_reset_tokens::token_hash.eq(&token_hash))
+                .filter(password_reset_tokens::used_at.is_null())
+                .filter(password_reset_tokens::expires_at.gt(now))
+                .select(password_reset_tokens::user_id)
+                .for_update()
+                .first(conn)
+                .await
+                .optional()?;
+
+            let Some(user_id) = user_id else {
+                return Ok(None);
+            };
+
+            diesel::update(
+                password_reset_tokens::table
+                    .filter(password_reset_tokens::user_id.eq(user_id))
+                    .filter(password_reset_tokens::used_at.is_null()),
+            )
+            .set((
+                password_reset_tokens::used_at.eq(now),
+                password_reset_tokens::updated_at.eq(now),
+            ))
+            .execute(conn)
+            .await?;
+
+            diesel::update(auth_users::table.find(user_id))
+                .set(auth_users::password_hash.eq(password_hash))
+                .execute(conn)
+                .await?;
+            revoke_sessions(conn, user_id, now).await?;
+
+            Ok(Some((user_id, now)))
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Issues a verification token for `email`, the user's current address.
+pub async fn create_email_verification_token(
+    pool: &DbPool,
+    user_id: Uuid,
+    email: &str,
+) -> Result<String, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token = generate_token();
+
+    diesel::insert_into(email_verification_tokens::table)
+        .values(&NewEmailVerificationToken {
+            id: Uuid::new_v4(),
+            user_id,
+            email: email.to_string(),
+            token_hash: hash_token(&token),
+            expires_at: Utc::now() + Duration::hours(EMAIL_VERIFICATION_TTL_HOURS),
+        })
+        .execute(&mut conn)
+        .await?;
+
+    Ok(token)
+}
+
+/// Consumes a verification token and marks the address as verified. Returns
+/// `None` if the token is unknown, used, expired, or was sent to an address
+/// the account no longer has.
+pub async fn verify_email(
+    pool: &DbPool,
+    token: &str,
+) -> Result<Option<AuthUser>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token_hash = hash_token(token);
+
+    conn.transaction(|conn| {
+        async move {
+            let now = Utc::now();
+            let pending: Option<(Uuid, String)> = email_verification_tokens::table
+                .filter(email_verification_tokens::token_hash.eq(&token_hash))
+                .filter(email_verification_tokens::used_at.is_null())
+                .filter(email_verification_tokens::expires_at.gt(now))
+                .select((email_verification_tokens::user_id, email_verification_tokens::email))
+                .for_update()
+                .first(conn)
+                .await
+                .optional()?;
+
+            let Some((user_id, email)) = pending else {
+                return Ok(None);
+            };
+
+            let user: Option<AuthUser> = diesel::update(
+                auth_users::table
+                    .filter(auth_users::id.eq(user_id))
+                    .filter(auth_users::email.eq(&email)),
+            )
+            .set((
+                auth_users::email_verified_at.eq(now),
+                auth_users::updated_at.eq(now),
+            ))
+            .get_result(conn)
+            .await
+            .optional()?;
+
+            if user.is_some() {
+                diesel::update(
+                    email_verification_tokens::table
+                        .filter(email_verification_tokens::user_id.eq(user_id))
+                        .filter(email_verification_tokens::used_at.is_null()),
+                )
+                .set((
+                    email_verification_tokens::used_at.eq(now),
+                    email_verification_tokens::updated_at.eq(now),
+                ))
+                .execute(conn)
+                .await?;
+            }
+
+            Ok(user)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Issues a sign-in link token for `email`, the user's current address.
+pub async fn create_magic_link_token(
+    pool: &DbPool,
+    user_id: Uuid,
+    email: &str,
+) -> Result<String, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token = generate_token();
+
+    diesel::insert_into(magic_link_tokens::table)
+        .values(&NewMagicLinkToken {
+            id: Uuid::new_v4(),
+            user_id,
+            email: email.to_string(),
+            token_hash: hash_token(&token),
+            expires_at: Utc::now() + Duration::minutes(MAGIC_LINK_TTL_MINUTES),
+        })
+        .execute(&mut conn)
+        .await?;
+
+    Ok(token)
+}
+
+/// Consumes a sign-in link token together with every other outstanding link
+/// of the account, and returns the account. Following the link proves the
+/// address, so an unverified one becomes verified. Returns `None` if the token
+/// is unknown, used, expired, or was sent to an address the account no longer
+/// has.
+pub async fn consume_magic_link_token(
+    pool: &DbPool,
+    token: &str,
+) -> Result<Option<AuthUser>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token_hash = hash_token(token);
+
+    conn.transaction(|conn| {
+        async move {
+            let now = Utc::now();
+            let pending: Option<(Uuid, String)> = magic_link_tokens::table
+                .filter(magic_link_tokens::token_hash.eq(&token_hash))
+                .filter(magic_link_tokens::used_at.is_null())
+                .filter(magic_link_tokens::expires_at.gt(now))
+                .select((magic_link_tokens::user_id, magic_link_tokens::email))
+                .for_update()
+                .first(conn)
+                .await
+                .optional()?;
+
+            let Some((user_id, email)) = pending else {
+                return Ok(None);
+            };
+
+            let user: Option<AuthUser> = auth_users::table
+                .filter(auth_users::id.eq(user_id))
+                .filter(auth_users::email.eq(&email))
+                .first(conn)
+                .await
+                .optional()?;
+            let Some(mut user) = user else {
+                return Ok(None);
+            };
+
+            diesel::update(
+                magic_link_tokens::table
+                    .filter(magic_link_tokens::user_id.eq(user_id))
+                    .filter(magic_link_tokens::used_at.is_null()),
+            )
+            .set((
+                magic_link_tokens::used_at.eq(now),
+                magic_link_tokens::updated_at.eq(now),
+            ))
+            .execute(conn)
+            .await?;
+
+            if user.email_verified_at.is_none() {
+                user = diesel::update(auth_users::table.find(user_id))
+                    .set((
+                        auth_users::email_verified_at.eq(now),
+                        auth_users::updated_at.eq(now),
+                    ))
+                    .get_result(conn)
+                    .await?;
+            }
+
+            Ok(Some(user))
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Records `now` as the user's token cutoff and revokes their refresh tokens.
+async fn revoke_sessions(
+    conn: &mut AsyncPgConnection,
+    user_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<(), diesel::result::Error> {
+    diesel::update(auth_users::table.find(user_id))
+        .set((
+            auth_users::tokens_revoked_at.eq(now),
+            auth_users::updated_at.eq(now),
+        ))
+        .execute(conn)
+        .await?;
+
+    diesel::update(
+        refresh_tokens::table
+            .filter(refresh_tokens::user_id.eq(user_id))
+            .filter(refresh_tokens::revoked_at.is_null()),
+    )
+    .set((
+        refresh_tokens::revoked_at.eq(now),
+        refresh_tokens::updated_at.eq(now),
+    ))
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Revoked access tokens that have not expired yet, as `(jti, expires_at)`.
+pub async fn get_revoked_tokens(
+    pool: &DbPool,
+) -> Result<Vec<(Uuid, DateTime<Utc>)>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    revoked_tokens::table
+        .filter(revoked_tokens::expires_at.gt(Utc::now()))
+        .select((revoked_tokens::jti, revoked_tokens::expires_at))
+        .load(&mut conn)
+        .await
+}
+
+/// Users whose tokens were revoked wholesale after `since`, with the cutoff.
+/// Accounts deleted after `since` are included with their deletion time.
+pub async fn get_token_cutoffs(
+    pool: &DbPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<(Uuid, DateTime<Utc>)>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    let mut cutoffs: Vec<(Uuid, DateTime<Utc>)> = auth_users::table
+        .filter(auth_users::tokens_revoked_at.gt(since))
+        .select((auth_users::id, auth_users::tokens_revoked_at.assume_not_null()))
+        .load(&mut conn)
+        .await?;
+
+    let deleted: Vec<(Uuid, DateTime<Utc>)> = deleted_accounts::table
+        .filter(deleted_accounts::deleted_at.gt(since))
+        .select((deleted_accounts::user_id, deleted_accounts::deleted_at))
+        .load(&mut conn)
+        .await?;
+    cutoffs.extend(deleted);
+
+    Ok(cutoffs)
+}
+
+/// Sessions (refresh token families) with tokens revoked after `since`.
+pub async fn get_revoked_families(
+    pool: &DbPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<Uuid>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    refresh_tokens::table
+        .filter(refresh_tokens::revoked_at.gt(since))
+        .select(refresh_tokens::family_id)
+        .distinct()
+        .load(&mut conn)
+        .await
+}
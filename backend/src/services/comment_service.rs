@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::{Comment, CreateCommentRequest, UpdateCommentRequest, WithRelations},
+    query::{
+        distinct_ids, expand_parents, sort_by, text_condition, value_condition, BoxedCondition,
+        BoxedOrder, FieldFilter, ListError, QueryError, Relations, SortKey,
+    },
+    schema::comments,
+    services::{post_service, resource::resource_queries},
+};
+
+const COMMENT_FIELDS: &[&str] = &["id", "post_id", "name", "email", "body", "created_at", "updated_at"];
+const COMMENT_EMBEDS: &[&str] = &[];
+const COMMENT_EXPANDS: &[&str] = &["post"];
+
+fn comment_condition(filter: &FieldFilter) -> Result<BoxedCondition<comments::table>, QueryError> {
+    match filter.field.as_str() {
+        "id" => value_condition!(comments::id, filter, Uuid),
+        "post_id" => value_condition!(comments::post_id, filter, Uuid),
+        "name" => text_condition!(comments::name, filter),
+        "email" => text_condition!(comments::email, filter),
+        "body" => text_condition!(comments::body, filter),
+        "created_at" => value_condition!(comments::created_at, filter, DateTime<Utc>),
+        "updated_at" => value_condition!(comments::updated_at, filter, DateTime<Utc>),
+        field => Err(QueryError::unknown_field(field, COMMENT_FIELDS)),
+    }
+}
+
+fn comment_order(key: &SortKey) -> Result<BoxedOrder<comments::table>, QueryError> {
+    match key.field.as_str() {
+        "id" => Ok(sort_by(comments::id, key)),
+        "post_id" => Ok(sort_by(comments::post_id, key)),
+        "name" => Ok(sort_by(comments::name, key)),
+        "email" => Ok(sort_by(comments::email, key)),
+        "body" => Ok(sort_by(comments::body, key)),
+        "created_at" => Ok(sort_by(comments::created_at, key)),
+        "updated_at" => Ok(sort_by(comments::updated_at, key)),
+        field => Err(QueryError::unknown_sort_field(field, COMMENT_FIELDS)),
+    }
+}
+
+resource_queries! {
+    table: comments,
+    model: Comment,
+    condition: comment_condition,
+    order: comment_order,
+    get_all: get_all_comments,
+    get_by_id: get_comment_by_id,
+    get_with_relations: get_comment_with_relations,
+    get_by_parent_ids: get_comments_by_post_ids(post_id),
+    delete: delete_comment,
+}
+
+/// Attaches the `_embed`/`_expand` relations to each comment, one batch query per relation.
+pub async fn with_relations(
+    pool: &DbPool,
+    comments: Vec<Comment>,
+    relations: &Relations,
+) -> Result<Vec<WithRelations<Comment>>, ListError> {
+    relations.validate(COMMENT_EMBEDS, COMMENT_EXPANDS)?;
+
+    let mut items: Vec<WithRelations<Comment>> =
+        comments.into_iter().map(WithRelations::from).collect();
+
+    if relations.expands("post") {
+        let ids = distinct_ids(&items, |comment| comment.post_id);
+        let posts = post_service::get_posts_by_ids(pool, &ids).await?;
+        expand_parents(&mut items, "post", posts, |comment| comment.post_id, |parent| parent.id);
+    }
+
+    Ok(items)
+}
+
+pub async fn create_comment(
+    pool: &DbPool,
+    comment_data: &CreateCommentRequest,
+) -> Result<Comment, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    diesel::insert_into(comments::table)
+        .values((
+            comments::id.eq(Uuid::new_v4()),
+            comments::post_id.eq(comment_data.post_id),
+            comments::name.eq(&comment_data.name),
+            comments::email.eq(&comment_data.email),
+            comments::body.eq(&comment_data.body),
+            comments::created_at.eq(now),
+            comments::updated_at.eq(now),
+        ))
+        .returning(Comment::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Replaces every writable field of a comment (`PUT` semantics).
+pub async fn replace_comment(
+    pool: &DbPool,
+    comment_id: Uuid,
+    comment_data: &CreateCommentRequest,
+) -> Result<Comment, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(comments::table.find(comment_id))
+        .set((
+            comments::post_id.eq(comment_data.post_id),
+            comments::name.eq(&comment_data.name),
+            comments::email.eq(&comment_data.email),
+            comments::body.eq(&comment_data.body),
+            comments::updated_at.eq(Utc::now()),
+        ))
+        .returning(Comment::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Updates only the fields present in the request (`PATCH` semantics).
+pub async fn update_comment(
+    pool: &DbPool,
+    comment_id: Uuid,
+    comment_data: &UpdateCommentRequest,
+) -> Result<Comment, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(comments::table.find(comment_id))
+        .set((
+            comment_data.post_id.map(|post_id| comments::post_id.eq(post_id)),
+            comment_data.name.as_ref().map(|name| comments::name.eq(name)),
+            comment_data.email.as_ref().map(|email| comments::email.eq(email)),
+            comment_data.body.as_ref().map(|body| comments::body.eq(body)),
+            comments::updated_at.eq(Utc::now()),
+        ))
+        .returning(Comment::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
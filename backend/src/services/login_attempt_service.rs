@@ -0,0 +1,258 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::{pg::Pg, prelude::*, sql_types::Text};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::{LoginAttempt, NewLoginAttempt},
+    query::{
+        paginate, sort_by, text_condition, value_condition, BoxedCondition, BoxedOrder,
+        FieldFilter, ListError, ListPage, ListQuery, QueryError, SortKey,
+    },
+    schema::login_attempts,
+};
+
+/// Failed attempts an account gets before backoff starts.
+pub const ACCOUNT_FREE_ATTEMPTS: i64 = 5;
+/// Failed attempts after which an account is locked for [`LOCKOUT_MINUTES`].
+pub const ACCOUNT_LOCKOUT_ATTEMPTS: i64 = 10;
+/// Failed attempts a client IP gets before backoff starts. Higher than the
+/// per-account limit since many users can share an address.
+pub const IP_FREE_ATTEMPTS: i64 = 20;
+/// Failed attempts after which a client IP is locked for [`LOCKOUT_MINUTES`].
+pub const IP_LOCKOUT_ATTEMPTS: i64 = 50;
+pub const LOCKOUT_MINUTES: i64 = 15;
+/// How far back failures count; older ones are forgotten.
+pub const FAILURE_WINDOW_MINUTES: i64 = 60;
+
+const LOGIN_ATTEMPT_FIELDS: &[&str] =
+    &["id", "email", "user_id", "ip_address", "succeeded", "created_at"];
+
+fn login_attempt_condition(
+    filter: &FieldFilter,
+) -> Result<BoxedCondition<login_attempts::table>, QueryError> {
+    match filter.field.as_str() {
+        "id" => value_condition!(login_attempts::id, filter, Uuid),
+        "email" => text_condition!(login_attempts::email, filter),
+        "user_id" => value_condition!(login_attempts::user_id, filter, Uuid),
+        "ip_address" => text_condition!(login_attempts::ip_address, filter),
+        "succeeded" => value_condition!(login_attempts::succeeded, filter, bool),
+        "created_at" => value_condition!(login_attempts::created_at, filter, DateTime<Utc>),
+        field => Err(QueryError::unknown_field(field, LOGIN_ATTEMPT_FIELDS)),
+    }
+}
+
+fn login_attempt_order(key: &SortKey) -> Result<BoxedOrder<login_attempts::table>, QueryError> {
+    match key.field.as_str() {
+        "id" => Ok(sort_by(login_attempts::id, key)),
+        "email" => Ok(sort_by(login_attempts::email, key)),
+        "user_id" => Ok(sort_by(login_attempts::user_id, key)),
+        "ip_address" => Ok(sort_by(login_attempts::ip_address, key)),
+        "succeeded" => Ok(sort_by(login_attempts::succeeded, key)),
+        "created_at" => Ok(sort_by(login_attempts::created_at, key)),
+        field => Err(QueryError::unknown_sort_field(field, LOGIN_ATTEMPT_FIELDS)),
+    }
+}
+
+fn filtered_login_attempts(
+    params: &ListQuery,
+) -> Result<login_attempts::BoxedQuery<'static, Pg>, QueryError> {
+    let mut query = login_attempts::table.into_boxed();
+
+    for filter in &params.filters {
+        query = query.filter(login_attempt_condition(filter)?);
+    }
+
+    Ok(query)
+}
+
+/// Recorded attempts for admins, newest first unless `_sort` says otherwise.
+pub async fn get_all_login_attempts(
+    pool: &DbPool,
+    params: &ListQuery,
+) -> Result<ListPage<LoginAttempt>, ListError> {
+    params.reject_keyset()?;
+    params.relations.validate(&[], &[])?;
+
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    let total_count = match params.pagination {
+        Some(_) => Some(filtered_login_attempts(params)?.count().get_result(&mut conn).await?),
+        None => None,
+    };
+
+    let mut query = filtered_login_attempts(params)?.select(LoginAttempt::as_select());
+    for key in &params.sort {
+        query = query.then_order_by(login_attempt_order(key)?);
+    }
+    let query = query.then_order_by((login_attempts::created_at.desc(), login_attempts::id.desc()));
+
+    let items = paginate(query, params.pagination).load(&mut conn).await?;
+
+    Ok(ListPage {
+        items,
+        total_count,
+        next_cursor: None,
+    })
+}
+
+pub async fn record_login_attempt(
+    pool: &DbPool,
+    attempt: &NewLoginAttempt,
+) -> Result<(), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::insert_into(login_attempts::table)
+        .values(attempt)
+        .execute(&mut conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Records `attempt` as failed unless its email or client address is backing
+/// off, in which case the remaining wait is returned instead. Attempts for the
+/// same email or address take advisory locks, so concurrent requests cannot all
+/// pass the check before any of them is recorded. Once the outcome is known the
+/// caller settles the attempt with [`mark_succeeded`] or [`delete_attempt`].
+pub async fn start_attempt(
+    pool: &DbPool,
+    attempt: &NewLoginAttempt,
+) -> Result<Option<Duration>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    conn.transaction(|conn| {
+        async move {
+            // Always email first, then address, so two attempts cannot deadlock
+            for key in [
+                format!("login-email:{}", attempt.email),
+                format!("login-ip:{}", attempt.ip_address),
+            ] {
+                diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext($1))")
+                    .bind::<Text, _>(key)
+                    .execute(conn)
+                    .await?;
+            }
+
+            if let Some(wait) = retry_after_on(conn, &attempt.email, &attempt.ip_address).await? {
+                return Ok(Some(wait));
+            }
+
+            diesel::insert_into(login_attempts::table)
+                .values(attempt)
+                .execute(conn)
+                .await?;
+
+            Ok(None)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+pub async fn mark_succeeded(pool: &DbPool, id: Uuid) -> Result<(), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(login_attempts::table.find(id))
+        .set(login_attempts::succeeded.eq(true))
+        .execute(&mut conn)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_attempt(pool: &DbPool, id: Uuid) -> Result<(), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::delete(login_attempts::table.find(id))
+        .execute(&mut conn)
+        .await?;
+
+    Ok(())
+}
+
+/// How long a client must wait after `failures` consecutive failed attempts:
+/// nothing for the first `free_attempts`, then 1s doubling with each further
+/// failure, and a [`LOCKOUT_MINUTES`] lockout from `lockout_attempts` on.
+pub fn backoff(failures: i64, free_attempts: i64, lockout_attempts: i64) -> Option<Duration> {
+    let lockout = Duration::minutes(LOCKOUT_MINUTES);
+    if failures < free_attempts {
+        return None;
+    }
+    if failures >= lockout_attempts {
+        return Some(lockout);
+    }
+
+    let exponent = (failures - free_attempts).min(30) as u32;
+    Some(Duration::seconds(1i64 << exponent).min(lockout))
+}
+
+/// Time left before `email` may try to log in again from `ip_address`, or
+/// `None` if the attempt may go ahead. Account failures reset on a successful
+/// login; IP failures only age out of the window.
+pub async fn retry_after(
+    pool: &DbPool,
+    email: &str,
+    ip_address: &str,
+) -> Result<Option<Duration>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    retry_after_on(&mut conn, email, ip_address).await
+}
+
+async fn retry_after_on(
+    conn: &mut AsyncPgConnection,
+    email: &str,
+    ip_address: &str,
+) -> Result<Option<Duration>, diesel::result::Error> {
+    let now = Utc::now();
+    let window_start = now - Duration::minutes(FAILURE_WINDOW_MINUTES);
+
+    let last_success: Option<DateTime<Utc>> = login_attempts::table
+        .filter(login_attempts::email.eq(email))
+        .filter(login_attempts::succeeded.eq(true))
+        .select(login_attempts::created_at)
+        .order(login_attempts::created_at.desc())
+        .first(conn)
+        .await
+        .optional()?;
+    let account_since = last_success.map_or(window_start, |success| success.max(window_start));
+
+    // Newest first; backoff saturates at the lockout threshold, so that many rows are enough
+    let account_failures: Vec<DateTime<Utc>> = login_attempts::table
+        .filter(login_attempts::email.eq(email))
+        .filter(login_attempts::succeeded.eq(false))
+        .filter(login_attempts::created_at.gt(account_since))
+        .select(login_attempts::created_at)
+        .order(login_attempts::created_at.desc())
+        .limit(ACCOUNT_LOCKOUT_ATTEMPTS)
+        .load(conn)
+        .await?;
+
+    let ip_failures: Vec<DateTime<Utc>> = login_attempts::table
+        .filter(login_attempts::ip_address.eq(ip_address))
+        .filter(login_attempts::succeeded.eq(false))
+        .filter(login_attempts::created_at.gt(window_start))
+        .select(login_attempts::created_at)
+        .order(login_attempts::created_at.desc())
+        .limit(IP_LOCKOUT_ATTEMPTS)
+        .load(conn)
+        .await?;
+
+    let waits = [
+        (&account_failures, ACCOUNT_FREE_ATTEMPTS, ACCOUNT_LOCKOUT_ATTEMPTS),
+        (&ip_failures, IP_FREE_ATTEMPTS, IP_LOCKOUT_ATTEMPTS),
+    ];
+
+    Ok(waits
+        .into_iter()
+        .filter_map(|(failures, free_attempts, lockout_attempts)| {
+            let delay = backoff(failures.len() as i64, free_attempts, lockout_attempts)?;
+            Some(*failures.iter().next()? + delay - now)
+        })
+        .filter(|wait| *wait > Duration::zero())
+        .max())
+}
@@ -0,0 +1,300 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::{NewMfaChallenge, NewMfaRecoveryCode, NewTotpCredential, TotpCredential},
+    schema::{mfa_challenges, mfa_recovery_codes, totp_credentials},
+    services::auth_service::{generate_token, hash_token},
+};
+
+pub const MFA_CHALLENGE_TTL_MINUTES: i64 = 5;
+
+pub async fn get_totp_credential(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<Option<TotpCredential>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    totp_credentials::table
+        .filter(totp_credentials::user_id.eq(user_id))
+        .select(TotpCredential::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+}
+
+/// Whether logins of the user need a second factor.
+pub async fn is_totp_enabled(pool: &DbPool, user_id: Uuid) -> Result<bool, diesel::result::Error> {
+    Ok(get_totp_credential(pool, user_id)
+        .await?
+        .is_some_and(|credential| credential.confirmed_at.is_some()))
+}
+
+/// Stores a new unconfirmed secret, replacing any earlier unconfirmed one.
+/// Returns `false` without changes if TOTP is already enabled.
+pub async fn start_totp_enrollment(
+    pool: &DbPool,
+    user_id: Uuid,
+    secret_encrypted: &str,
+) -> Result<bool, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    conn.transaction(|conn| {
+        async move {
+            let existing: Option<TotpCredential> = totp_credentials::table
+                .filter(totp_credentials::user_id.eq(user_id))
+                .select(TotpCredential::as_select())
+                .for_update()
+                .first(conn)
+                .await
+                .optional()?;
+
+            match existing {
+                Some(credential) if credential.confirmed_at.is_some() => return Ok(false),
+                Some(credential) => {
+                    diesel::delete(totp_credentials::table.find(credential.id))
+                        .execute(conn)
+                        .await?;
+                }
+                None => {}
+            }
+
+            diesel::insert_into(totp_credentials::table)
+                .values(&NewTotpCredential {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    secret_encrypted: secret_encrypted.to_string(),
+                })
+                .execute(conn)
+                .await?;
+            Ok(true)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Enables TOTP after the first valid code and stores the recovery code
+/// hashes. Returns `false` if there is no pending enrollment.
+pub async fn confirm_totp(
+    pool: &DbPool,
+    user_id: Uuid,
+    step: i64,
+    recovery_code_hashes: &[String],
+) -> Result<bool, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    conn.transaction(|conn| {
+        async move {
+            let now = Utc::now();
+            let confirmed = diesel::update(
+                totp_credentials::table
+                    .filter(totp_credentials::user_id.eq(user_id))
+                    .filter(totp_credentials::confirmed_at.is_null()),
+            )
+            .set((
+                totp_credentials::confirmed_at.eq(now),
+                totp_credentials::last_used_step.eq(step),
+                totp_credentials::updated_at.eq(now),
+            ))
+            .execute(conn)
+            .await?;
+
+            if confirmed == 0 {
+                return Ok(false);
+            }
+            store_recovery_codes(conn, user_id, recovery_code_hashes).await?;
+            Ok(true)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Records that the code for `step` was used. Returns `false` if that step or
+/// a later one was already used, so each code works only once.
+pub async fn use_totp_step(
+    pool: &DbPool,
+    user_id: Uuid,
+    step: i64,
+) -> Result<bool, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    let updated = diesel::update(
+        totp_credentials::table
+            .filter(totp_credentials::user_id.eq(user_id))
+            .filter(totp_credentials::confirmed_at.is_not_null())
+            .filter(
+                totp_credentials::last_used_step
+                    .is_null()
+                    .or(totp_credentials::last_used_step.lt(step)),
+            ),
+    )
+    .set((
+        totp_credentials::last_used_step.eq(step),
+        totp_credentials::updated_at.eq(Utc::now()),
+    ))
+    .execute(&mut conn)
+    .await?;
+
+    Ok(updated > 0)
+}
+
+/// Marks an unused recovery code as used. Returns `false` if it does not match.
+pub async fn use_recovery_code(
+    pool: &DbPool,
+    user_id: Uuid,
+    code_hash: &str,
+) -> Result<bool, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    let updated = diesel::update(
+        mfa_recovery_codes::table
+            .filter(mfa_recovery_codes::user_id.eq(user_id))
+            .filter(mfa_recovery_codes::code_hash.eq(code_hash))
+            .filter(mfa_recovery_codes::used_at.is_null()),
+    )
+    .set((
+        mfa_recovery_codes::used_at.eq(now),
+        mfa_recovery_codes::updated_at.eq(now),
+    ))
+    .execute(&mut conn)
+    .await?;
+
+    Ok(updated > 0)
+}
+
+/// Replaces all recovery codes of the user.
+pub async fn replace_recovery_codes(
+    pool: &DbPool,
+    user_id: Uuid,
+    recovery_code_hashes: &[String],
+) -> Result<(), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    conn.transaction(|conn| {
+        async move { store_recovery_codes(conn, user_id, recovery_code_hashes).await }
+            .scope_boxed()
+    })
+    .await
+}
+
+async fn store_recovery_codes(
+    conn: &mut AsyncPgConnection,
+    user_id: Uuid,
+    recovery_code_hashes: &[String],
+) -> Result<(), diesel::result::Error> {
+    diesel::delete(mfa_recovery_codes::table.filter(mfa_recovery_codes::user_id.eq(user_id)))
+        .execute(conn)
+        .await?;
+
+    let codes: Vec<NewMfaRecoveryCode> = recovery_code_hashes
+        .iter()
+        .map(|code_hash| NewMfaRecoveryCode {
+            id: Uuid::new_v4(),
+            user_id,
+            code_hash: code_hash.clone(),
+        })
+        .collect();
+    diesel::insert_into(mfa_recovery_codes::table)
+        .values(&codes)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Removes the secret, recovery codes and pending login challenges.
+pub async fn disable_totp(pool: &DbPool, user_id: Uuid) -> Result<(), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    conn.transaction(|conn| {
+        async move {
+            diesel::delete(totp_credentials::table.filter(totp_credentials::user_id.eq(user_id)))
+                .execute(conn)
+                .await?;
+            diesel::delete(
+                mfa_recovery_codes::table.filter(mfa_recovery_codes::user_id.eq(user_id)),
+            )
+            .execute(conn)
+            .await?;
+            diesel::delete(mfa_challenges::table.filter(mfa_challenges::user_id.eq(user_id)))
+                .execute(conn)
+                .await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Issues the token a client trades for access tokens at `/auth/mfa/verify`
+/// once it has the second factor; only its hash is stored.
+pub async fn create_mfa_challenge(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<String, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token = generate_token();
+
+    diesel::insert_into(mfa_challenges::table)
+        .values(&NewMfaChallenge {
+            id: Uuid::new_v4(),
+            user_id,
+            token_hash: hash_token(&token),
+            expires_at: Utc::now() + Duration::minutes(MFA_CHALLENGE_TTL_MINUTES),
+        })
+        .execute(&mut conn)
+        .await?;
+
+    Ok(token)
+}
+
+/// The user a pending challenge belongs to, or `None` if the token is
+/// unknown, used or expired.
+pub async fn find_mfa_challenge(
+    pool: &DbPool,
+    token: &str,
+) -> Result<Option<Uuid>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    mfa_challenges::table
+        .filter(mfa_challenges::token_hash.eq(hash_token(token)))
+        .filter(mfa_challenges::used_at.is_null())
+        .filter(mfa_challenges::expires_at.gt(Utc::now()))
+        .select(mfa_challenges::user_id)
+        .first(&mut conn)
+        .await
+        .optional()
+}
+
+/// Marks a pending challenge as used. Returns `false` if it was used or
+/// expired in the meantime.
+pub async fn consume_mfa_challenge(
+    pool: &DbPool,
+    token: &str,
+) -> Result<bool, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    let updated = diesel::update(
+        mfa_challenges::table
+            .filter(mfa_challenges::token_hash.eq(hash_token(token)))
+            .filter(mfa_challenges::used_at.is_null())
+            .filter(mfa_challenges::expires_at.gt(now)),
+    )
+    .set((
+        mfa_challenges::used_at.eq(now),
+        mfa_challenges::updated_at.eq(now),
+    ))
+    .execute(&mut conn)
+    .await?;
+
+    Ok(updated > 0)
+}
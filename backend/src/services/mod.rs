@@ -1,2 +1,12 @@
+pub mod album_service;
+pub mod api_key_service;
 pub mod auth_service;
-pub mod user_service; 
\ No newline at end of file
+pub mod comment_service;
+pub mod login_attempt_service;
+pub mod mfa_service;
+pub mod oauth_service;
+pub mod photo_service;
+pub mod post_service;
+mod resource;
+pub mod todo_service;
+pub mod user_service;
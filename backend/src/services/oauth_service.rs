@@ -0,0 +1,371 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::{
+        NewOAuthAuthorizationCode, NewOAuthClient, NewOAuthConsent, NewRefreshToken,
+        OAuthAuthorizationCode, OAuthClient, OAuthConsent, RefreshToken, Scope,
+    },
+    oauth::AUTHORIZATION_CODE_TTL_MINUTES,
+    schema::{oauth_authorization_codes, oauth_clients, oauth_consents, refresh_tokens},
+    services::auth_service::{generate_token, hash_token, REFRESH_TOKEN_TTL_DAYS},
+};
+
+/// Result of presenting an authorization code to [`consume_authorization_code`].
+#[derive(Debug)]
+pub enum AuthorizationCodeOutcome {
+    /// The code was unused and unexpired; it is now used up.
+    Consumed(OAuthAuthorizationCode),
+    /// The code had already been redeemed, so it was intercepted or replayed;
+    /// the refresh tokens issued from it have been revoked.
+    Reused { family_id: Uuid },
+    /// Unknown or expired code.
+    Invalid,
+}
+
+/// Registers a client owned by `owner_id` and returns it with its secret,
+/// which is not kept. Public clients get no secret.
+pub async fn create_client(
+    pool: &DbPool,
+    owner_id: Uuid,
+    name: &str,
+    redirect_uris: Vec<String>,
+    scopes: Vec<Scope>,
+    public: bool,
+) -> Result<(OAuthClient, Option<String>), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let client_secret = (!public).then(generate_token);
+
+    let client = diesel::insert_into(oauth_clients::table)
+        .values(&NewOAuthClient {
+            id: Uuid::new_v4(),
+            owner_id,
+            name: name.to_string(),
+            client_secret_hash: client_secret.as_deref().map(hash_token),
+            redirect_uris,
+            scopes,
+        })
+        .returning(OAuthClient::as_returning())
+        .get_result(&mut conn)
+        .await?;
+
+    Ok((client, client_secret))
+}
+
+pub async fn get_clients(pool: &DbPool) -> Result<Vec<OAuthClient>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    oauth_clients::table
+        .order(oauth_clients::created_at.desc())
+        .select(OAuthClient::as_select())
+        .load(&mut conn)
+        .await
+}
+
+pub async fn get_client(
+    pool: &DbPool,
+    client_id: Uuid,
+) -> Result<Option<OAuthClient>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    oauth_clients::table
+        .find(client_id)
+        .select(OAuthClient::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+}
+
+/// Deletes a client together with its codes, consents and refresh tokens.
+/// Returns `false` if there is no such client.
+pub async fn delete_client(pool: &DbPool, client_id: Uuid) -> Result<bool, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    let deleted = diesel::delete(oauth_clients::table.find(client_id))
+        .execute(&mut conn)
+        .await?;
+
+    Ok(deleted > 0)
+}
+
+pub async fn get_consent(
+    pool: &DbPool,
+    user_id: Uuid,
+    client_id: Uuid,
+) -> Result<Option<OAuthConsent>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    oauth_consents::table
+        .filter(oauth_consents::user_id.eq(user_id))
+        .filter(oauth_consents::client_id.eq(client_id))
+        .select(OAuthConsent::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+}
+
+/// Records that the user allowed `scopes` to the client, in addition to any
+/// scopes allowed before.
+pub async fn grant_consent(
+    pool: &DbPool,
+    user_id: Uuid,
+    client_id: Uuid,
+    scopes: &[Scope],
+) -> Result<(), diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    conn.transaction(|conn| {
+        async move {
+            let existing: Option<OAuthConsent> = oauth_consents::table
+                .filter(oauth_consents::user_id.eq(user_id))
+                .filter(oauth_consents::client_id.eq(client_id))
+                .select(OAuthConsent::as_select())
+                .for_update()
+                .first(conn)
+                .await
+                .optional()?;
+
+            match existing {
+                Some(consent) => {
+                    let mut granted = consent.scopes;
+                    for scope in scopes {
+                        if !granted.contains(scope) {
+                            granted.push(*scope);
+                        }
+                    }
+                    diesel::update(oauth_consents::table.find(consent.id))
+                        .set((
+                            oauth_consents::scopes.eq(granted),
+                            oauth_consents::updated_at.eq(Utc::now()),
+                        ))
+                        .execute(conn)
+                        .await?;
+                }
+                None => {
+                    diesel::insert_into(oauth_consents::table)
+                        .values(&NewOAuthConsent {
+                            id: Uuid::new_v4(),
+                            user_id,
+                            client_id,
+                            scopes: scopes.to_vec(),
+                        })
+                        .execute(conn)
+                        .await?;
+                }
+            }
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// The user's consents with the names of their clients, most recent first.
+pub async fn get_consents(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<Vec<(OAuthConsent, String)>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    oauth_consents::table
+        .inner_join(oauth_clients::table)
+        .filter(oauth_consents::user_id.eq(user_id))
+        .order(oauth_consents::updated_at.desc())
+        .select((OAuthConsent::as_select(), oauth_clients::name))
+        .load(&mut conn)
+        .await
+}
+
+/// Withdraws the user's consent for a client and revokes the refresh tokens
+/// and pending codes it holds for the user. Returns `false` if there was no
+/// consent.
+pub async fn revoke_consent(
+    pool: &DbPool,
+    user_id: Uuid,
+    client_id: Uuid,
+) -> Result<bool, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    conn.transaction(|conn| {
+        async move {
+            let now = Utc::now();
+            let deleted = diesel::delete(
+                oauth_consents::table
+                    .filter(oauth_consents::user_id.eq(user_id))
+                    .filter(oauth_consents::client_id.eq(client_id)),
+            )
+            .execute(conn)
+            .await?;
+
+            diesel::update(
+                refresh_tokens::table
+                    .filter(refresh_tokens::user_id.eq(user_id))
+                    .filter(refresh_tokens::client_id.eq(client_id))
+                    .filter(refresh_tokens::revoked_at.is_null()),
+            )
+            .set((
+                refresh_tokens::revoked_at.eq(now),
+                refresh_tokens::updated_at.eq(now),
+            ))
+            .execute(conn)
+            .await?;
+
+            diesel::delete(
+                oauth_authorization_codes::table
+                    .filter(oauth_authorization_codes::user_id.eq(user_id))
+                    .filter(oauth_authorization_codes::client_id.eq(client_id))
+                    .filter(oauth_authorization_codes::used_at.is_null()),
+            )
+            .execute(conn)
+            .await?;
+
+            Ok(deleted > 0)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Issues an authorization code for the client to redeem with the PKCE
+/// verifier of `code_challenge`; only its hash is stored.
+pub async fn create_authorization_code(
+    pool: &DbPool,
+    client_id: Uuid,
+    user_id: Uuid,
+    redirect_uri: &str,
+    scopes: Vec<Scope>,
+    code_challenge: &str,
+) -> Result<String, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let code = generate_token();
+
+    diesel::insert_into(oauth_authorization_codes::table)
+        .values(&NewOAuthAuthorizationCode {
+            id: Uuid::new_v4(),
+            client_id,
+            user_id,
+            code_hash: hash_token(&code),
+            redirect_uri: redirect_uri.to_string(),
+            scopes,
+            code_challenge: code_challenge.to_string(),
+            expires_at: Utc::now() + Duration::minutes(AUTHORIZATION_CODE_TTL_MINUTES),
+            family_id: Uuid::new_v4(),
+        })
+        .execute(&mut conn)
+        .await?;
+
+    Ok(code)
+}
+
+/// Marks an unused, unexpired code as used. The code row is locked, so two
+/// concurrent requests cannot both redeem it. The caller still has to check
+/// the client, redirect URI and PKCE verifier.
+pub async fn consume_authorization_code(
+    pool: &DbPool,
+    code: &str,
+) -> Result<AuthorizationCodeOutcome, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let code_hash = hash_token(code);
+
+    conn.transaction(|conn| {
+        async move {
+            let now = Utc::now();
+            let Some(code) = oauth_authorization_codes::table
+                .filter(oauth_authorization_codes::code_hash.eq(&code_hash))
+                .select(OAuthAuthorizationCode::as_select())
+                .for_update()
+                .first(conn)
+                .await
+                .optional()?
+            else {
+                return Ok(AuthorizationCodeOutcome::Invalid);
+            };
+
+            if code.used_at.is_some() {
+                diesel::update(
+                    refresh_tokens::table
+                        .filter(refresh_tokens::family_id.eq(code.family_id))
+                        .filter(refresh_tokens::revoked_at.is_null()),
+                )
+                .set((
+                    refresh_tokens::revoked_at.eq(now),
+                    refresh_tokens::updated_at.eq(now),
+                ))
+                .execute(conn)
+                .await?;
+
+                return Ok(AuthorizationCodeOutcome::Reused {
+                    family_id: code.family_id,
+                });
+            }
+
+            if code.expires_at <= now {
+                return Ok(AuthorizationCodeOutcome::Invalid);
+            }
+
+            let code = diesel::update(oauth_authorization_codes::table.find(code.id))
+                .set((
+                    oauth_authorization_codes::used_at.eq(now),
+                    oauth_authorization_codes::updated_at.eq(now),
+                ))
+                .returning(OAuthAuthorizationCode::as_returning())
+                .get_result(conn)
+                .await?;
+
+            Ok(AuthorizationCodeOutcome::Consumed(code))
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Issues a refresh token to an OAuth client, starting token family
+/// `family_id`. It is rotated by
+/// [`rotate_refresh_token`](crate::services::auth_service::rotate_refresh_token).
+pub async fn create_refresh_token(
+    pool: &DbPool,
+    user_id: Uuid,
+    family_id: Uuid,
+    client_id: Uuid,
+    scopes: Vec<Scope>,
+) -> Result<String, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let token = generate_token();
+
+    diesel::insert_into(refresh_tokens::table)
+        .values(&NewRefreshToken {
+            id: Uuid::new_v4(),
+            user_id,
+            family_id,
+            token_hash: hash_token(&token),
+            expires_at: Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+            client_id: Some(client_id),
+            scopes: Some(scopes),
+        })
+        .execute(&mut conn)
+        .await?;
+
+    Ok(token)
+}
+
+/// An OAuth refresh token that can still be exchanged, or `None`.
+pub async fn find_refresh_token(
+    pool: &DbPool,
+    token: &str,
+) -> Result<Option<RefreshToken>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    refresh_tokens::table
+        .filter(refresh_tokens::token_hash.eq(hash_token(token)))
+        .filter(refresh_tokens::client_id.is_not_null())
+        .filter(refresh_tokens::used_at.is_null())
+        .filter(refresh_tokens::revoked_at.is_null())
+        .filter(refresh_tokens::expires_at.gt(Utc::now()))
+        .select(RefreshToken::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+}
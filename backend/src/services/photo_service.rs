@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::{CreatePhotoRequest, Photo, UpdatePhotoRequest, WithRelations},
+    query::{
+        distinct_ids, expand_parents, sort_by, text_condition, value_condition, BoxedCondition,
+        BoxedOrder, FieldFilter, ListError, QueryError, Relations, SortKey,
+    },
+    schema::photos,
+    services::{album_service, resource::resource_queries},
+};
+
+const PHOTO_FIELDS: &[&str] = &[
+    "id",
+    "album_id",
+    "title",
+    "url",
+    "thumbnail_url",
+    "created_at",
+    "updated_at",
+];
+const PHOTO_EMBEDS: &[&str] = &[];
+const PHOTO_EXPANDS: &[&str] = &["album"];
+
+fn photo_condition(filter: &FieldFilter) -> Result<BoxedCondition<photos::table>, QueryError> {
+    match filter.field.as_str() {
+        "id" => value_condition!(photos::id, filter, Uuid),
+        "album_id" => value_condition!(photos::album_id, filter, Uuid),
+        "title" => text_condition!(photos::title, filter),
+        "url" => text_condition!(photos::url, filter),
+        "thumbnail_url" => text_condition!(photos::thumbnail_url, filter),
+        "created_at" => value_condition!(photos::created_at, filter, DateTime<Utc>),
+        "updated_at" => value_condition!(photos::updated_at, filter, DateTime<Utc>),
+        field => Err(QueryError::unknown_field(field, PHOTO_FIELDS)),
+    }
+}
+
+fn photo_order(key: &SortKey) -> Result<BoxedOrder<photos::table>, QueryError> {
+    match key.field.as_str() {
+        "id" => Ok(sort_by(photos::id, key)),
+        "album_id" => Ok(sort_by(photos::album_id, key)),
+        "title" => Ok(sort_by(photos::title, key)),
+        "url" => Ok(sort_by(photos::url, key)),
+        "thumbnail_url" => Ok(sort_by(photos::thumbnail_url, key)),
+        "created_at" => Ok(sort_by(photos::created_at, key)),
+        "updated_at" => Ok(sort_by(photos::updated_at, key)),
+        field => Err(QueryError::unknown_sort_field(field, PHOTO_FIELDS)),
+    }
+}
+
+resource_queries! {
+    table: photos,
+    model: Photo,
+    condition: photo_condition,
+    order: photo_order,
+    get_all: get_all_photos,
+    get_by_id: get_photo_by_id,
+    get_with_relations: get_photo_with_relations,
+    get_by_parent_ids: get_photos_by_album_ids(album_id),
+    delete: delete_photo,
+}
+
+/// Attaches the `_embed`/`_expand` relations to each photo, one batch query per relation.
+pub async fn with_relations(
+    pool: &DbPool,
+    photos: Vec<Photo>,
+    relations: &Relations,
+) -> Result<Vec<WithRelations<Photo>>, ListError> {
+    relations.validate(PHOTO_EMBEDS, PHOTO_EXPANDS)?;
+
+    let mut items: Vec<WithRelations<Photo>> =
+        photos.into_iter().map(WithRelations::from).collect();
+
+    if relations.expands("album") {
+        let ids = distinct_ids(&items, |photo| photo.album_id);
+        let albums = album_service::get_albums_by_ids(pool, &ids).await?;
+        expand_parents(&mut items, "album", albums, |photo| photo.album_id, |parent| parent.id);
+    }
+
+    Ok(items)
+}
+
+pub async fn create_photo(
+    pool: &DbPool,
+    photo_data: &CreatePhotoRequest,
+) -> Result<Photo, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    diesel::insert_into(photos::table)
+        .values((
+            photos::id.eq(Uuid::new_v4()),
+            photos::album_id.eq(photo_data.album_id),
+            photos::title.eq(&photo_data.title),
+            photos::url.eq(&photo_data.url),
+            photos::thumbnail_url.eq(&photo_data.thumbnail_url),
+            photos::created_at.eq(now),
+            photos::updated_at.eq(now),
+        ))
+        .returning(Photo::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Replaces every writable field of a photo (`PUT` semantics).
+pub async fn replace_photo(
+    pool: &DbPool,
+    photo_id: Uuid,
+    photo_data: &CreatePhotoRequest,
+) -> Result<Photo, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(photos::table.find(photo_id))
+        .set((
+            photos::album_id.eq(photo_data.album_id),
+            photos::title.eq(&photo_data.title),
+            photos::url.eq(&photo_data.url),
+            photos::thumbnail_url.eq(&photo_data.thumbnail_url),
+            photos::updated_at.eq(Utc::now()),
+        ))
+        .returning(Photo::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Updates only the fields present in the request (`PATCH` semantics).
+pub async fn update_photo(
+    pool: &DbPool,
+    photo_id: Uuid,
+    photo_data: &UpdatePhotoRequest,
+) -> Result<Photo, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(photos::table.find(photo_id))
+        .set((
+            photo_data.album_id.map(|album_id| photos::album_id.eq(album_id)),
+            photo_data.title.as_ref().map(|title| photos::title.eq(title)),
+            photo_data.url.as_ref().map(|url| photos::url.eq(url)),
+            photo_data
+                .thumbnail_url
+                .as_ref()
+                .map(|thumbnail_url| photos::thumbnail_url.eq(thumbnail_url)),
+            photos::updated_at.eq(Utc::now()),
+        ))
+        .returning(Photo::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
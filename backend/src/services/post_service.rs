@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::{CreatePostRequest, Post, UpdatePostRequest, WithRelations},
+    query::{
+        distinct_ids, embed_children, expand_parents, sort_by, text_condition, value_condition,
+        BoxedCondition, BoxedOrder, FieldFilter, ListError, QueryError, Relations, SortKey,
+    },
+    schema::posts,
+    services::{comment_service, resource::resource_queries, user_service},
+};
+
+const POST_FIELDS: &[&str] = &["id", "user_id", "title", "body", "created_at", "updated_at"];
+const POST_EMBEDS: &[&str] = &["comments"];
+const POST_EXPANDS: &[&str] = &["user"];
+
+fn post_condition(filter: &FieldFilter) -> Result<BoxedCondition<posts::table>, QueryError> {
+    match filter.field.as_str() {
+        "id" => value_condition!(posts::id, filter, Uuid),
+        "user_id" => value_condition!(posts::user_id, filter, Uuid),
+        "title" => text_condition!(posts::title, filter),
+        "body" => text_condition!(posts::body, filter),
+        "created_at" => value_condition!(posts::created_at, filter, DateTime<Utc>),
+        "updated_at" => value_condition!(posts::updated_at, filter, DateTime<Utc>),
+        field => Err(QueryError::unknown_field(field, POST_FIELDS)),
+    }
+}
+
+fn post_order(key: &SortKey) -> Result<BoxedOrder<posts::table>, QueryError> {
+    match key.field.as_str() {
+        "id" => Ok(sort_by(posts::id, key)),
+        "user_id" => Ok(sort_by(posts::user_id, key)),
+        "title" => Ok(sort_by(posts::title, key)),
+        "body" => Ok(sort_by(posts::body, key)),
+        "created_at" => Ok(sort_by(posts::created_at, key)),
+        "updated_at" => Ok(sort_by(posts::updated_at, key)),
+        field => Err(QueryError::unknown_sort_field(field, POST_FIELDS)),
+    }
+}
+
+resource_queries! {
+    table: posts,
+    model: Post,
+    condition: post_condition,
+    order: post_order,
+    get_all: get_all_posts,
+    get_by_id: get_post_by_id,
+    get_with_relations: get_post_with_relations,
+    get_by_ids: get_posts_by_ids,
+    get_by_parent_ids: get_posts_by_user_ids(user_id),
+    delete: delete_post,
+}
+
+/// Attaches the `_embed`/`_expand` relations to each post, one batch query per relation.
+pub async fn with_relations(
+    pool: &DbPool,
+    posts: Vec<Post>,
+    relations: &Relations,
+) -> Result<Vec<WithRelations<Post>>, ListError> {
+    relations.validate(POST_EMBEDS, POST_EXPANDS)?;
+
+    let mut items: Vec<WithRelations<Post>> = posts.into_iter().map(WithRelations::from).collect();
+
+    if relations.embeds("comments") {
+        let ids = distinct_ids(&items, |post| post.id);
+        let comments = comment_service::get_comments_by_post_ids(pool, &ids).await?;
+        embed_children(&mut items, "comments", comments, |post| post.id, |child| child.post_id);
+    }
+
+    if relations.expands("user") {
+        let ids = distinct_ids(&items, |post| post.user_id);
+        let users = user_service::get_users_by_ids(pool, &ids).await?;
+        expand_parents(&mut items, "user", users, |post| post.user_id, |parent| parent.id);
+    }
+
+    Ok(items)
+}
+
+pub async fn create_post(
+    pool: &DbPool,
+    post_data: &CreatePostRequest,
+) -> Result<Post, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    diesel::insert_into(posts::table)
+        .values((
+            posts::id.eq(Uuid::new_v4()),
+            posts::user_id.eq(post_data.user_id),
+            posts::title.eq(&post_data.title),
+            posts::body.eq(&post_data.body),
+            posts::created_at.eq(now),
+            posts::updated_at.eq(now),
+        ))
+        .returning(Post::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Replaces every writable field of a post (`PUT` semantics).
+pub async fn replace_post(
+    pool: &DbPool,
+    post_id: Uuid,
+    post_data: &CreatePostRequest,
+) -> Result<Post, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(posts::table.find(post_id))
+        .set((
+            posts::user_id.eq(post_data.user_id),
+            posts::title.eq(&post_data.title),
+            posts::body.eq(&post_data.body),
+            posts::updated_at.eq(Utc::now()),
+        ))
+        .returning(Post::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Updates only the fields present in the request (`PATCH` semantics).
+pub async fn update_post(
+    pool: &DbPool,
+    post_id: Uuid,
+    post_data: &UpdatePostRequest,
+) -> Result<Post, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(posts::table.find(post_id))
+        .set((
+            post_data.user_id.map(|user_id| posts::user_id.eq(user_id)),
+            post_data.title.as_ref().map(|title| posts::title.eq(title)),
+            post_data.body.as_ref().map(|body| posts::body.eq(body)),
+            posts::updated_at.eq(Utc::now()),
+        ))
+        .returning(Post::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
@@ -0,0 +1,162 @@
+//! Queries shared by the json-server style resources: posts, comments,
+//! albums, photos and todos.
+
+/// Generates the queries every resource service has in common. The service
+/// provides the `condition` and `order` functions mapping `?field=` filters
+/// and `_sort` keys to its columns, plus `with_relations`; inserts and
+/// updates list the resource's columns and stay hand-written.
+///
+/// Names of the generated functions are given explicitly so that callers and
+/// grep see `post_service::get_post_by_id` rather than a generic name.
+macro_rules! resource_queries {
+    (
+        table: $table:ident,
+        model: $model:ident,
+        condition: $condition:ident,
+        order: $order:ident,
+        get_all: $get_all:ident,
+        get_by_id: $get_by_id:ident,
+        get_with_relations: $get_with_relations:ident,
+        $(get_by_ids: $get_by_ids:ident,)?
+        get_by_parent_ids: $get_by_parent_ids:ident($parent_id:ident),
+        delete: $delete:ident $(,)?
+    ) => {
+        fn filtered(
+            params: &$crate::query::ListQuery,
+        ) -> Result<$table::BoxedQuery<'static, diesel::pg::Pg>, $crate::query::QueryError> {
+            use diesel::QueryDsl;
+
+            let mut query = $table::table.into_boxed();
+
+            for filter in &params.filters {
+                query = query.filter($condition(filter)?);
+            }
+
+            Ok(query)
+        }
+
+        pub async fn $get_all(
+            pool: &$crate::database::DbPool,
+            params: &$crate::query::ListQuery,
+        ) -> Result<
+            $crate::query::ListPage<$crate::models::WithRelations<$model>>,
+            $crate::query::ListError,
+        > {
+            use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+            use diesel_async::RunQueryDsl;
+
+            params.reject_keyset()?;
+
+            let mut conn =
+                pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+            let total_count = match params.pagination {
+                Some(_) => Some(filtered(params)?.count().get_result(&mut conn).await?),
+                None => None,
+            };
+
+            let mut query = filtered(params)?.select($model::as_select());
+            for key in &params.sort {
+                query = query.then_order_by($order(key)?);
+            }
+            // Creation order breaks ties so pages stay stable
+            let query = query.then_order_by(($table::created_at.asc(), $table::id.asc()));
+
+            let rows = $crate::query::paginate(query, params.pagination).load(&mut conn).await?;
+            drop(conn);
+
+            let items = with_relations(pool, rows, &params.relations).await?;
+
+            Ok($crate::query::ListPage {
+                items,
+                total_count,
+                next_cursor: None,
+            })
+        }
+
+        pub async fn $get_by_id(
+            pool: &$crate::database::DbPool,
+            id: uuid::Uuid,
+        ) -> Result<$model, diesel::result::Error> {
+            use diesel::{QueryDsl, SelectableHelper};
+            use diesel_async::RunQueryDsl;
+
+            let mut conn =
+                pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+            $table::table.find(id).select($model::as_select()).first(&mut conn).await
+        }
+
+        pub async fn $get_with_relations(
+            pool: &$crate::database::DbPool,
+            id: uuid::Uuid,
+            relations: &$crate::query::Relations,
+        ) -> Result<$crate::models::WithRelations<$model>, $crate::query::ListError> {
+            let row = $get_by_id(pool, id).await?;
+            let mut items = with_relations(pool, vec![row], relations).await?;
+
+            Ok(items.remove(0))
+        }
+
+        $(
+            pub async fn $get_by_ids(
+                pool: &$crate::database::DbPool,
+                ids: &[uuid::Uuid],
+            ) -> Result<Vec<$model>, diesel::result::Error> {
+                use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+                use diesel_async::RunQueryDsl;
+
+                let mut conn = pool
+                    .get()
+                    .await
+                    .map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+                $table::table
+                    .filter($table::id.eq_any(ids))
+                    .select($model::as_select())
+                    .order(($table::created_at.asc(), $table::id.asc()))
+                    .load(&mut conn)
+                    .await
+            }
+        )?
+
+        pub async fn $get_by_parent_ids(
+            pool: &$crate::database::DbPool,
+            parent_ids: &[uuid::Uuid],
+        ) -> Result<Vec<$model>, diesel::result::Error> {
+            use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+            use diesel_async::RunQueryDsl;
+
+            let mut conn =
+                pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+            $table::table
+                .filter($table::$parent_id.eq_any(parent_ids))
+                .select($model::as_select())
+                .order(($table::created_at.asc(), $table::id.asc()))
+                .load(&mut conn)
+                .await
+        }
+
+        pub async fn $delete(
+            pool: &$crate::database::DbPool,
+            id: uuid::Uuid,
+        ) -> Result<(), diesel::result::Error> {
+            use diesel::QueryDsl;
+            use diesel_async::RunQueryDsl;
+
+            let mut conn =
+                pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+            let deleted = diesel::delete($table::table.find(id)).execute(&mut conn).await?;
+
+            if deleted == 0 {
+                return Err(diesel::result::Error::NotFound);
+            }
+
+            Ok(())
+        }
+    };
+}
+
+pub(crate) use resource_queries;
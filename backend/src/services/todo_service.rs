@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::{
+    database::DbPool,
+    models::{CreateTodoRequest, Todo, UpdateTodoRequest, WithRelations},
+    query::{
+        distinct_ids, expand_parents, sort_by, text_condition, value_condition, BoxedCondition,
+        BoxedOrder, FieldFilter, ListError, QueryError, Relations, SortKey,
+    },
+    schema::todos,
+    services::{resource::resource_queries, user_service},
+};
+
+const TODO_FIELDS: &[&str] = &["id", "user_id", "title", "completed", "created_at", "updated_at"];
+const TODO_EMBEDS: &[&str] = &[];
+const TODO_EXPANDS: &[&str] = &["user"];
+
+fn todo_condition(filter: &FieldFilter) -> Result<BoxedCondition<todos::table>, QueryError> {
+    match filter.field.as_str() {
+        "id" => value_condition!(todos::id, filter, Uuid),
+        "user_id" => value_condition!(todos::user_id, filter, Uuid),
+        "title" => text_condition!(todos::title, filter),
+        "completed" => value_condition!(todos::completed, filter, bool),
+        "created_at" => value_condition!(todos::created_at, filter, DateTime<Utc>),
+        "updated_at" => value_condition!(todos::updated_at, filter, DateTime<Utc>),
+        field => Err(QueryError::unknown_field(field, TODO_FIELDS)),
+    }
+}
+
+fn todo_order(key: &SortKey) -> Result<BoxedOrder<todos::table>, QueryError> {
+    match key.field.as_str() {
+        "id" => Ok(sort_by(todos::id, key)),
+        "user_id" => Ok(sort_by(todos::user_id, key)),
+        "title" => Ok(sort_by(todos::title, key)),
+        "completed" => Ok(sort_by(todos::completed, key)),
+        "created_at" => Ok(sort_by(todos::created_at, key)),
+        "updated_at" => Ok(sort_by(todos::updated_at, key)),
+        field => Err(QueryError::unknown_sort_field(field, TODO_FIELDS)),
+    }
+}
+
+resource_queries! {
+    table: todos,
+    model: Todo,
+    condition: todo_condition,
+    order: todo_order,
+    get_all: get_all_todos,
+    get_by_id: get_todo_by_id,
+    get_with_relations: get_todo_with_relations,
+    get_by_parent_ids: get_todos_by_user_ids(user_id),
+    delete: delete_todo,
+}
+
+/// Attaches the `_embed`/`_expand` relations to each todo, one batch query per relation.
+pub async fn with_relations(
+    pool: &DbPool,
+    todos: Vec<Todo>,
+    relations: &Relations,
+) -> Result<Vec<WithRelations<Todo>>, ListError> {
+    relations.validate(TODO_EMBEDS, TODO_EXPANDS)?;
+
+    let mut items: Vec<WithRelations<Todo>> = todos.into_iter().map(WithRelations::from).collect();
+
+    if relations.expands("user") {
+        let ids = distinct_ids(&items, |todo| todo.user_id);
+        let users = user_service::get_users_by_ids(pool, &ids).await?;
+        expand_parents(&mut items, "user", users, |todo| todo.user_id, |parent| parent.id);
+    }
+
+    Ok(items)
+}
+
+pub async fn create_todo(
+    pool: &DbPool,
+    todo_data: &CreateTodoRequest,
+) -> Result<Todo, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    let now = Utc::now();
+
+    diesel::insert_into(todos::table)
+        .values((
+            todos::id.eq(Uuid::new_v4()),
+            todos::user_id.eq(todo_data.user_id),
+            todos::title.eq(&todo_data.title),
+            todos::completed.eq(todo_data.completed),
+            todos::created_at.eq(now),
+            todos::updated_at.eq(now),
+        ))
+        .returning(Todo::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Replaces every writable field of a todo (`PUT` semantics).
+pub async fn replace_todo(
+    pool: &DbPool,
+    todo_id: Uuid,
+    todo_data: &CreateTodoRequest,
+) -> Result<Todo, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(todos::table.find(todo_id))
+        .set((
+            todos::user_id.eq(todo_data.user_id),
+            todos::title.eq(&todo_data.title),
+            todos::completed.eq(todo_data.completed),
+            todos::updated_at.eq(Utc::now()),
+        ))
+        .returning(Todo::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
+/// Updates only the fields present in the request (`PATCH` semantics).
+pub async fn update_todo(
+    pool: &DbPool,
+    todo_id: Uuid,
+    todo_data: &UpdateTodoRequest,
+) -> Result<Todo, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    diesel::update(todos::table.find(todo_id))
+        .set((
+            todo_data.user_id.map(|user_id| todos::user_id.eq(user_id)),
+            todo_data.title.as_ref().map(|title| todos::title.eq(title)),
+            todo_data.completed.map(|completed| todos::completed.eq(completed)),
+            todos::updated_at.eq(Utc::now()),
+        ))
+        .returning(Todo::as_returning())
+        .get_result(&mut conn)
+        .await
+}
+
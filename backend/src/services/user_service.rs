@@ -1,101 +1,151 @@
+// Joined user rows are spelled out as tuples where they are loaded.
+#![allow(clippy::type_complexity)]
+
 use bigdecimal::{BigDecimal, FromPrimitive};
-use chrono::Utc;
-use diesel::prelude::*;
+use chrono::{DateTime, Utc};
+use diesel::{
+    dsl::{count_star, sql, IntoBoxed, LeftJoin},
+    helper_types::LeftJoinQuerySource,
+    pg::Pg,
+    prelude::*,
+    sql_types::{Bool, Float, Nullable, Text, Timestamptz, Uuid as SqlUuid},
+};
 use diesel_async::RunQueryDsl;
 use uuid::Uuid;
 
 use crate::{
     database::DbPool,
-    models::{Address, Company, CreateUserRequest, Geo, UpdateUserRequest, User},
+    models::{Address, Company, CreateUserRequest, Geo, UpdateUserRequest, User, WithRelations},
+    query::{
+        distinct_ids, embed_children, paginate, prefix_tsquery, sort_by, text_condition,
+        value_condition, BoxedCondition, BoxedOrder, Cursor, CursorKey, FieldFilter, Keyset,
+        ListError, ListPage, ListQuery, QueryError, Relations, SortKey,
+    },
     schema::{addresses, companies, users},
+    services::{album_service, post_service, todo_service},
 };
 
-pub async fn get_all_users(pool: &DbPool) -> Result<Vec<User>, diesel::result::Error> {
-    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
-    
-    // Get all users with their addresses and companies
-    let users_data: Vec<(
-        (Uuid, String, String, String, Option<String>, Option<String>, chrono::DateTime<Utc>, chrono::DateTime<Utc>),
-        Option<(Uuid, Uuid, String, Option<String>, String, String, Option<bigdecimal::BigDecimal>, Option<bigdecimal::BigDecimal>)>,
-        Option<(Uuid, Uuid, String, Option<String>, Option<String>)>,
-    )> = users::table
-        .left_join(addresses::table.on(addresses::user_id.eq(users::id)))
-        .left_join(companies::table.on(companies::user_id.eq(users::id)))
-        .load(&mut conn)
-        .await?;
+/// `users` left-joined with its address and company, so nested fields can be filtered in SQL.
+type UserSource = LeftJoinQuerySource<LeftJoinQuerySource<users::table, addresses::table>, companies::table>;
 
-    let mut users_map: std::collections::HashMap<Uuid, User> = std::collections::HashMap::new();
-
-    for (user_data, address_data, company_data) in users_data {
-        let user_id = user_data.0;
-        
-        let address = address_data.map(|addr| Address {
-            id: addr.0,
-            user_id: addr.1,
-            street: addr.2,
-            suite: addr.3,
-            city: addr.4,
-            zipcode: addr.5,
-            geo: match (addr.6, addr.7) {
-                (Some(lat), Some(lng)) => Some(Geo {
-                    lat: lat.to_string().parse().unwrap_or(0.0),
-                    lng: lng.to_string().parse().unwrap_or(0.0),
-                }),
-                _ => None,
-            },
-        });
-
-        let company = company_data.map(|comp| Company {
-            id: comp.0,
-            user_id: comp.1,
-            name: comp.2,
-            catch_phrase: comp.3,
-            bs: comp.4,
-        });
-
-        users_map.insert(user_id, User {
-            id: user_data.0,
-            name: user_data.1,
-            username: user_data.2,
-            email: user_data.3,
-            phone: user_data.4,
-            website: user_data.5,
-            address,
-            company,
-            created_at: user_data.6,
-            updated_at: user_data.7,
-        });
+const USER_FIELDS: &[&str] = &[
+    "id",
+    "name",
+    "username",
+    "email",
+    "phone",
+    "website",
+    "created_at",
+    "updated_at",
+    "owner_id",
+    "address.street",
+    "address.suite",
+    "address.city",
+    "address.zipcode",
+    "address.geo.lat",
+    "address.geo.lng",
+    "company.name",
+    "company.catch_phrase",
+    "company.bs",
+];
+
+const USER_EMBEDS: &[&str] = &["posts", "albums", "todos"];
+const USER_EXPANDS: &[&str] = &[];
+
+fn user_condition(filter: &FieldFilter) -> Result<BoxedCondition<UserSource>, QueryError> {
+    match filter.field.as_str() {
+        "id" => value_condition!(users::id, filter, Uuid),
+        "name" => text_condition!(users::name, filter),
+        "username" => text_condition!(users::username, filter),
+        "email" => text_condition!(users::email, filter),
+        "phone" => text_condition!(users::phone, filter),
+        "website" => text_condition!(users::website, filter),
+        "created_at" => value_condition!(users::created_at, filter, DateTime<Utc>),
+        "updated_at" => value_condition!(users::updated_at, filter, DateTime<Utc>),
+        "owner_id" => value_condition!(users::owner_id, filter, Uuid),
+        "address.street" => text_condition!(addresses::street, filter),
+        "address.suite" => text_condition!(addresses::suite, filter),
+        "address.city" => text_condition!(addresses::city, filter),
+        "address.zipcode" => text_condition!(addresses::zipcode, filter),
+        "address.geo.lat" => value_condition!(addresses::lat, filter, BigDecimal),
+        "address.geo.lng" => value_condition!(addresses::lng, filter, BigDecimal),
+        "company.name" => text_condition!(companies::name, filter),
+        "company.catch_phrase" => text_condition!(companies::catch_phrase, filter),
+        "company.bs" => text_condition!(companies::bs, filter),
+        field => Err(QueryError::unknown_field(field, USER_FIELDS)),
     }
+}
 
-    Ok(users_map.into_values().collect())
+fn user_order(key: &SortKey) -> Result<BoxedOrder<UserSource>, QueryError> {
+    match key.field.as_str() {
+        "id" => Ok(sort_by(users::id, key)),
+        "name" => Ok(sort_by(users::name, key)),
+        "username" => Ok(sort_by(users::username, key)),
+        "email" => Ok(sort_by(users::email, key)),
+        "phone" => Ok(sort_by(users::phone, key)),
+        "website" => Ok(sort_by(users::website, key)),
+        "created_at" => Ok(sort_by(users::created_at, key)),
+        "updated_at" => Ok(sort_by(users::updated_at, key)),
+        "owner_id" => Ok(sort_by(users::owner_id, key)),
+        "address.street" => Ok(sort_by(addresses::street, key)),
+        "address.suite" => Ok(sort_by(addresses::suite, key)),
+        "address.city" => Ok(sort_by(addresses::city, key)),
+        "address.zipcode" => Ok(sort_by(addresses::zipcode, key)),
+        "address.geo.lat" => Ok(sort_by(addresses::lat, key)),
+        "address.geo.lng" => Ok(sort_by(addresses::lng, key)),
+        "company.name" => Ok(sort_by(companies::name, key)),
+        "company.catch_phrase" => Ok(sort_by(companies::catch_phrase, key)),
+        "company.bs" => Ok(sort_by(companies::bs, key)),
+        field => Err(QueryError::unknown_sort_field(field, USER_FIELDS)),
+    }
 }
 
-pub async fn get_user_by_id(pool: &DbPool, user_id: Uuid) -> Result<User, diesel::result::Error> {
-    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
-    
-    // Get user
-    let user_data: (Uuid, String, String, String, Option<String>, Option<String>, chrono::DateTime<Utc>, chrono::DateTime<Utc>) = 
-        users::table
-            .filter(users::id.eq(user_id))
-            .first(&mut conn)
-            .await?;
+type UserQuery = IntoBoxed<'static, LeftJoin<LeftJoin<users::table, addresses::table>, companies::table>, Pg>;
 
-    // Get address
-    let address_data: Option<(Uuid, Uuid, String, Option<String>, String, String, Option<bigdecimal::BigDecimal>, Option<bigdecimal::BigDecimal>)> = 
-        addresses::table
-            .filter(addresses::user_id.eq(user_id))
-            .first(&mut conn)
-            .await
-            .optional()?;
+/// Search document of a user, including its company and address: the
+/// trigger-maintained `users.search_vector` (see the `denormalize_user_search`
+/// migration). It is not part of `schema.rs`, so it is referenced as SQL.
+const USER_SEARCH_DOCUMENT: &str = "users.search_vector";
 
-    // Get company
-    let company_data: Option<(Uuid, Uuid, String, Option<String>, Option<String>)> = 
-        companies::table
-            .filter(companies::user_id.eq(user_id))
-            .first(&mut conn)
-            .await
-            .optional()?;
+fn user_search_condition(tsquery: &str) -> BoxedCondition<UserSource> {
+    Box::new(
+        sql::<Nullable<Bool>>(&format!("{} @@ to_tsquery('simple', ", USER_SEARCH_DOCUMENT))
+            .bind::<Text, _>(tsquery.to_string())
+            .sql(")"),
+    )
+}
 
+fn user_search_rank(tsquery: &str) -> BoxedOrder<UserSource> {
+    Box::new(
+        sql::<Float>(&format!("ts_rank({}, to_tsquery('simple', ", USER_SEARCH_DOCUMENT))
+            .bind::<Text, _>(tsquery.to_string())
+            .sql("))")
+            .desc(),
+    )
+}
+
+fn filtered_users(params: &ListQuery) -> Result<UserQuery, QueryError> {
+    let mut query = users::table
+        .left_join(addresses::table)
+        .left_join(companies::table)
+        .into_boxed();
+
+    for filter in &params.filters {
+        query = query.filter(user_condition(filter)?);
+    }
+
+    if let Some(tsquery) = params.search.as_deref().and_then(prefix_tsquery) {
+        query = query.filter(user_search_condition(&tsquery));
+    }
+
+    Ok(query)
+}
+
+fn build_user(
+    user_data: (Uuid, String, String, String, Option<String>, Option<String>, chrono::DateTime<Utc>, chrono::DateTime<Utc>, Option<Uuid>),
+    address_data: Option<(Uuid, Uuid, String, Option<String>, String, String, Option<bigdecimal::BigDecimal>, Option<bigdecimal::BigDecimal>)>,
+    company_data: Option<(Uuid, Uuid, String, Option<String>, Option<String>)>,
+) -> User {
     let address = address_data.map(|addr| Address {
         id: addr.0,
         user_id: addr.1,
@@ -120,7 +170,7 @@ pub async fn get_user_by_id(pool: &DbPool, user_id: Uuid) -> Result<User, diesel
         bs: comp.4,
     });
 
-    Ok(User {
+    User {
         id: user_data.0,
         name: user_data.1,
         username: user_data.2,
@@ -131,12 +181,248 @@ pub async fn get_user_by_id(pool: &DbPool, user_id: Uuid) -> Result<User, diesel
         company,
         created_at: user_data.6,
         updated_at: user_data.7,
+        owner_id: user_data.8,
+    }
+}
+
+pub async fn get_all_users(
+    pool: &DbPool,
+    params: &ListQuery,
+    cursor_key: &CursorKey,
+) -> Result<ListPage<WithRelations<User>>, ListError> {
+    if let Some(keyset) = &params.keyset {
+        return get_users_page_after(pool, params, keyset, cursor_key).await;
+    }
+
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    let total_count = match params.pagination {
+        Some(_) => Some(
+            filtered_users(params)?
+                .select(count_star())
+                .get_result(&mut conn)
+                .await?,
+        ),
+        None => None,
+    };
+
+    // Get matching users with their addresses and companies; best search matches
+    // come right after the explicit sort keys and creation order breaks ties
+    let mut query = filtered_users(params)?;
+    for key in &params.sort {
+        query = query.then_order_by(user_order(key)?);
+    }
+    if let Some(tsquery) = params.search.as_deref().and_then(prefix_tsquery) {
+        query = query.then_order_by(user_search_rank(&tsquery));
+    }
+    let query = query.then_order_by((users::created_at.asc(), users::id.asc()));
+
+    let users_data: Vec<(
+        (Uuid, String, String, String, Option<String>, Option<String>, chrono::DateTime<Utc>, chrono::DateTime<Utc>, Option<Uuid>),
+        Option<(Uuid, Uuid, String, Option<String>, String, String, Option<bigdecimal::BigDecimal>, Option<bigdecimal::BigDecimal>)>,
+        Option<(Uuid, Uuid, String, Option<String>, Option<String>)>,
+    )> =
+        paginate(query, params.pagination).load(&mut conn).await?;
+    drop(conn);
+
+    let items = with_relations(pool, collect_users(users_data), &params.relations).await?;
+
+    Ok(ListPage {
+        items,
+        total_count,
+        next_cursor: None,
     })
 }
 
+/// Keyset page of users in creation order, starting after `keyset.cursor`.
+/// Filters and search apply as usual; search results are not ranked here
+/// because the page boundary has to follow `(created_at, id)`.
+async fn get_users_page_after(
+    pool: &DbPool,
+    params: &ListQuery,
+    keyset: &Keyset,
+    cursor_key: &CursorKey,
+) -> Result<ListPage<WithRelations<User>>, ListError> {
+    let secret = cursor_key.as_bytes();
+    let after = keyset
+        .cursor
+        .as_deref()
+        .map(|token| Cursor::decode(token, secret))
+        .transpose()?;
+
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    let mut query = filtered_users(params)?;
+    if let Some(after) = after {
+        // Row comparison lets Postgres seek on idx_users_created_at_id
+        query = query.filter(
+            sql::<Bool>("(users.created_at, users.id) > (")
+                .bind::<Timestamptz, _>(after.created_at)
+                .sql(", ")
+                .bind::<SqlUuid, _>(after.id)
+                .sql(")"),
+        );
+    }
+
+    // One extra row tells whether another page follows
+    let users_data: Vec<(
+        (Uuid, String, String, String, Option<String>, Option<String>, chrono::DateTime<Utc>, chrono::DateTime<Utc>, Option<Uuid>),
+        Option<(Uuid, Uuid, String, Option<String>, String, String, Option<bigdecimal::BigDecimal>, Option<bigdecimal::BigDecimal>)>,
+        Option<(Uuid, Uuid, String, Option<String>, Option<String>)>,
+    )> = query
+        .order((users::created_at.asc(), users::id.asc()))
+        .limit(keyset.limit + 1)
+        .load(&mut conn)
+        .await?;
+    drop(conn);
+
+    let mut users_list = collect_users(users_data);
+    let next_cursor = if users_list.len() as i64 > keyset.limit {
+        users_list.truncate(keyset.limit as usize);
+        users_list.last().map(|user| {
+            Cursor {
+                created_at: user.created_at,
+                id: user.id,
+            }
+            .encode(secret)
+        })
+    } else {
+        None
+    };
+
+    let items = with_relations(pool, users_list, &params.relations).await?;
+
+    Ok(ListPage {
+        items,
+        total_count: None,
+        next_cursor,
+    })
+}
+
+/// Builds users from joined rows. Addresses and companies are unique per user,
+/// so there is exactly one row per user and LIMIT/OFFSET count whole users.
+fn collect_users(users_data: Vec<(
+        (Uuid, String, String, String, Option<String>, Option<String>, chrono::DateTime<Utc>, chrono::DateTime<Utc>, Option<Uuid>),
+        Option<(Uuid, Uuid, String, Option<String>, String, String, Option<bigdecimal::BigDecimal>, Option<bigdecimal::BigDecimal>)>,
+        Option<(Uuid, Uuid, String, Option<String>, Option<String>)>,
+    )>) -> Vec<User> {
+    users_data
+        .into_iter()
+        .map(|(user_data, address_data, company_data)| {
+            build_user(user_data, address_data, company_data)
+        })
+        .collect()
+}
+
+/// Attaches the `_embed` relations to each user, one batch query per relation.
+pub async fn with_relations(
+    pool: &DbPool,
+    users: Vec<User>,
+    relations: &Relations,
+) -> Result<Vec<WithRelations<User>>, ListError> {
+    relations.validate(USER_EMBEDS, USER_EXPANDS)?;
+
+    let mut items: Vec<WithRelations<User>> = users.into_iter().map(WithRelations::from).collect();
+    let ids = distinct_ids(&items, |user| user.id);
+
+    if relations.embeds("posts") {
+        let posts = post_service::get_posts_by_user_ids(pool, &ids).await?;
+        embed_children(&mut items, "posts", posts, |user| user.id, |post| post.user_id);
+    }
+
+    if relations.embeds("albums") {
+        let albums = album_service::get_albums_by_user_ids(pool, &ids).await?;
+        embed_children(&mut items, "albums", albums, |user| user.id, |album| album.user_id);
+    }
+
+    if relations.embeds("todos") {
+        let todos = todo_service::get_todos_by_user_ids(pool, &ids).await?;
+        embed_children(&mut items, "todos", todos, |user| user.id, |todo| todo.user_id);
+    }
+
+    Ok(items)
+}
+
+pub async fn get_user_with_relations(
+    pool: &DbPool,
+    user_id: Uuid,
+    relations: &Relations,
+) -> Result<WithRelations<User>, ListError> {
+    let user = get_user_by_id(pool, user_id).await?;
+    let mut items = with_relations(pool, vec![user], relations).await?;
+
+    Ok(items.remove(0))
+}
+
+pub async fn get_users_by_ids(
+    pool: &DbPool,
+    user_ids: &[Uuid],
+) -> Result<Vec<User>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    let users_data: Vec<(
+        (Uuid, String, String, String, Option<String>, Option<String>, chrono::DateTime<Utc>, chrono::DateTime<Utc>, Option<Uuid>),
+        Option<(Uuid, Uuid, String, Option<String>, String, String, Option<bigdecimal::BigDecimal>, Option<bigdecimal::BigDecimal>)>,
+        Option<(Uuid, Uuid, String, Option<String>, Option<String>)>,
+    )> = users::table
+        .left_join(addresses::table)
+        .left_join(companies::table)
+        .filter(users::id.eq_any(user_ids))
+        .order((users::created_at.asc(), users::id.asc()))
+        .load(&mut conn)
+        .await?;
+
+    Ok(collect_users(users_data))
+}
+
+pub async fn get_user_by_id(pool: &DbPool, user_id: Uuid) -> Result<User, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+    
+    // Get user
+    let user_data: (Uuid, String, String, String, Option<String>, Option<String>, chrono::DateTime<Utc>, chrono::DateTime<Utc>, Option<Uuid>) = 
+        users::table
+            .filter(users::id.eq(user_id))
+            .first(&mut conn)
+            .await?;
+
+    // Get address
+    let address_data: Option<(Uuid, Uuid, String, Option<String>, String, String, Option<bigdecimal::BigDecimal>, Option<bigdecimal::BigDecimal>)> = 
+        addresses::table
+            .filter(addresses::user_id.eq(user_id))
+            .first(&mut conn)
+            .await
+            .optional()?;
+
+    // Get company
+    let company_data: Option<(Uuid, Uuid, String, Option<String>, Option<String>)> = 
+        companies::table
+            .filter(companies::user_id.eq(user_id))
+            .first(&mut conn)
+            .await
+            .optional()?;
+
+    Ok(build_user(user_data, address_data, company_data))
+}
+
+/// Returns the owner of a user profile; `NotFound` if the profile does not exist.
+pub async fn get_user_owner(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<Option<Uuid>, diesel::result::Error> {
+    let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
+
+    users::table
+        .find(user_id)
+        .select(users::owner_id)
+        .first(&mut conn)
+        .await
+}
+
+/// Creates a user profile owned by the auth account `owner_id`.
 pub async fn create_user(
     pool: &DbPool,
     user_data: &CreateUserRequest,
+    owner_id: Uuid,
 ) -> Result<User, diesel::result::Error> {
     let mut conn = pool.get().await.map_err(|_| diesel::result::Error::BrokenTransactionManager)?;
     let user_id = Uuid::new_v4();
@@ -153,6 +439,7 @@ pub async fn create_user(
             users::website.eq(&user_data.website),
             users::created_at.eq(now),
             users::updated_at.eq(now),
+            users::owner_id.eq(owner_id),
         ))
         .execute(&mut conn)
         .await?;
@@ -0,0 +1,78 @@
+use axum::extract::FromRef;
+use std::sync::Arc;
+
+use crate::{
+    config::Config, database::DbPool, keyring::Keyring, mailer::Mailer, mfa::SecretCipher,
+    password::Passwords, query::CursorKey, revocation::RevocationList,
+};
+
+/// Shared application state. Handlers that only need the database keep
+/// extracting `State<DbPool>`.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: DbPool,
+    pub revocations: Arc<RevocationList>,
+    pub mailer: Arc<dyn Mailer>,
+    pub keys: Arc<Keyring>,
+    /// Encrypts TOTP secrets at rest.
+    pub mfa_cipher: Arc<SecretCipher>,
+    pub config: Arc<Config>,
+    pub passwords: Arc<Passwords>,
+    /// Signs keyset pagination cursors.
+    pub cursor_key: Arc<CursorKey>,
+}
+
+impl AppState {
+    /// Builds the state and loads the current token revocations.
+    pub async fn new(
+        pool: DbPool,
+        mailer: Arc<dyn Mailer>,
+        keys: Keyring,
+        mfa_cipher: SecretCipher,
+        config: Config,
+    ) -> Result<Self, diesel::result::Error> {
+        let revocations = Arc::new(RevocationList::default());
+        revocations.sync(&pool).await?;
+
+        Ok(AppState {
+            pool,
+            revocations,
+            mailer,
+            keys: Arc::new(keys),
+            mfa_cipher: Arc::new(mfa_cipher),
+            cursor_key: Arc::new(CursorKey::from_config(&config)),
+            config: Arc::new(config),
+            passwords: Arc::new(Passwords::default()),
+        })
+    }
+}
+
+impl FromRef<AppState> for DbPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RevocationList> {
+    fn from_ref(state: &AppState) -> Self {
+        state.revocations.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<CursorKey> {
+    fn from_ref(state: &AppState) -> Self {
+        state.cursor_key.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Keyring> {
+    fn from_ref(state: &AppState) -> Self {
+        state.keys.clone()
+    }
+}
@@ -0,0 +1,93 @@
+use cursor_backend::{
+    models::{Claims, CreateApiKeyRequest, Role, Scope},
+    services::api_key_service::{generate_api_key, parse_api_key, API_KEY_PREFIX},
+};
+use validator::Validate;
+
+#[test]
+fn test_generated_keys_parse_back_into_prefix_and_secret() {
+    let (prefix, secret, key) = generate_api_key();
+
+    assert!(key.starts_with(&format!("{}_{}_", API_KEY_PREFIX, prefix)));
+    assert_eq!(prefix.len(), 8);
+    assert!(prefix.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+    assert_eq!(parse_api_key(&key), Some((prefix.as_str(), secret.as_str())));
+    assert_ne!(key, generate_api_key().2);
+}
+
+#[test]
+fn test_secrets_may_contain_underscores() {
+    assert_eq!(parse_api_key("ck_abcd1234_se_cr_et"), Some(("abcd1234", "se_cr_et")));
+}
+
+#[test]
+fn test_malformed_keys_do_not_parse() {
+    for key in [
+        "",
+        "ck_",
+        "ck_abcd1234",
+        "ck_abcd1234_",
+        "ck_short_secret",
+        "xx_abcd1234_secret",
+        "ckabcd1234_secret",
+    ] {
+        assert_eq!(parse_api_key(key), None, "{}", key);
+    }
+}
+
+#[test]
+fn test_scopes_use_colon_names() {
+    let scopes: Vec<Scope> =
+        serde_json::from_str(r#"["users:read", "users:write", "admin"]"#).unwrap();
+
+    assert_eq!(scopes, vec![Scope::UsersRead, Scope::UsersWrite, Scope::Admin]);
+    for scope in scopes {
+        assert_eq!(serde_json::to_value(scope).unwrap(), scope.as_str());
+        assert_eq!(scope.as_str().parse::<Scope>(), Ok(scope));
+    }
+    assert!(serde_json::from_str::<Scope>(r#""users""#).is_err());
+}
+
+#[test]
+fn test_only_scoped_claims_are_limited() {
+    let session = Claims {
+        sub: "user".to_string(),
+        email: "test@example.com".to_string(),
+        jti: "jti".to_string(),
+        sid: "sid".to_string(),
+        role: Role::Editor,
+        exp: 0,
+        iat: 0,
+        iat_micros: None,
+        scopes: None,
+        client_id: None,
+    };
+    let api_key = Claims {
+        scopes: Some(vec![Scope::UsersRead]),
+        ..session.clone()
+    };
+
+    assert!(session.has_scope(Scope::Admin));
+    assert!(api_key.has_scope(Scope::UsersRead));
+    assert!(!api_key.has_scope(Scope::UsersWrite));
+    // Session tokens never carry the claim
+    assert!(!serde_json::to_string(&session).unwrap().contains("scopes"));
+}
+
+#[test]
+fn test_api_key_requests_need_a_name_and_scopes() {
+    let request = |name: &str, scopes: Vec<Scope>, expires_in_days: Option<i64>| {
+        CreateApiKeyRequest {
+            name: name.to_string(),
+            scopes,
+            expires_in_days,
+        }
+    };
+
+    assert!(request("ci", vec![Scope::UsersRead], Some(30)).validate().is_ok());
+    assert!(request("ci", vec![Scope::UsersRead], None).validate().is_ok());
+    assert!(request("", vec![Scope::UsersRead], None).validate().is_err());
+    assert!(request("ci", vec![], None).validate().is_err());
+    assert!(request("ci", vec![Scope::UsersRead], Some(0)).validate().is_err());
+    assert!(request("ci", vec![Scope::UsersRead], Some(366)).validate().is_err());
+}
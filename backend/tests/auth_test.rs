@@ -0,0 +1,358 @@
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    middleware,
+    routing::get,
+    Router,
+};
+use chrono::{Duration, Utc};
+use cursor_backend::{
+    config::Config,
+    database::DbPool,
+    keyring::Keyring,
+    mailer::FileOutbox,
+    mfa::SecretCipher,
+    middleware::{
+        auth::{auth_middleware, require_permission, require_scope, require_session},
+        client_ip::ClientIp,
+    },
+    models::{Claims, ErrorResponse, Permission, Role, Scope},
+    query::CursorKey,
+    revocation::RevocationList,
+    services::{
+        auth_service::{generate_token, hash_token},
+        login_attempt_service::{backoff, LOCKOUT_MINUTES},
+    },
+    state::AppState,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+use diesel_async::{
+    pooled_connection::{bb8::Pool, AsyncDieselConnectionManager},
+    AsyncPgConnection,
+};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use tower::ServiceExt;
+
+const JWT_SECRET: &str = "test-secret";
+
+async fn whoami(claims: Claims) -> String {
+    claims.email
+}
+
+async fn client_ip(ClientIp(ip): ClientIp) -> String {
+    ip
+}
+
+const USER_ID: &str = "850e8400-e29b-41d4-a716-446655440001";
+
+// The middleware never touches the database, so the pool is never connected
+fn protected_app(revocations: Arc<RevocationList>) -> Router {
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new("postgres://localhost/unused");
+    let pool: DbPool = Pool::builder().build_unchecked(manager);
+    let config = Config {
+        database_url: "postgres://localhost/unused".to_string(),
+        jwt_secret: JWT_SECRET.to_string(),
+        jwt_keys_dir: None,
+        jwt_signing_kid: None,
+        mfa_encryption_key: None,
+        cursor_secret: None,
+        port: 0,
+        public_reads: true,
+        require_email_verification: false,
+        default_role: Role::Editor,
+        app_url: "http://localhost".to_string(),
+        smtp_url: None,
+        mail_from: "no-reply@localhost".to_string(),
+        mail_outbox: "outbox.jsonl".to_string(),
+        trust_forwarded_for: true,
+    };
+    let state = AppState {
+        pool,
+        revocations,
+        mailer: Arc::new(FileOutbox::new(&config.mail_outbox)),
+        keys: Arc::new(Keyring::hs256(JWT_SECRET.as_bytes())),
+        mfa_cipher: Arc::new(SecretCipher::new(&[7; 32])),
+        config: Arc::new(config),
+        passwords: Arc::default(),
+        cursor_key: Arc::new(CursorKey::new(b"cursor-secret")),
+    };
+
+    Router::new()
+        .route("/protected", get(whoami))
+        .route(
+            "/delete",
+            get(whoami).route_layer(middleware::from_fn_with_state(
+                Permission::DeleteUsers,
+                require_permission,
+            )),
+        )
+        .route(
+            "/manage",
+            get(whoami).route_layer(middleware::from_fn_with_state(
+                Permission::ManageAnyUser,
+                require_permission,
+            )),
+        )
+        .route(
+            "/scoped",
+            get(whoami)
+                .route_layer(middleware::from_fn_with_state(Scope::UsersWrite, require_scope)),
+        )
+        .route("/session", get(whoami).route_layer(middleware::from_fn(require_session)))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware))
+        .route("/public", get(whoami))
+        .route("/ip", get(client_ip))
+        .with_state(state)
+}
+
+fn claims(exp_offset: i64) -> Claims {
+    let now = Utc::now().timestamp();
+    Claims {
+        sub: USER_ID.to_string(),
+        email: "test@example.com".to_string(),
+        jti: Uuid::new_v4().to_string(),
+        sid: Uuid::new_v4().to_string(),
+        role: Role::Editor,
+        exp: (now + exp_offset) as usize,
+        iat: now as usize,
+        iat_micros: None,
+        scopes: None,
+        client_id: None,
+    }
+}
+
+fn sign(claims: &Claims) -> String {
+    encode(&Header::default(), claims, &EncodingKey::from_secret(JWT_SECRET.as_bytes())).unwrap()
+}
+
+fn token(exp_offset: i64) -> String {
+    sign(&claims(exp_offset))
+}
+
+async fn send(uri: &str, authorization: Option<String>) -> (StatusCode, Vec<u8>) {
+    send_with(Arc::default(), uri, authorization).await
+}
+
+async fn send_with(
+    revocations: Arc<RevocationList>,
+    uri: &str,
+    authorization: Option<String>,
+) -> (StatusCode, Vec<u8>) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(authorization) = authorization {
+        request = request.header("authorization", authorization);
+    }
+
+    let response = protected_app(revocations)
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn test_valid_token_exposes_claims() {
+    let (status, body) = send("/protected", Some(format!("Bearer {}", token(3600)))).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, b"test@example.com");
+}
+
+#[tokio::test]
+async fn test_missing_invalid_and_expired_tokens_get_json_401() {
+    for authorization in [
+        None,
+        Some(token(3600)),
+        Some("Bearer not-a-jwt".to_string()),
+        Some(format!("Bearer {}", token(-3600))),
+    ] {
+        let (status, body) = send("/protected", authorization).await;
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(error.error, "unauthorized");
+    }
+}
+
+#[tokio::test]
+async fn test_claims_extractor_rejects_unauthenticated_routes() {
+    let (status, body) = send("/public", None).await;
+    let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(error.message, "Authentication required");
+}
+
+#[test]
+fn test_generated_tokens_are_random_and_url_safe() {
+    let first = generate_token();
+    let second = generate_token();
+
+    assert_ne!(first, second);
+    assert_eq!(first.len(), 43);
+    assert!(first.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+}
+
+#[test]
+fn test_tokens_are_stored_as_sha256_hex() {
+    assert_eq!(
+        hash_token("abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_ne!(hash_token("abc"), hash_token("abd"));
+}
+
+#[tokio::test]
+async fn test_revoked_token_is_rejected() {
+    let revocations = Arc::new(RevocationList::default());
+    let revoked = claims(3600);
+    let other = claims(3600);
+    revocations.revoke_token(revoked.jti.parse().unwrap(), Utc::now() + Duration::hours(1));
+
+    let (status, body) = send_with(revocations.clone(), "/protected", Some(format!("Bearer {}", sign(&revoked)))).await;
+    let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(error.message, "Token has been revoked");
+
+    let (status, _) = send_with(revocations, "/protected", Some(format!("Bearer {}", sign(&other)))).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[test]
+fn test_logout_all_revokes_tokens_issued_before_the_cutoff() {
+    let revocations = RevocationList::default();
+    let mut before = claims(3600);
+    before.iat -= 60;
+    let after = claims(3600);
+    let mut other_user = before.clone();
+    other_user.sub = Uuid::new_v4().to_string();
+
+    revocations.revoke_all(USER_ID.parse().unwrap(), Utc::now() - Duration::seconds(30));
+
+    assert!(revocations.is_revoked(&before));
+    assert!(!revocations.is_revoked(&after));
+    assert!(!revocations.is_revoked(&other_user));
+}
+
+#[test]
+fn test_cutoff_compares_issue_times_to_the_microsecond() {
+    let revocations = RevocationList::default();
+    let cutoff = Utc::now();
+    let mut before = claims(3600);
+    before.iat = cutoff.timestamp() as usize;
+    before.iat_micros = Some(cutoff.timestamp_micros() - 1);
+    let mut after = before.clone();
+    after.iat_micros = Some(cutoff.timestamp_micros() + 1);
+    // Without `iat_micros` the whole second of the cutoff is revoked
+    let mut legacy = before.clone();
+    legacy.iat_micros = None;
+
+    revocations.revoke_all(USER_ID.parse().unwrap(), cutoff);
+
+    assert!(revocations.is_revoked(&before));
+    assert!(!revocations.is_revoked(&after));
+    assert!(revocations.is_revoked(&legacy));
+}
+
+#[tokio::test]
+async fn test_role_and_permission_layers_reject_with_403() {
+    let editor = sign(&claims(3600));
+    let admin = sign(&Claims {
+        role: Role::Admin,
+        ..claims(3600)
+    });
+
+    for uri in ["/delete", "/manage"] {
+        let (status, body) = send(uri, Some(format!("Bearer {}", editor))).await;
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(error.error, "forbidden");
+
+        let (status, _) = send(uri, Some(format!("Bearer {}", admin))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(uri, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn test_client_ip_uses_first_forwarded_for_entry() {
+    let request = Request::builder()
+        .uri("/ip")
+        .header("x-forwarded-for", "203.0.113.7, 10.0.0.1")
+        .body(Body::empty())
+        .unwrap();
+    let response = protected_app(Arc::default()).oneshot(request).await.unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..], b"203.0.113.7");
+
+    // No header and no connect info when driving the router directly
+    let (status, body) = send("/ip", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, b"unknown");
+}
+
+#[test]
+fn test_login_backoff_doubles_then_locks_out() {
+    assert_eq!(backoff(0, 5, 10), None);
+    assert_eq!(backoff(4, 5, 10), None);
+    assert_eq!(backoff(5, 5, 10), Some(Duration::seconds(1)));
+    assert_eq!(backoff(6, 5, 10), Some(Duration::seconds(2)));
+    assert_eq!(backoff(9, 5, 10), Some(Duration::seconds(16)));
+    assert_eq!(backoff(10, 5, 10), Some(Duration::minutes(LOCKOUT_MINUTES)));
+    assert_eq!(backoff(1000, 5, 1000), Some(Duration::minutes(LOCKOUT_MINUTES)));
+}
+
+#[tokio::test]
+async fn test_scope_layers_limit_scoped_callers_only() {
+    let session = sign(&claims(3600));
+    let writer = sign(&Claims {
+        scopes: Some(vec![Scope::UsersWrite]),
+        ..claims(3600)
+    });
+    let reader = sign(&Claims {
+        scopes: Some(vec![Scope::UsersRead]),
+        ..claims(3600)
+    });
+
+    let (status, _) = send("/scoped", Some(format!("Bearer {}", session))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send("/scoped", Some(format!("Bearer {}", writer))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send("/scoped", Some(format!("Bearer {}", reader))).await;
+    let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(error.message, "Requires the users:write scope");
+
+    let (status, _) = send("/session", Some(format!("Bearer {}", session))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send("/session", Some(format!("Bearer {}", writer))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_malformed_api_keys_and_unknown_schemes_get_401() {
+    // Malformed keys are rejected before the database is asked
+    let (status, body) = send("/protected", Some("ApiKey not-a-key".to_string())).await;
+    let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(error.message, "Invalid or expired API key");
+
+    let request = Request::builder()
+        .uri("/protected")
+        .header("x-api-key", "ck_short_secret")
+        .body(Body::empty())
+        .unwrap();
+    let response = protected_app(Arc::default()).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let (status, body) = send("/protected", Some("Basic dXNlcjpwYXNz".to_string())).await;
+    let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(error.message, "Authorization header must use the Bearer or ApiKey scheme");
+}
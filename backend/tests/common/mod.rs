@@ -0,0 +1,198 @@
+//! Setup shared by tests that need Postgres. They run against the migrated
+//! database in `TEST_DATABASE_URL` and are skipped when it is not set.
+#![allow(dead_code)]
+
+use argon2::Params;
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Method, Request, StatusCode},
+    routing::post,
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use cursor_backend::{
+    config::Config,
+    handlers,
+    keyring::Keyring,
+    mailer::{Email, FileOutbox},
+    mfa::SecretCipher,
+    models::Role,
+    password::{Argon2Hasher, Passwords},
+    query::CursorKey,
+    state::AppState,
+};
+use diesel_async::{
+    pooled_connection::{bb8::Pool, AsyncDieselConnectionManager},
+    AsyncPgConnection,
+};
+use serde_json::{json, Value};
+use std::{path::PathBuf, sync::Arc};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+pub const PASSWORD: &str = "password123";
+
+pub struct TestContext {
+    pub state: AppState,
+    pub outbox: Arc<FileOutbox>,
+    outbox_path: PathBuf,
+    /// Sent as `X-Forwarded-For`, so every test has its own IP throttle.
+    ip_address: String,
+}
+
+impl TestContext {
+    /// `None` when `TEST_DATABASE_URL` is not set.
+    pub async fn new() -> Option<Self> {
+        let Ok(database_url) = std::env::var("TEST_DATABASE_URL") else {
+            eprintln!("TEST_DATABASE_URL is not set, skipping");
+            return None;
+        };
+        let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(database_url);
+        let pool = Pool::builder()
+            .max_size(4)
+            .build(manager)
+            .await
+            .expect("failed to connect to TEST_DATABASE_URL");
+
+        let outbox_path = std::env::temp_dir().join(format!("outbox-{}.jsonl", Uuid::new_v4()));
+        let outbox = Arc::new(FileOutbox::new(&outbox_path));
+        let config = Config {
+            database_url: String::new(),
+            jwt_secret: "test-secret".to_string(),
+            jwt_keys_dir: None,
+            jwt_signing_kid: None,
+            mfa_encryption_key: None,
+            cursor_secret: None,
+            port: 0,
+            public_reads: true,
+            require_email_verification: false,
+            default_role: Role::Editor,
+            app_url: "http://localhost".to_string(),
+            smtp_url: None,
+            mail_from: "no-reply@localhost".to_string(),
+            mail_outbox: outbox_path.display().to_string(),
+            trust_forwarded_for: true,
+        };
+        // The cheapest Argon2 parameters keep logins fast
+        let argon2 = Argon2Hasher::new(Params::new(Params::MIN_M_COST, 1, 1, None).unwrap());
+        let state = AppState {
+            pool,
+            revocations: Arc::default(),
+            mailer: outbox.clone(),
+            keys: Arc::new(Keyring::hs256(b"test-secret")),
+            mfa_cipher: Arc::new(SecretCipher::new(&[7; 32])),
+            config: Arc::new(config),
+            passwords: Arc::new(Passwords::new(Box::new(argon2), Vec::new())),
+            cursor_key: Arc::new(CursorKey::new(b"cursor-secret")),
+        };
+
+        let [a, b, c, ..] = *Uuid::new_v4().as_bytes();
+        let ip_address = format!("10.{}.{}.{}", a, b, c);
+
+        Some(TestContext {
+            state,
+            outbox,
+            outbox_path,
+            ip_address,
+        })
+    }
+
+    /// Sends a JSON request and returns the status with the JSON body (`null`
+    /// when empty).
+    pub async fn send(
+        &self,
+        app: &Router,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+        token: Option<&str>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("x-forwarded-for", &self.ip_address);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+
+        call(app, request.unwrap()).await
+    }
+
+    /// Sends a form like OAuth clients do, authenticating with HTTP Basic if
+    /// `basic` is given.
+    pub async fn send_form(
+        &self,
+        app: &Router,
+        uri: &str,
+        form: &[(&str, &str)],
+        basic: Option<(&str, &str)>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("x-forwarded-for", &self.ip_address)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+        if let Some((id, secret)) = basic {
+            let credentials = STANDARD.encode(format!("{}:{}", id, secret));
+            request = request.header(header::AUTHORIZATION, format!("Basic {}", credentials));
+        }
+        let body = serde_urlencoded::to_string(form).unwrap();
+
+        call(app, request.body(Body::from(body)).unwrap()).await
+    }
+
+
+    /// Registers an account with a fresh email and [`PASSWORD`] and returns the
+    /// `/auth/register` response: `token`, `refresh_token` and `user`.
+    pub async fn register(&self) -> Value {
+        let app = Router::new()
+            .route("/auth/register", post(handlers::auth::register))
+            .with_state(self.state.clone());
+        let email = format!("test-{}@example.com", Uuid::new_v4());
+        let body = json!({ "name": "Test User", "email": email, "password": PASSWORD });
+
+        let (status, response) =
+            self.send(&app, Method::POST, "/auth/register", Some(body), None).await;
+        assert_eq!(status, StatusCode::OK, "{}", response);
+        response
+    }
+
+    /// The last email sent to `to`.
+    pub async fn last_email_to(&self, to: &str) -> Option<Email> {
+        let messages = self.outbox.messages().await.unwrap();
+        messages.into_iter().rev().find(|email| email.to == to)
+    }
+}
+
+impl Drop for TestContext {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.outbox_path);
+    }
+}
+
+async fn call(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (status, body)
+}
+
+/// The `token` query parameter of the first link in an email body.
+pub fn link_token(email: &Email) -> String {
+    let start = email.body.find("token=").expect("no link in email") + "token=".len();
+    email.body[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
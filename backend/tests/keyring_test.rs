@@ -0,0 +1,125 @@
+use chrono::Utc;
+use cursor_backend::{
+    keyring::{Keyring, KeyringError},
+    models::{Claims, Role},
+};
+use jsonwebtoken::{
+    decode, decode_header, encode, jwk::AlgorithmParameters, Algorithm, DecodingKey, EncodingKey,
+    Header, Validation,
+};
+use uuid::Uuid;
+
+const KEYS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/keys");
+const RSA_KID: &str = "2024-01-rsa";
+const ED25519_KID: &str = "2024-06-ed25519";
+const RSA_PEM: &[u8] = include_bytes!("keys/2024-01-rsa.pem");
+const ED25519_PEM: &[u8] = include_bytes!("keys/2024-06-ed25519.pem");
+
+fn claims() -> Claims {
+    let now = Utc::now().timestamp();
+    Claims {
+        sub: Uuid::new_v4().to_string(),
+        email: "test@example.com".to_string(),
+        jti: Uuid::new_v4().to_string(),
+        sid: Uuid::new_v4().to_string(),
+        role: Role::Editor,
+        exp: (now + 900) as usize,
+        iat: now as usize,
+        iat_micros: None,
+        scopes: None,
+        client_id: None,
+    }
+}
+
+#[test]
+fn test_loaded_keyring_signs_with_the_newest_kid() {
+    let keys = Keyring::load(KEYS_DIR, None).unwrap();
+    assert_eq!(keys.signing_kid(), Some(ED25519_KID));
+
+    let token = keys.sign(&claims()).unwrap();
+    let header = decode_header(&token).unwrap();
+    assert_eq!(header.alg, Algorithm::EdDSA);
+    assert_eq!(header.kid.as_deref(), Some(ED25519_KID));
+
+    let decoded: Claims = keys.verify(&token).unwrap();
+    assert_eq!(decoded.email, "test@example.com");
+}
+
+#[test]
+fn test_old_keys_keep_verifying_during_rotation() {
+    // Before the rotation only the RSA key exists
+    let before = Keyring::from_pem(vec![(RSA_KID.to_string(), RSA_PEM.to_vec())], None).unwrap();
+    let old_token = before.sign(&claims()).unwrap();
+    assert_eq!(decode_header(&old_token).unwrap().alg, Algorithm::RS256);
+
+    // During it both keys are present and the new one signs
+    let during = Keyring::load(KEYS_DIR, None).unwrap();
+    assert!(during.verify::<Claims>(&old_token).is_ok());
+
+    // Once the RSA key is removed its tokens are rejected
+    let after =
+        Keyring::from_pem(vec![(ED25519_KID.to_string(), ED25519_PEM.to_vec())], None).unwrap();
+    assert!(after.verify::<Claims>(&old_token).is_err());
+    assert!(after.verify::<Claims>(&during.sign(&claims()).unwrap()).is_ok());
+}
+
+#[test]
+fn test_signing_kid_can_be_pinned() {
+    let keys = Keyring::load(KEYS_DIR, Some(RSA_KID)).unwrap();
+    assert_eq!(keys.signing_kid(), Some(RSA_KID));
+    assert_eq!(decode_header(&keys.sign(&claims()).unwrap()).unwrap().alg, Algorithm::RS256);
+
+    assert!(matches!(
+        Keyring::load(KEYS_DIR, Some("missing")),
+        Err(KeyringError::UnknownSigningKey(kid)) if kid == "missing"
+    ));
+}
+
+#[test]
+fn test_jwks_lets_other_services_verify_tokens() {
+    let keys = Keyring::load(KEYS_DIR, None).unwrap();
+    let jwks = keys.jwks();
+    assert_eq!(jwks.keys.len(), 2);
+    assert!(matches!(jwks.find(RSA_KID).unwrap().algorithm, AlgorithmParameters::RSA(_)));
+
+    // The published set carries public keys only
+    let json = serde_json::to_value(&jwks).unwrap();
+    assert_eq!(json["keys"][1]["kid"], ED25519_KID);
+    assert_eq!(json["keys"][1]["kty"], "OKP");
+    assert!(json["keys"][1].get("d").is_none());
+
+    let token = keys.sign(&claims()).unwrap();
+    let kid = decode_header(&token).unwrap().kid.unwrap();
+    let key = DecodingKey::from_jwk(jwks.find(&kid).unwrap()).unwrap();
+    let decoded = decode::<Claims>(&token, &key, &Validation::new(Algorithm::EdDSA)).unwrap();
+    assert_eq!(decoded.claims.role, Role::Editor);
+}
+
+#[test]
+fn test_tokens_must_name_a_known_key_and_algorithm() {
+    let keys = Keyring::load(KEYS_DIR, None).unwrap();
+    let hs256 = Keyring::hs256(b"secret");
+
+    // No kid, unknown kid, and an HS256 token claiming a kid from the ring
+    assert!(keys.verify::<Claims>(&hs256.sign(&claims()).unwrap()).is_err());
+    assert!(hs256.verify::<Claims>(&keys.sign(&claims()).unwrap()).is_err());
+    let mut header = Header::new(Algorithm::HS256);
+    header.kid = Some(ED25519_KID.to_string());
+    let forged = encode(&header, &claims(), &EncodingKey::from_secret(b"secret")).unwrap();
+    assert!(keys.verify::<Claims>(&forged).is_err());
+
+    assert!(hs256.jwks().keys.is_empty());
+}
+
+#[test]
+fn test_non_key_files_are_rejected() {
+    assert!(matches!(
+        Keyring::from_pem(vec![("bad".to_string(), b"not a pem".to_vec())], None),
+        Err(KeyringError::Pem { .. })
+    ));
+
+    let empty = std::env::temp_dir().join(format!("keys-{}", Uuid::new_v4()));
+    std::fs::create_dir(&empty).unwrap();
+    assert!(matches!(Keyring::load(&empty, None), Err(KeyringError::Empty(_))));
+    std::fs::remove_dir(empty).unwrap();
+}
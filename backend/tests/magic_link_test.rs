@@ -0,0 +1,116 @@
+mod common;
+
+use axum::{http::Method, http::StatusCode, routing::post, Router};
+use chrono::{Duration, Utc};
+use common::{link_token, TestContext};
+use cursor_backend::{handlers, schema::magic_link_tokens};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde_json::{json, Value};
+
+fn app(ctx: &TestContext) -> Router {
+    Router::new()
+        .route("/auth/magic-link", post(handlers::auth::request_magic_link))
+        .route("/auth/magic-link/consume", post(handlers::auth::consume_magic_link))
+        .with_state(ctx.state.clone())
+}
+
+/// Asks for a sign-in link for `email` and returns its token.
+async fn request_link(ctx: &TestContext, app: &Router, email: &str) -> String {
+    let body = json!({ "email": email });
+    let (status, _) = ctx.send(app, Method::POST, "/auth/magic-link", Some(body), None).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    link_token(&ctx.last_email_to(email).await.expect("no sign-in email"))
+}
+
+async fn consume_link(ctx: &TestContext, app: &Router, token: &str) -> (StatusCode, Value) {
+    let body = json!({ "token": token });
+    ctx.send(app, Method::POST, "/auth/magic-link/consume", Some(body), None).await
+}
+
+#[tokio::test]
+async fn test_link_works_once() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let user = ctx.register().await;
+    let email = user["user"]["email"].as_str().unwrap();
+
+    let token = request_link(&ctx, &app, email).await;
+    let (status, response) = consume_link(&ctx, &app, &token).await;
+    assert_eq!(status, StatusCode::OK, "{}", response);
+    assert_eq!(response["user"]["email"], email);
+    assert!(response["token"].is_string());
+
+    let (status, response) = consume_link(&ctx, &app, &token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response["error"], "invalid_token");
+}
+
+#[tokio::test]
+async fn test_expired_link_fails() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let user = ctx.register().await;
+    let email = user["user"]["email"].as_str().unwrap();
+
+    let token = request_link(&ctx, &app, email).await;
+    let mut conn = ctx.state.pool.get().await.unwrap();
+    diesel::update(magic_link_tokens::table.filter(magic_link_tokens::email.eq(email)))
+        .set(magic_link_tokens::expires_at.eq(Utc::now() - Duration::minutes(1)))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let (status, response) = consume_link(&ctx, &app, &token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response["error"], "invalid_token");
+}
+
+#[tokio::test]
+async fn test_using_a_link_rejects_the_others() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let user = ctx.register().await;
+    let email = user["user"]["email"].as_str().unwrap();
+
+    let first = request_link(&ctx, &app, email).await;
+    let second = request_link(&ctx, &app, email).await;
+    assert_ne!(first, second);
+
+    let (status, _) = consume_link(&ctx, &app, &second).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = consume_link(&ctx, &app, &first).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_link_requests_are_throttled() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let user = ctx.register().await;
+    let email = user["user"]["email"].as_str().unwrap();
+
+    for _ in 0..5 {
+        request_link(&ctx, &app, email).await;
+    }
+    let body = json!({ "email": email });
+    let (status, response) =
+        ctx.send(&app, Method::POST, "/auth/magic-link", Some(body), None).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response["error"], "too_many_attempts");
+    let messages = ctx.outbox.messages().await.unwrap();
+    let links = messages.iter().filter(|message| message.subject == "Your sign-in link");
+    assert_eq!(links.count(), 5);
+
+    // Unknown addresses are throttled the same way
+    let unknown = format!("unknown-{}", email);
+    for _ in 0..5 {
+        let body = json!({ "email": unknown });
+        let (status, _) = ctx.send(&app, Method::POST, "/auth/magic-link", Some(body), None).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+    }
+    let body = json!({ "email": unknown });
+    let (status, _) = ctx.send(&app, Method::POST, "/auth/magic-link", Some(body), None).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
@@ -0,0 +1,46 @@
+use cursor_backend::mailer::{Email, FileOutbox, Mailer};
+use uuid::Uuid;
+
+fn outbox_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("outbox-{}.jsonl", Uuid::new_v4()))
+}
+
+#[tokio::test]
+async fn test_file_outbox_records_sent_emails_in_order() {
+    let path = outbox_path();
+    let outbox = FileOutbox::new(&path);
+    assert!(outbox.messages().await.unwrap().is_empty());
+
+    let first = Email::password_reset("one@example.com", "http://localhost/reset-password?token=a");
+    let second = Email::password_reset("two@example.com", "http://localhost/reset-password?token=b");
+    outbox.send(&first).await.unwrap();
+    outbox.send(&second).await.unwrap();
+
+    assert_eq!(outbox.messages().await.unwrap(), vec![first, second]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_password_reset_email_contains_the_link() {
+    let email = Email::password_reset("user@example.com", "http://localhost/reset-password?token=abc");
+
+    assert_eq!(email.to, "user@example.com");
+    assert!(email.body.contains("http://localhost/reset-password?token=abc"));
+}
+
+#[test]
+fn test_email_verification_email_contains_the_link() {
+    let email = Email::email_verification("user@example.com", "http://localhost/verify-email?token=abc");
+
+    assert_eq!(email.to, "user@example.com");
+    assert!(email.body.contains("http://localhost/verify-email?token=abc"));
+}
+
+#[test]
+fn test_magic_link_email_contains_the_link() {
+    let email = Email::magic_link("user@example.com", "http://localhost/magic-link?token=abc");
+
+    assert_eq!(email.to, "user@example.com");
+    assert!(email.body.contains("http://localhost/magic-link?token=abc"));
+    assert!(email.body.contains("15 minutes"));
+}
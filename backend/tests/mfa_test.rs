@@ -0,0 +1,106 @@
+use chrono::{TimeZone, Utc};
+use cursor_backend::{
+    mfa::{
+        generate_recovery_codes, generate_totp_secret, is_totp_code, normalize_recovery_code,
+        totp_enrollment, verify_totp, SecretCipher, RECOVERY_CODE_COUNT, TOTP_STEP_SECONDS,
+    },
+    models::{LoginResponse, MfaChallengeResponse},
+};
+use totp_rs::{Algorithm, TOTP};
+
+fn code_at(secret: &[u8], timestamp: i64) -> String {
+    TOTP::new_unchecked(
+        Algorithm::SHA1,
+        6,
+        0,
+        TOTP_STEP_SECONDS,
+        secret.to_vec(),
+        None,
+        String::new(),
+    )
+    .generate(timestamp as u64)
+}
+
+#[test]
+fn test_secret_cipher_round_trips_and_rejects_tampering() {
+    let cipher = SecretCipher::new(&[7; 32]);
+    let secret = generate_totp_secret();
+
+    let sealed = cipher.encrypt(&secret).unwrap();
+    assert_eq!(cipher.decrypt(&sealed).unwrap(), secret);
+    // A fresh nonce every time
+    assert_ne!(sealed, cipher.encrypt(&secret).unwrap());
+
+    let other = SecretCipher::new(&[8; 32]);
+    assert!(other.decrypt(&sealed).is_err());
+
+    let mut bytes = sealed.into_bytes();
+    let last = bytes.len() - 3;
+    bytes[last] = if bytes[last] == b'A' { b'B' } else { b'A' };
+    assert!(cipher.decrypt(&String::from_utf8(bytes).unwrap()).is_err());
+    assert!(cipher.decrypt("not base64!").is_err());
+}
+
+#[test]
+fn test_totp_accepts_adjacent_steps_only() {
+    let secret = generate_totp_secret();
+    let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+    let step = now.timestamp() / TOTP_STEP_SECONDS as i64;
+    let step_seconds = TOTP_STEP_SECONDS as i64;
+
+    assert_eq!(verify_totp(&secret, &code_at(&secret, now.timestamp()), now), Some(step));
+    let previous = code_at(&secret, now.timestamp() - step_seconds);
+    assert_eq!(verify_totp(&secret, &previous, now), Some(step - 1));
+    let next = code_at(&secret, now.timestamp() + step_seconds);
+    assert_eq!(verify_totp(&secret, &next, now), Some(step + 1));
+
+    let stale = code_at(&secret, now.timestamp() - 3 * step_seconds);
+    assert_eq!(verify_totp(&secret, &stale, now), None);
+    assert_eq!(verify_totp(&generate_totp_secret(), &code_at(&secret, 1_700_000_000), now), None);
+}
+
+#[test]
+fn test_enrollment_uri_names_issuer_and_account() {
+    let secret = generate_totp_secret();
+    let (base32, uri) = totp_enrollment(&secret, "user@example.com").unwrap();
+
+    assert!(uri.starts_with("otpauth://totp/Cursor:user%40example.com?"));
+    assert!(uri.contains(&format!("secret={}", base32)));
+    assert!(uri.contains("issuer=Cursor"));
+}
+
+#[test]
+fn test_recovery_codes_are_unique_and_normalized() {
+    let codes = generate_recovery_codes();
+
+    assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+    for code in &codes {
+        assert_eq!(code.len(), 11);
+        assert_eq!(code.as_bytes()[5], b'-');
+        assert!(!is_totp_code(code));
+    }
+    let mut unique = codes.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), codes.len());
+
+    assert_eq!(normalize_recovery_code("K3X9Q-7MF2A"), "k3x9q7mf2a");
+    assert_eq!(normalize_recovery_code(" k3x9q 7mf2a "), "k3x9q7mf2a");
+    assert!(is_totp_code("012345"));
+    assert!(!is_totp_code("01234"));
+    assert!(!is_totp_code("01234a"));
+}
+
+#[test]
+fn test_mfa_challenge_serializes_without_tag() {
+    let response = LoginResponse::MfaRequired(MfaChallengeResponse {
+        mfa_required: true,
+        mfa_token: "token".to_string(),
+        expires_in: 300,
+    });
+
+    assert_eq!(
+        serde_json::to_value(&response).unwrap(),
+        serde_json::json!({ "mfa_required": true, "mfa_token": "token", "expires_in": 300 })
+    );
+}
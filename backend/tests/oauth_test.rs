@@ -0,0 +1,457 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    middleware,
+    routing::post,
+    Router,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use common::TestContext;
+use cursor_backend::{
+    handlers,
+    middleware::auth::auth_middleware,
+    models::{Claims, IntrospectionResponse, Scope, TokenRequest, TokenResponse},
+    oauth::{
+        basic_credentials, format_scopes, is_code_challenge, is_valid_redirect_uri,
+        parse_scopes, redirect_with, requested_scopes, verify_code_challenge,
+    },
+    revocation::RevocationList,
+    services::oauth_service,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+// The example from RFC 7636 appendix B
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+#[test]
+fn test_pkce_matches_rfc_example() {
+    assert!(is_code_challenge(CHALLENGE));
+    assert!(verify_code_challenge(VERIFIER, CHALLENGE));
+    assert_eq!(URL_SAFE_NO_PAD.encode(Sha256::digest(VERIFIER.as_bytes())), CHALLENGE);
+}
+
+#[test]
+fn test_pkce_rejects_wrong_or_malformed_verifiers() {
+    let other = "x".repeat(43);
+    assert!(!verify_code_challenge(&other, CHALLENGE));
+    // The plain method would compare the verifier itself
+    assert!(!verify_code_challenge(CHALLENGE, CHALLENGE));
+
+    let short = "a".repeat(42);
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(short.as_bytes()));
+    assert!(!verify_code_challenge(&short, &challenge));
+    let invalid = format!("{}+", "a".repeat(43));
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(invalid.as_bytes()));
+    assert!(!verify_code_challenge(&invalid, &challenge));
+
+    assert!(!is_code_challenge("short"));
+    assert!(!is_code_challenge(&format!("{}=", &CHALLENGE[..42])));
+}
+
+#[test]
+fn test_scopes_are_space_separated() {
+    assert_eq!(
+        parse_scopes("users:read  admin users:read"),
+        Ok(vec![Scope::UsersRead, Scope::Admin])
+    );
+    assert!(parse_scopes("users:read users").is_err());
+    assert_eq!(format_scopes(&[Scope::UsersRead, Scope::UsersWrite]), "users:read users:write");
+}
+
+#[test]
+fn test_requested_scopes_must_be_allowed() {
+    let allowed = [Scope::UsersRead, Scope::UsersWrite];
+
+    assert_eq!(requested_scopes(None, &allowed), Some(allowed.to_vec()));
+    assert_eq!(requested_scopes(Some("users:read"), &allowed), Some(vec![Scope::UsersRead]));
+    assert_eq!(requested_scopes(Some("admin"), &allowed), None);
+    assert_eq!(requested_scopes(Some("users:read admin"), &allowed), None);
+    assert_eq!(requested_scopes(Some(""), &allowed), None);
+    assert_eq!(requested_scopes(Some("bogus"), &allowed), None);
+    assert_eq!(requested_scopes(None, &[]), None);
+}
+
+#[test]
+fn test_redirect_uris_need_https_outside_localhost() {
+    for uri in [
+        "https://app.example.com/callback",
+        "https://app.example.com/callback?tenant=1",
+        "http://localhost:3000/callback",
+        "http://127.0.0.1:8080/cb",
+        "http://[::1]/cb",
+    ] {
+        assert!(is_valid_redirect_uri(uri), "{}", uri);
+    }
+    for uri in [
+        "http://app.example.com/callback",
+        "https://app.example.com/callback#fragment",
+        "javascript:alert(1)",
+        "/callback",
+        "",
+    ] {
+        assert!(!is_valid_redirect_uri(uri), "{}", uri);
+    }
+}
+
+#[test]
+fn test_redirect_keeps_existing_query() {
+    let redirect = redirect_with(
+        "https://app.example.com/cb?tenant=1",
+        &[("code", "abc"), ("state", "x y&z")],
+    );
+
+    assert_eq!(
+        redirect.as_deref(),
+        Some("https://app.example.com/cb?tenant=1&code=abc&state=x+y%26z")
+    );
+}
+
+#[test]
+fn test_basic_credentials_are_form_decoded() {
+    let header = format!("Basic {}", STANDARD.encode("my%20client:se%3Acret"));
+    assert_eq!(
+        basic_credentials(&header),
+        Some(("my client".to_string(), "se:cret".to_string()))
+    );
+
+    assert_eq!(basic_credentials("Bearer token"), None);
+    assert_eq!(basic_credentials("Basic not-base64!"), None);
+    assert_eq!(basic_credentials(&format!("Basic {}", STANDARD.encode("nocolon"))), None);
+}
+
+#[test]
+fn test_token_request_parses_from_form() {
+    let request: TokenRequest = serde_urlencoded::from_str(
+        "grant_type=authorization_code&code=abc&redirect_uri=https%3A%2F%2Fapp.example.com%2Fcb\
+         &code_verifier=v&client_id=id",
+    )
+    .unwrap();
+
+    assert_eq!(request.grant_type, "authorization_code");
+    assert_eq!(request.code.as_deref(), Some("abc"));
+    assert_eq!(request.redirect_uri.as_deref(), Some("https://app.example.com/cb"));
+    assert_eq!(request.client_secret, None);
+}
+
+#[test]
+fn test_token_response_omits_missing_refresh_token() {
+    let response = TokenResponse {
+        access_token: "token".to_string(),
+        token_type: "Bearer".to_string(),
+        expires_in: 900,
+        refresh_token: None,
+        scope: "users:read".to_string(),
+    };
+
+    assert_eq!(
+        serde_json::to_value(&response).unwrap(),
+        serde_json::json!({
+            "access_token": "token",
+            "token_type": "Bearer",
+            "expires_in": 900,
+            "scope": "users:read",
+        })
+    );
+}
+
+#[test]
+fn test_inactive_introspection_only_says_so() {
+    assert_eq!(
+        serde_json::to_value(IntrospectionResponse::default()).unwrap(),
+        serde_json::json!({ "active": false })
+    );
+}
+
+const REDIRECT_URI: &str = "https://app.example.com/callback";
+
+fn app(ctx: &TestContext) -> Router {
+    Router::new()
+        .route("/oauth/authorize", post(handlers::oauth::authorize_decision))
+        .route_layer(middleware::from_fn_with_state(ctx.state.clone(), auth_middleware))
+        .route("/oauth/token", post(handlers::oauth::token))
+        .route("/oauth/introspect", post(handlers::oauth::introspect))
+        .with_state(ctx.state.clone())
+}
+
+/// Registers a client allowed `users:read` and `users:write` for the
+/// account of `session`; returns its id and secret.
+async fn create_client(
+    ctx: &TestContext,
+    session: &Value,
+    public: bool,
+) -> (String, Option<String>) {
+    let owner_id = session["user"]["id"].as_str().unwrap().parse::<Uuid>().unwrap();
+    let (client, secret) = oauth_service::create_client(
+        &ctx.state.pool,
+        owner_id,
+        "Test app",
+        vec![REDIRECT_URI.to_string()],
+        vec![Scope::UsersRead, Scope::UsersWrite],
+        public,
+    )
+    .await
+    .unwrap();
+    (client.id.to_string(), secret)
+}
+
+/// Approves a `users:read` request from the client as the user of `session`
+/// and returns the authorization code.
+async fn authorize(ctx: &TestContext, app: &Router, session: &Value, client_id: &str) -> String {
+    let body = json!({
+        "response_type": "code",
+        "client_id": client_id,
+        "redirect_uri": REDIRECT_URI,
+        "scope": "users:read",
+        "state": "xyz",
+        "code_challenge": CHALLENGE,
+        "code_challenge_method": "S256",
+        "approve": true,
+    });
+    let token = session["token"].as_str();
+    let (status, response) =
+        ctx.send(app, Method::POST, "/oauth/authorize", Some(body), token).await;
+    assert_eq!(status, StatusCode::OK, "{}", response);
+
+    let redirect_to = url::Url::parse(response["redirect_to"].as_str().unwrap()).unwrap();
+    assert!(redirect_to.as_str().starts_with(REDIRECT_URI));
+    let param = |name: &str| {
+        let mut pairs = redirect_to.query_pairs();
+        pairs.find(|(key, _)| key == name).map(|(_, value)| value.into_owned())
+    };
+    assert_eq!(param("state").as_deref(), Some("xyz"));
+    param("code").expect("no code in redirect")
+}
+
+/// Redeems a code for a public client.
+async fn redeem(
+    ctx: &TestContext,
+    app: &Router,
+    client_id: &str,
+    code: &str,
+    verifier: &str,
+) -> (StatusCode, Value) {
+    let form = [
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", verifier),
+        ("client_id", client_id),
+    ];
+    ctx.send_form(app, "/oauth/token", &form, None).await
+}
+
+async fn introspect(
+    ctx: &TestContext,
+    app: &Router,
+    (id, secret): (&str, &str),
+    token: &Value,
+) -> Value {
+    let form = [("token", token.as_str().unwrap())];
+    let (status, response) =
+        ctx.send_form(app, "/oauth/introspect", &form, Some((id, secret))).await;
+    assert_eq!(status, StatusCode::OK, "{}", response);
+    response
+}
+
+#[tokio::test]
+async fn test_authorization_code_grant_with_pkce() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let session = ctx.register().await;
+    let (client_id, _) = create_client(&ctx, &session, true).await;
+    let (server_id, server_secret) = create_client(&ctx, &session, false).await;
+    let server = (server_id.as_str(), server_secret.as_deref().unwrap());
+
+    let code = authorize(&ctx, &app, &session, &client_id).await;
+    let (status, tokens) = redeem(&ctx, &app, &client_id, &code, VERIFIER).await;
+    assert_eq!(status, StatusCode::OK, "{}", tokens);
+    assert_eq!(tokens["token_type"], "Bearer");
+    assert_eq!(tokens["scope"], "users:read");
+    assert!(tokens["refresh_token"].is_string());
+
+    let introspection = introspect(&ctx, &app, server, &tokens["access_token"]).await;
+    assert_eq!(introspection["active"], true);
+    assert_eq!(introspection["client_id"], client_id);
+    assert_eq!(introspection["scope"], "users:read");
+    assert_eq!(introspection["sub"], session["user"]["id"]);
+    assert_eq!(introspection["username"], session["user"]["email"]);
+
+    // A wrong verifier uses the code up all the same
+    let code = authorize(&ctx, &app, &session, &client_id).await;
+    let other = "x".repeat(43);
+    let (status, response) = redeem(&ctx, &app, &client_id, &code, &other).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "invalid_grant");
+    let (status, _) = redeem(&ctx, &app, &client_id, &code, VERIFIER).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_reused_code_revokes_its_tokens() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let session = ctx.register().await;
+    let (client_id, _) = create_client(&ctx, &session, true).await;
+    let (server_id, server_secret) = create_client(&ctx, &session, false).await;
+    let server = (server_id.as_str(), server_secret.as_deref().unwrap());
+
+    let code = authorize(&ctx, &app, &session, &client_id).await;
+    let (status, tokens) = redeem(&ctx, &app, &client_id, &code, VERIFIER).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, response) = redeem(&ctx, &app, &client_id, &code, VERIFIER).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "invalid_grant");
+
+    let introspection = introspect(&ctx, &app, server, &tokens["access_token"]).await;
+    assert_eq!(introspection, json!({ "active": false }));
+    // Other instances learn of it on their next sync
+    let revocations = RevocationList::default();
+    revocations.sync(&ctx.state.pool).await.unwrap();
+    let claims: Claims = ctx.state.keys.verify(tokens["access_token"].as_str().unwrap()).unwrap();
+    assert!(revocations.is_revoked(&claims));
+
+    let form = [
+        ("grant_type", "refresh_token"),
+        ("refresh_token", tokens["refresh_token"].as_str().unwrap()),
+        ("client_id", client_id.as_str()),
+    ];
+    let (status, response) = ctx.send_form(&app, "/oauth/token", &form, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "invalid_grant");
+}
+
+#[tokio::test]
+async fn test_client_credentials_grant() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let session = ctx.register().await;
+    let (client_id, client_secret) = create_client(&ctx, &session, false).await;
+    let client_secret = client_secret.unwrap();
+    let basic = Some((client_id.as_str(), client_secret.as_str()));
+
+    let form = [("grant_type", "client_credentials"), ("scope", "users:read")];
+    let (status, tokens) = ctx.send_form(&app, "/oauth/token", &form, basic).await;
+    assert_eq!(status, StatusCode::OK, "{}", tokens);
+    assert_eq!(tokens["scope"], "users:read");
+    assert!(tokens.get("refresh_token").is_none());
+
+    // The token acts as the client's owner
+    let introspection =
+        introspect(&ctx, &app, (&client_id, &client_secret), &tokens["access_token"]).await;
+    assert_eq!(introspection["active"], true);
+    assert_eq!(introspection["sub"], session["user"]["id"]);
+
+    let wrong = Some((client_id.as_str(), "wrong-secret"));
+    let (status, response) = ctx.send_form(&app, "/oauth/token", &form, wrong).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response["error"], "invalid_client");
+
+    let form = [("grant_type", "client_credentials"), ("scope", "admin")];
+    let (status, response) = ctx.send_form(&app, "/oauth/token", &form, basic).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "invalid_scope");
+
+    let (public_id, _) = create_client(&ctx, &session, true).await;
+    let form = [("grant_type", "client_credentials"), ("client_id", public_id.as_str())];
+    let (status, response) = ctx.send_form(&app, "/oauth/token", &form, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "unauthorized_client");
+}
+
+#[tokio::test]
+async fn test_refresh_token_grant_rotates() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let session = ctx.register().await;
+    let (client_id, client_secret) = create_client(&ctx, &session, false).await;
+    let client_secret = client_secret.unwrap();
+    let basic = Some((client_id.as_str(), client_secret.as_str()));
+    let (other_id, other_secret) = create_client(&ctx, &session, false).await;
+    let other = Some((other_id.as_str(), other_secret.as_deref().unwrap()));
+
+    let code = authorize(&ctx, &app, &session, &client_id).await;
+    let form = [
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", VERIFIER),
+    ];
+    let (status, tokens) = ctx.send_form(&app, "/oauth/token", &form, basic).await;
+    assert_eq!(status, StatusCode::OK, "{}", tokens);
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+
+    // Refresh tokens only work for the client they were issued to
+    let form = [("grant_type", "refresh_token"), ("refresh_token", refresh_token)];
+    let (status, _) = ctx.send_form(&app, "/oauth/token", &form, other).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let narrowed = [
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("scope", "users:write"),
+    ];
+    let (status, response) = ctx.send_form(&app, "/oauth/token", &narrowed, basic).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "invalid_scope");
+
+    let (status, rotated) = ctx.send_form(&app, "/oauth/token", &form, basic).await;
+    assert_eq!(status, StatusCode::OK, "{}", rotated);
+    assert_eq!(rotated["scope"], "users:read");
+    assert_ne!(rotated["refresh_token"], tokens["refresh_token"]);
+
+    // Replaying the old token revokes its successor too
+    let (status, response) = ctx.send_form(&app, "/oauth/token", &form, basic).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "invalid_grant");
+    let form = [
+        ("grant_type", "refresh_token"),
+        ("refresh_token", rotated["refresh_token"].as_str().unwrap()),
+    ];
+    let (status, _) = ctx.send_form(&app, "/oauth/token", &form, basic).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_introspection() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let session = ctx.register().await;
+    let (client_id, client_secret) = create_client(&ctx, &session, false).await;
+    let client = (client_id.as_str(), client_secret.as_deref().unwrap());
+    let (other_id, other_secret) = create_client(&ctx, &session, false).await;
+    let other = (other_id.as_str(), other_secret.as_deref().unwrap());
+
+    let code = authorize(&ctx, &app, &session, &client_id).await;
+    let form = [
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", VERIFIER),
+    ];
+    let (_, tokens) = ctx.send_form(&app, "/oauth/token", &form, Some(client)).await;
+
+    let introspection = introspect(&ctx, &app, client, &tokens["refresh_token"]).await;
+    assert_eq!(introspection["active"], true);
+    assert_eq!(introspection["client_id"], client_id);
+    assert!(introspection.get("token_type").is_none());
+
+    // Refresh tokens are only described to the client holding them
+    let introspection = introspect(&ctx, &app, other, &tokens["refresh_token"]).await;
+    assert_eq!(introspection, json!({ "active": false }));
+    let introspection = introspect(&ctx, &app, other, &json!("not-a-token")).await;
+    assert_eq!(introspection, json!({ "active": false }));
+
+    let (public_id, _) = create_client(&ctx, &session, true).await;
+    let form = [("token", tokens["access_token"].as_str().unwrap()), ("client_id", &public_id)];
+    let (status, response) = ctx.send_form(&app, "/oauth/introspect", &form, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response["error"], "invalid_client");
+}
@@ -0,0 +1,70 @@
+use argon2::Params;
+use cursor_backend::password::{Argon2Hasher, BcryptHasher, PasswordError, PasswordHasher, Passwords};
+
+// Minimal work factors keep the tests fast in debug builds
+fn argon2(t_cost: u32) -> Argon2Hasher {
+    Argon2Hasher::new(Params::new(Params::MIN_M_COST, t_cost, 1, None).unwrap())
+}
+
+fn passwords() -> Passwords {
+    Passwords::new(Box::new(argon2(1)), vec![Box::new(BcryptHasher::new(4))])
+}
+
+#[test]
+fn test_new_hashes_are_argon2id_and_verify() {
+    let passwords = passwords();
+    let hash = passwords.hash("password123").unwrap();
+
+    assert!(hash.starts_with("$argon2id$v=19$"));
+    assert!(passwords.verify("password123", &hash).unwrap());
+    assert!(!passwords.verify("password124", &hash).unwrap());
+    assert!(!passwords.needs_rehash(&hash));
+    assert_ne!(hash, passwords.hash("password123").unwrap());
+}
+
+#[test]
+fn test_bcrypt_hashes_still_verify_and_need_rehash() {
+    let passwords = passwords();
+    let hash = bcrypt::hash("password123", 4).unwrap();
+
+    assert!(passwords.verify("password123", &hash).unwrap());
+    assert!(!passwords.verify("wrong", &hash).unwrap());
+    assert!(passwords.needs_rehash(&hash));
+}
+
+#[test]
+fn test_argon2_hashes_with_outdated_params_need_rehash() {
+    let old_hash = argon2(1).hash("password123").unwrap();
+    let stronger = argon2(2);
+
+    assert!(stronger.verify("password123", &old_hash).unwrap());
+    assert!(stronger.needs_rehash(&old_hash));
+    assert!(!stronger.needs_rehash(&stronger.hash("password123").unwrap()));
+}
+
+#[test]
+fn test_bcrypt_hasher_flags_lower_costs() {
+    let hasher = BcryptHasher::new(5);
+
+    assert!(hasher.needs_rehash(&bcrypt::hash("password123", 4).unwrap()));
+    assert!(!hasher.needs_rehash(&hasher.hash("password123").unwrap()));
+}
+
+#[test]
+fn test_unknown_hash_formats_are_rejected() {
+    let passwords = passwords();
+
+    assert!(matches!(
+        passwords.verify("password123", "plaintext"),
+        Err(PasswordError::UnknownFormat)
+    ));
+    assert!(passwords.needs_rehash("plaintext"));
+}
+
+#[test]
+fn test_dummy_verification_always_fails() {
+    let passwords = passwords();
+
+    assert!(!passwords.verify_dummy("password123"));
+    assert!(!passwords.verify_dummy("not a password"));
+}
@@ -0,0 +1,265 @@
+use cursor_backend::query::{
+    like_pattern, prefix_tsquery, Cursor, FieldFilter, FilterOp, Keyset, ListQuery, Pagination,
+    QueryError, Relations, SortKey, MAX_PAGE_LIMIT,
+};
+
+fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+    items
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_split_key_recognises_operators() {
+    assert_eq!(FilterOp::split_key("username"), ("username", FilterOp::Eq));
+    assert_eq!(FilterOp::split_key("created_at_gte"), ("created_at", FilterOp::Gte));
+    assert_eq!(FilterOp::split_key("created_at_lte"), ("created_at", FilterOp::Lte));
+    assert_eq!(FilterOp::split_key("id_ne"), ("id", FilterOp::Ne));
+    assert_eq!(FilterOp::split_key("address.city_like"), ("address.city", FilterOp::Like));
+    assert_eq!(FilterOp::split_key("_like"), ("_like", FilterOp::Eq));
+}
+
+#[test]
+fn test_repeated_keys_are_grouped() {
+    let query = ListQuery::from_pairs(pairs(&[
+        ("username", "Bret"),
+        ("address.city", "Gwenborough"),
+        ("username", "Antonette"),
+    ]))
+    .unwrap();
+
+    assert_eq!(
+        query.filters,
+        vec![
+            FieldFilter {
+                field: "username".to_string(),
+                op: FilterOp::Eq,
+                values: vec!["Bret".to_string(), "Antonette".to_string()],
+            },
+            FieldFilter {
+                field: "address.city".to_string(),
+                op: FilterOp::Eq,
+                values: vec!["Gwenborough".to_string()],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_control_parameters_are_not_filters() {
+    let query = ListQuery::from_pairs(pairs(&[("_page", "2"), ("q", "leanne"), ("name_like", "lea")])).unwrap();
+
+    assert_eq!(query.filters.len(), 1);
+    assert_eq!(query.filters[0].field, "name");
+    assert_eq!(query.filters[0].op, FilterOp::Like);
+}
+
+#[test]
+fn test_like_pattern_escapes_wildcards() {
+    assert_eq!(like_pattern("Bret"), "%Bret%");
+    assert_eq!(like_pattern("50%_off"), "%50\\%\\_off%");
+}
+
+#[test]
+fn test_unknown_field_lists_allowed_fields() {
+    let err = QueryError::unknown_field("nickname", &["id", "name"]);
+
+    assert_eq!(err.to_string(), "Unknown field `nickname`. Allowed fields: id, name");
+}
+
+#[test]
+fn test_page_pagination_defaults_limit() {
+    let query = ListQuery::from_pairs(pairs(&[("_page", "3")])).unwrap();
+
+    assert_eq!(query.pagination, Some(Pagination::Page { page: 3, limit: 10 }));
+    assert_eq!(query.pagination.unwrap().offset(), 20);
+    assert_eq!(query.pagination.unwrap().limit(), Some(10));
+}
+
+#[test]
+fn test_slice_pagination() {
+    let query = ListQuery::from_pairs(pairs(&[("_start", "5"), ("_end", "15")])).unwrap();
+    assert_eq!(query.pagination, Some(Pagination::Slice { start: 5, end: Some(15) }));
+    assert_eq!(query.pagination.unwrap().limit(), Some(10));
+
+    let query = ListQuery::from_pairs(pairs(&[("_start", "5"), ("_limit", "3")])).unwrap();
+    assert_eq!(query.pagination, Some(Pagination::Slice { start: 5, end: Some(8) }));
+
+    let query = ListQuery::from_pairs(pairs(&[("_start", "5")])).unwrap();
+    assert_eq!(query.pagination.unwrap().limit(), None);
+}
+
+#[test]
+fn test_invalid_pagination_is_rejected() {
+    assert!(ListQuery::from_pairs(pairs(&[("_page", "0")])).is_err());
+    assert!(ListQuery::from_pairs(pairs(&[("_limit", "ten")])).is_err());
+}
+
+#[test]
+fn test_page_sizes_are_clamped() {
+    let query = ListQuery::from_pairs(pairs(&[("_page", "2"), ("_limit", "1000000")])).unwrap();
+    assert_eq!(query.pagination, Some(Pagination::Page { page: 2, limit: MAX_PAGE_LIMIT }));
+
+    let query = ListQuery::from_pairs(pairs(&[("_start", "5"), ("_end", "1000000")])).unwrap();
+    assert_eq!(query.pagination.unwrap().limit(), Some(MAX_PAGE_LIMIT));
+
+    let query = ListQuery::from_pairs(pairs(&[("limit", "1000000")])).unwrap();
+    assert_eq!(query.keyset.unwrap().limit, MAX_PAGE_LIMIT);
+}
+
+#[test]
+fn test_overflowing_pagination_is_rejected() {
+    let max = i64::MAX.to_string();
+
+    assert!(ListQuery::from_pairs(pairs(&[("_page", &max), ("_limit", "100")])).is_err());
+    assert!(ListQuery::from_pairs(pairs(&[("_start", &max), ("_limit", "10")])).is_err());
+    assert!(ListQuery::from_pairs(pairs(&[("_page", "99999999999999999999")])).is_err());
+
+    let query = ListQuery::from_pairs(pairs(&[("_start", &max), ("_end", &max)])).unwrap();
+    assert_eq!(query.pagination.unwrap().limit(), Some(0));
+
+    let query = ListQuery::from_pairs(pairs(&[("_page", "1"), ("_limit", "100")])).unwrap();
+    let headers = query.response_headers(Some(i64::MAX));
+    let last = format!("_page={}>; rel=\"last\"", i64::MAX / 100 + 1);
+    assert!(headers["link"].to_str().unwrap().contains(&last));
+}
+
+#[test]
+fn test_response_headers_include_total_count_and_links() {
+    let query = ListQuery::from_pairs(pairs(&[("_page", "2"), ("_limit", "3"), ("username_like", "a")])).unwrap();
+    let headers = query.response_headers(Some(10));
+
+    assert_eq!(headers["x-total-count"], "10");
+    assert_eq!(
+        headers["link"],
+        "<?_limit=3&username_like=a&_page=1>; rel=\"first\", \
+         <?_limit=3&username_like=a&_page=1>; rel=\"prev\", \
+         <?_limit=3&username_like=a&_page=3>; rel=\"next\", \
+         <?_limit=3&username_like=a&_page=4>; rel=\"last\""
+    );
+
+    assert!(ListQuery::default().response_headers(None).is_empty());
+}
+
+#[test]
+fn test_sort_pairs_fields_with_orders() {
+    let query = ListQuery::from_pairs(pairs(&[("_sort", "address.city,created_at,name"), ("_order", "asc,DESC")])).unwrap();
+
+    assert_eq!(
+        query.sort,
+        vec![
+            SortKey { field: "address.city".to_string(), descending: false },
+            SortKey { field: "created_at".to_string(), descending: true },
+            SortKey { field: "name".to_string(), descending: false },
+        ]
+    );
+    assert!(query.filters.is_empty());
+}
+
+#[test]
+fn test_invalid_sort_order_is_rejected() {
+    let err = ListQuery::from_pairs(pairs(&[("_sort", "name"), ("_order", "sideways")])).unwrap_err();
+
+    assert_eq!(
+        err,
+        QueryError::InvalidParameter { name: "_order".to_string(), value: "sideways".to_string() }
+    );
+}
+
+#[test]
+fn test_relations_accept_lists_and_repeated_keys() {
+    let query = ListQuery::from_pairs(pairs(&[
+        ("_embed", "comments, photos"),
+        ("_expand", "user"),
+        ("_embed", "todos"),
+        ("title_like", "qui"),
+    ]))
+    .unwrap();
+
+    assert_eq!(
+        query.relations,
+        Relations {
+            embed: vec!["comments".to_string(), "photos".to_string(), "todos".to_string()],
+            expand: vec!["user".to_string()],
+        }
+    );
+    assert_eq!(query.filters.len(), 1);
+    assert!(query.relations.embeds("photos"));
+    assert!(!query.relations.expands("post"));
+}
+
+#[test]
+fn test_unknown_relation_is_rejected() {
+    let relations = Relations { embed: vec![], expand: vec!["album".to_string()] };
+    let err = relations.validate(&["comments"], &["user"]).unwrap_err();
+
+    assert_eq!(err.to_string(), "Unknown `_expand` relation `album`. Allowed relations: user");
+    assert!(relations.validate(&[], &["album"]).is_ok());
+}
+
+#[test]
+fn test_search_is_not_a_field_filter() {
+    let query = ListQuery::from_pairs(pairs(&[("q", "  Romaguera "), ("name_like", "a")])).unwrap();
+
+    assert_eq!(query.search.as_deref(), Some("Romaguera"));
+    assert_eq!(query.filters.len(), 1);
+    assert_eq!(ListQuery::from_pairs(pairs(&[("q", " ")])).unwrap().search, None);
+}
+
+#[test]
+fn test_prefix_tsquery_strips_syntax() {
+    assert_eq!(prefix_tsquery("Roma crona").as_deref(), Some("roma:* & crona:*"));
+    assert_eq!(prefix_tsquery("Sincere@april.biz").as_deref(), Some("sincere:* & april:* & biz:*"));
+    assert_eq!(prefix_tsquery("a & !b | (c):*").as_deref(), Some("a:* & b:* & c:*"));
+    assert_eq!(prefix_tsquery("&|!"), None);
+}
+
+#[test]
+fn test_keyset_parameters() {
+    let query = ListQuery::from_pairs(pairs(&[("limit", "25"), ("cursor", "abc"), ("name_like", "a")])).unwrap();
+
+    assert_eq!(query.keyset, Some(Keyset { cursor: Some("abc".to_string()), limit: 25 }));
+    assert_eq!(query.pagination, None);
+    assert_eq!(query.filters.len(), 1);
+
+    let first = ListQuery::from_pairs(pairs(&[("cursor", "")])).unwrap();
+    assert_eq!(first.keyset, Some(Keyset { cursor: None, limit: 10 }));
+    assert!(first.reject_keyset().is_err());
+    assert!(ListQuery::default().reject_keyset().is_ok());
+}
+
+#[test]
+fn test_keyset_conflicts_with_offset_pagination_and_sorting() {
+    for key in ["_page", "_start", "_limit", "_sort"] {
+        let err = ListQuery::from_pairs(pairs(&[("limit", "5"), (key, "1")])).unwrap_err();
+        assert_eq!(err, QueryError::ConflictingParameters { name: key.to_string(), other: "cursor" });
+    }
+}
+
+#[test]
+fn test_cursor_round_trip_and_tampering() {
+    let cursor = Cursor {
+        created_at: chrono::DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap(),
+        id: uuid::Uuid::parse_str("550e8400-e29b-41d4-a716-446655440001").unwrap(),
+    };
+    let token = cursor.encode(b"secret");
+
+    assert_eq!(Cursor::decode(&token, b"secret").unwrap(), cursor);
+    assert!(Cursor::decode(&token, b"other-secret").is_err());
+
+    let (payload, signature) = token.split_once('.').unwrap();
+    let forged = format!("{}A.{}", payload, signature);
+    assert!(Cursor::decode(&forged, b"secret").is_err());
+    assert!(Cursor::decode("not-a-cursor", b"secret").is_err());
+}
+
+#[test]
+fn test_cursor_headers() {
+    let query = ListQuery::from_pairs(pairs(&[("limit", "2"), ("cursor", "old")])).unwrap();
+    let headers = query.cursor_headers(Some("new"));
+
+    assert_eq!(headers["x-next-cursor"], "new");
+    assert_eq!(headers["link"], "<?limit=2&cursor=new>; rel=\"next\"");
+    assert!(query.cursor_headers(None).is_empty());
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    middleware,
+    routing::{get, post, put},
+    Router,
+};
+use chrono::Utc;
+use common::{TestContext, PASSWORD};
+use cursor_backend::{handlers, middleware::auth::auth_middleware};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn app(ctx: &TestContext) -> Router {
+    Router::new()
+        .route("/auth/logout", post(handlers::auth::logout))
+        .route("/auth/logout-all", post(handlers::auth::logout_all))
+        .route("/auth/users/:id/role", put(handlers::auth::update_role))
+        .route("/auth/me", get(handlers::auth::me))
+        .route_layer(middleware::from_fn_with_state(ctx.state.clone(), auth_middleware))
+        .route("/auth/login", post(handlers::auth::login))
+        .route("/auth/refresh", post(handlers::auth::refresh))
+        .with_state(ctx.state.clone())
+}
+
+async fn refresh(ctx: &TestContext, app: &Router, refresh_token: &Value) -> (StatusCode, Value) {
+    let body = json!({ "refresh_token": refresh_token });
+    ctx.send(app, Method::POST, "/auth/refresh", Some(body), None).await
+}
+
+#[tokio::test]
+async fn test_refresh_rotates_the_token() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let session = ctx.register().await;
+
+    let (status, rotated) = refresh(&ctx, &app, &session["refresh_token"]).await;
+    assert_eq!(status, StatusCode::OK, "{}", rotated);
+    assert_ne!(rotated["refresh_token"], session["refresh_token"]);
+    assert_eq!(rotated["user"]["id"], session["user"]["id"]);
+
+    let token = rotated["token"].as_str().unwrap();
+    let (status, me) = ctx.send(&app, Method::GET, "/auth/me", None, Some(token)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["id"], session["user"]["id"]);
+
+    // The successor rotates in turn
+    let (status, _) = refresh(&ctx, &app, &rotated["refresh_token"]).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_reuse_revokes_the_whole_family() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let session = ctx.register().await;
+    let (_, rotated) = refresh(&ctx, &app, &session["refresh_token"]).await;
+    let token = rotated["token"].as_str().unwrap();
+    let (status, _) = ctx.send(&app, Method::GET, "/auth/me", None, Some(token)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, response) = refresh(&ctx, &app, &session["refresh_token"]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response["error"], "token_reused");
+
+    // Access tokens of the family stop working at once, not at the next sync
+    let (status, _) = ctx.send(&app, Method::GET, "/auth/me", None, Some(token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The successor handed out before the reuse is revoked with the family
+    let (status, response) = refresh(&ctx, &app, &rotated["refresh_token"]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response["error"], "invalid_token");
+    let (status, _) = refresh(&ctx, &app, &session["refresh_token"]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_logout_ends_only_its_session() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let session = ctx.register().await;
+    let body = json!({ "email": session["user"]["email"], "password": PASSWORD });
+    let (status, other) = ctx.send(&app, Method::POST, "/auth/login", Some(body), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", other);
+
+    let token = session["token"].as_str().unwrap();
+    let (status, _) = ctx.send(&app, Method::POST, "/auth/logout", None, Some(token)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = ctx.send(&app, Method::GET, "/auth/me", None, Some(token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, response) = refresh(&ctx, &app, &session["refresh_token"]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response["error"], "invalid_token");
+
+    let other_token = other["token"].as_str().unwrap();
+    let (status, _) = ctx.send(&app, Method::GET, "/auth/me", None, Some(other_token)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = refresh(&ctx, &app, &other["refresh_token"]).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_logout_all_revokes_tokens_refreshed_in_the_same_second() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let admin = ctx.register().await;
+    let session = ctx.register().await;
+    let admin_token = admin["token"].as_str().unwrap();
+    let uri = format!("/auth/users/{}/role", session["user"]["id"].as_str().unwrap());
+
+    // Start at the top of a second so both cutoffs and the refresh share it
+    let elapsed = Utc::now().timestamp_subsec_millis() as u64;
+    tokio::time::sleep(Duration::from_millis(1000 - elapsed)).await;
+
+    let body = json!({ "role": "editor" });
+    let (status, response) = ctx.send(&app, Method::PUT, &uri, Some(body), Some(admin_token)).await;
+    assert_eq!(status, StatusCode::OK, "{}", response);
+    let (status, refreshed) = refresh(&ctx, &app, &session["refresh_token"]).await;
+    assert_eq!(status, StatusCode::OK, "{}", refreshed);
+    let token = refreshed["token"].as_str().unwrap();
+    let (status, _) = ctx.send(&app, Method::GET, "/auth/me", None, Some(token)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = ctx.send(&app, Method::POST, "/auth/logout-all", None, Some(token)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = ctx.send(&app, Method::GET, "/auth/me", None, Some(token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
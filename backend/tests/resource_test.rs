@@ -0,0 +1,315 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    routing::get,
+    Router,
+};
+use common::TestContext;
+use cursor_backend::{handlers, models::CreateUserRequest, services::user_service};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+fn app(ctx: &TestContext) -> Router {
+    use handlers::{albums, comments, photos, posts, todos};
+
+    Router::new()
+        .route("/users/:id/posts", get(posts::get_user_posts))
+        .route("/users/:id/albums", get(albums::get_user_albums))
+        .route("/users/:id/todos", get(todos::get_user_todos))
+        .route("/posts", get(posts::get_posts).post(posts::create_post))
+        .route(
+            "/posts/:id",
+            get(posts::get_post)
+                .put(posts::replace_post)
+                .patch(posts::update_post)
+                .delete(posts::delete_post),
+        )
+        .route("/posts/:id/comments", get(comments::get_post_comments))
+        .route("/comments", get(comments::get_comments).post(comments::create_comment))
+        .route(
+            "/comments/:id",
+            get(comments::get_comment)
+                .put(comments::replace_comment)
+                .patch(comments::update_comment)
+                .delete(comments::delete_comment),
+        )
+        .route("/albums", get(albums::get_albums).post(albums::create_album))
+        .route(
+            "/albums/:id",
+            get(albums::get_album)
+                .put(albums::replace_album)
+                .patch(albums::update_album)
+                .delete(albums::delete_album),
+        )
+        .route("/albums/:id/photos", get(photos::get_album_photos))
+        .route("/photos", get(photos::get_photos).post(photos::create_photo))
+        .route(
+            "/photos/:id",
+            get(photos::get_photo)
+                .put(photos::replace_photo)
+                .patch(photos::update_photo)
+                .delete(photos::delete_photo),
+        )
+        .route("/todos", get(todos::get_todos).post(todos::create_todo))
+        .route(
+            "/todos/:id",
+            get(todos::get_todo)
+                .put(todos::replace_todo)
+                .patch(todos::update_todo)
+                .delete(todos::delete_todo),
+        )
+        .with_state(ctx.state.clone())
+}
+
+/// A user for resources to belong to, owned by a fresh account.
+async fn create_user(ctx: &TestContext) -> Uuid {
+    let session = ctx.register().await;
+    let owner_id = session["user"]["id"].as_str().unwrap().parse().unwrap();
+    let suffix = Uuid::new_v4().simple().to_string();
+    let user = CreateUserRequest {
+        name: "Resource Owner".to_string(),
+        username: format!("owner-{}", &suffix[..8]),
+        email: format!("owner-{}@example.com", suffix),
+        phone: None,
+        website: None,
+        address: None,
+        company: None,
+    };
+    user_service::create_user(&ctx.state.pool, &user, owner_id).await.unwrap().id
+}
+
+async fn create(ctx: &TestContext, app: &Router, uri: &str, body: Value) -> Value {
+    let (status, created) = ctx.send(app, Method::POST, uri, Some(body), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", created);
+    created
+}
+
+/// Walks a resource through `PUT`, `PATCH` and `DELETE`, checking the
+/// responses every resource shares.
+async fn check_lifecycle(
+    ctx: &TestContext,
+    app: &Router,
+    uri: &str,
+    name: &str,
+    replacement: Value,
+    (field, value): (&str, Value),
+) {
+    let (status, replaced) = ctx.send(app, Method::PUT, uri, Some(replacement.clone()), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", replaced);
+    for (key, expected) in replacement.as_object().unwrap() {
+        assert_eq!(&replaced[key], expected, "{}", key);
+    }
+
+    let patch = json!({ field: value });
+    let (status, patched) = ctx.send(app, Method::PATCH, uri, Some(patch), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", patched);
+    assert_eq!(patched[field], value);
+    assert_eq!(patched["id"], replaced["id"]);
+
+    let (status, _) = ctx.send(app, Method::DELETE, uri, None, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, response) = ctx.send(app, Method::GET, uri, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(response["message"], format!("{} not found", name));
+    let (status, _) = ctx.send(app, Method::DELETE, uri, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, response) = ctx.send(app, Method::PATCH, uri, Some(json!({})), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(response["message"], format!("{} not found or update failed", name));
+}
+
+#[tokio::test]
+async fn test_posts() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let user_id = create_user(&ctx).await;
+
+    let post = json!({ "user_id": user_id, "title": "First", "body": "Hello" });
+    let created = create(&ctx, &app, "/posts", post).await;
+    assert_eq!(created["user_id"], json!(user_id));
+    assert_eq!(created["title"], "First");
+    let second = json!({ "user_id": user_id, "title": "Second", "body": "." });
+    create(&ctx, &app, "/posts", second).await;
+
+    let uri = format!("/users/{}/posts?_sort=title&_order=desc", user_id);
+    let (status, posts) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let titles: Vec<&Value> = posts.as_array().unwrap().iter().map(|post| &post["title"]).collect();
+    assert_eq!(titles, [&json!("Second"), &json!("First")]);
+
+    let uri = format!("/posts/{}?_expand=user&_embed=comments", created["id"].as_str().unwrap());
+    let (status, post) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(post["user"]["id"], json!(user_id));
+    assert_eq!(post["comments"], json!([]));
+
+    let missing = json!({ "user_id": Uuid::new_v4(), "title": "Orphan", "body": "." });
+    let (status, response) = ctx.send(&app, Method::POST, "/posts", Some(missing), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "invalid_reference");
+    assert_eq!(response["message"], "User does not exist");
+    let empty = json!({ "user_id": user_id, "title": "", "body": "." });
+    let (status, response) = ctx.send(&app, Method::POST, "/posts", Some(empty), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "validation_error");
+    let (status, response) = ctx.send(&app, Method::GET, "/posts?bogus=1", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "invalid_query");
+
+    let uri = format!("/posts/{}", created["id"].as_str().unwrap());
+    let replacement = json!({ "user_id": user_id, "title": "Replaced", "body": "New body" });
+    check_lifecycle(&ctx, &app, &uri, "Post", replacement, ("title", json!("Patched"))).await;
+}
+
+#[tokio::test]
+async fn test_comments() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let user_id = create_user(&ctx).await;
+    let post = json!({ "user_id": user_id, "title": "P", "body": "." });
+    let post = create(&ctx, &app, "/posts", post).await;
+    let post_id = post["id"].as_str().unwrap();
+
+    let comment = json!({
+        "post_id": post_id,
+        "name": "Reader",
+        "email": "reader@example.com",
+        "body": "Nice",
+    });
+    let created = create(&ctx, &app, "/comments", comment).await;
+    assert_eq!(created["post_id"], post_id);
+
+    let uri = format!("/posts/{}/comments", post_id);
+    let (status, comments) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(comments.as_array().unwrap().len(), 1);
+    assert_eq!(comments[0]["id"], created["id"]);
+
+    let uri = format!("/comments/{}?_expand=post", created["id"].as_str().unwrap());
+    let (status, comment) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(comment["post"]["id"], post_id);
+
+    let orphan = json!({
+        "post_id": Uuid::new_v4(),
+        "name": "Reader",
+        "email": "reader@example.com",
+        "body": "Nice",
+    });
+    let (status, response) = ctx.send(&app, Method::POST, "/comments", Some(orphan), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["message"], "Post does not exist");
+
+    let uri = format!("/comments/{}", created["id"].as_str().unwrap());
+    let replacement = json!({
+        "post_id": post_id,
+        "name": "Editor",
+        "email": "editor@example.com",
+        "body": "Edited",
+    });
+    check_lifecycle(&ctx, &app, &uri, "Comment", replacement, ("body", json!("Patched"))).await;
+}
+
+#[tokio::test]
+async fn test_albums() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let user_id = create_user(&ctx).await;
+
+    let album = json!({ "user_id": user_id, "title": "Trip" });
+    let created = create(&ctx, &app, "/albums", album).await;
+    assert_eq!(created["title"], "Trip");
+
+    let uri = format!("/users/{}/albums", user_id);
+    let (status, albums) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(albums.as_array().unwrap().len(), 1);
+
+    let uri = format!("/albums/{}?_embed=photos&_expand=user", created["id"].as_str().unwrap());
+    let (status, album) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(album["photos"], json!([]));
+    assert_eq!(album["user"]["id"], json!(user_id));
+
+    let uri = format!("/albums/{}", created["id"].as_str().unwrap());
+    let replacement = json!({ "user_id": user_id, "title": "Holiday" });
+    check_lifecycle(&ctx, &app, &uri, "Album", replacement, ("title", json!("Patched"))).await;
+}
+
+#[tokio::test]
+async fn test_photos() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let user_id = create_user(&ctx).await;
+    let album = create(&ctx, &app, "/albums", json!({ "user_id": user_id, "title": "Trip" })).await;
+    let album_id = album["id"].as_str().unwrap();
+
+    let photo = json!({
+        "album_id": album_id,
+        "title": "Beach",
+        "url": "https://example.com/beach.png",
+        "thumbnail_url": "https://example.com/beach-thumb.png",
+    });
+    let created = create(&ctx, &app, "/photos", photo).await;
+    assert_eq!(created["thumbnail_url"], "https://example.com/beach-thumb.png");
+
+    let uri = format!("/albums/{}/photos", album_id);
+    let (status, photos) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(photos.as_array().unwrap().len(), 1);
+    let uri = format!("/albums/{}?_embed=photos", album_id);
+    let (_, album) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(album["photos"][0]["id"], created["id"]);
+
+    let invalid = json!({
+        "album_id": album_id,
+        "title": "Beach",
+        "url": "not a url",
+        "thumbnail_url": "https://example.com/beach-thumb.png",
+    });
+    let (status, response) = ctx.send(&app, Method::POST, "/photos", Some(invalid), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["error"], "validation_error");
+    let patch = json!({ "album_id": Uuid::new_v4() });
+    let uri = format!("/photos/{}", created["id"].as_str().unwrap());
+    let (status, response) = ctx.send(&app, Method::PATCH, &uri, Some(patch), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(response["message"], "Album does not exist");
+
+    let replacement = json!({
+        "album_id": album_id,
+        "title": "Sunset",
+        "url": "https://example.com/sunset.png",
+        "thumbnail_url": "https://example.com/sunset-thumb.png",
+    });
+    check_lifecycle(&ctx, &app, &uri, "Photo", replacement, ("title", json!("Patched"))).await;
+}
+
+#[tokio::test]
+async fn test_todos() {
+    let Some(ctx) = TestContext::new().await else { return };
+    let app = app(&ctx);
+    let user_id = create_user(&ctx).await;
+
+    let todo = json!({ "user_id": user_id, "title": "Write" });
+    let created = create(&ctx, &app, "/todos", todo).await;
+    assert_eq!(created["completed"], false);
+    let done = json!({ "user_id": user_id, "title": "Read", "completed": true });
+    create(&ctx, &app, "/todos", done).await;
+
+    let uri = format!("/users/{}/todos?completed=true", user_id);
+    let (status, todos) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(todos.as_array().unwrap().len(), 1);
+    assert_eq!(todos[0]["title"], "Read");
+
+    let uri = format!("/todos/{}?_expand=user", created["id"].as_str().unwrap());
+    let (status, todo) = ctx.send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(todo["user"]["id"], json!(user_id));
+
+    let uri = format!("/todos/{}", created["id"].as_str().unwrap());
+    let replacement = json!({ "user_id": user_id, "title": "Rewrite", "completed": false });
+    check_lifecycle(&ctx, &app, &uri, "Todo", replacement, ("completed", json!(true))).await;
+}
@@ -1,19 +1,26 @@
 #[cfg(test)]
 mod tests {
-    use cursor_backend::models::{Claims, CreateUserRequest, CreateAddressRequest, CreateGeoRequest};
+    use cursor_backend::models::{Claims, CreatePostRequest, CreateTodoRequest, CreateUserRequest, CreateAddressRequest, CreateGeoRequest, UpdateProfileRequest, AuthUserResponse, RegisterResponse, VerificationPendingResponse, Permission, Role};
     use uuid::Uuid;
+    use validator::Validate;
 
     #[test]
     fn test_claims_creation() {
         let claims = Claims {
             sub: Uuid::new_v4().to_string(),
             email: "test@example.com".to_string(),
+            jti: Uuid::new_v4().to_string(),
+            sid: Uuid::new_v4().to_string(),
+            role: Role::Viewer,
             exp: 1234567890,
             iat: 1234567890,
+            iat_micros: None,
+            scopes: None,
+            client_id: None,
         };
 
         assert_eq!(claims.email, "test@example.com");
-        assert!(claims.sub.len() > 0);
+        assert!(!claims.sub.is_empty());
     }
 
     #[test]
@@ -52,6 +59,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_create_post_request_uses_snake_case_fields() {
+        let user_id = Uuid::new_v4();
+        let post_request: CreatePostRequest = serde_json::from_value(serde_json::json!({
+            "user_id": user_id,
+            "title": "foo",
+            "body": "bar"
+        }))
+        .unwrap();
+
+        assert_eq!(post_request.user_id, user_id);
+        assert!(post_request.validate().is_ok());
+
+        let empty_title = CreatePostRequest {
+            title: String::new(),
+            ..post_request
+        };
+        assert!(empty_title.validate().is_err());
+    }
+
+    #[test]
+    fn test_create_todo_request_defaults_to_incomplete() {
+        let todo_request: CreateTodoRequest = serde_json::from_value(serde_json::json!({
+            "user_id": Uuid::new_v4(),
+            "title": "delectus aut autem"
+        }))
+        .unwrap();
+
+        assert!(!todo_request.completed);
+        assert!(todo_request.validate().is_ok());
+    }
+
+    #[test]
+    fn test_update_profile_request_allows_partial_updates() {
+        let rename: UpdateProfileRequest =
+            serde_json::from_value(serde_json::json!({ "name": "Jane Doe" })).unwrap();
+        assert!(rename.email.is_none());
+        assert!(rename.validate().is_ok());
+
+        let bad_email: UpdateProfileRequest =
+            serde_json::from_value(serde_json::json!({ "email": "not-an-email" })).unwrap();
+        assert!(bad_email.validate().is_err());
+
+        let empty_name: UpdateProfileRequest =
+            serde_json::from_value(serde_json::json!({ "name": "" })).unwrap();
+        assert!(empty_name.validate().is_err());
+    }
+
+    #[test]
+    fn test_pending_registration_has_no_tokens() {
+        let response = RegisterResponse::VerificationPending(VerificationPendingResponse {
+            user: AuthUserResponse {
+                id: Uuid::new_v4(),
+                name: "Jane Doe".to_string(),
+                email: "jane@example.com".to_string(),
+                email_verified_at: None,
+                role: Role::Editor,
+                created_at: chrono::Utc::now(),
+            },
+            email_verification_required: true,
+        });
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("token").is_none());
+        assert_eq!(json["email_verification_required"], true);
+        assert!(json["user"]["email_verified_at"].is_null());
+    }
+
+    #[test]
+    fn test_roles_are_ordered_and_grant_permissions() {
+        assert!(Role::Viewer < Role::Editor && Role::Editor < Role::Admin);
+        assert!(Role::Admin.has_permission(Permission::DeleteUsers));
+        assert!(Role::Editor.has_permission(Permission::UpdateUsers));
+        assert!(!Role::Editor.has_permission(Permission::DeleteUsers));
+        assert!(Role::Admin.has_permission(Permission::ManageAnyUser));
+        assert!(!Role::Editor.has_permission(Permission::ManageAnyUser));
+        assert!(!Role::Editor.has_permission(Permission::ViewLoginAttempts));
+        assert!(Role::Admin.has_permission(Permission::ViewLoginAttempts));
+        assert!(!Role::Editor.has_permission(Permission::ManageRoles));
+        assert!(Role::Viewer.permissions().is_empty());
+
+        assert_eq!("admin".parse::<Role>(), Ok(Role::Admin));
+        assert!("root".parse::<Role>().is_err());
+        assert_eq!(serde_json::to_value(Role::Editor).unwrap(), "editor");
+    }
+
     #[test]
     fn test_uuid_generation() {
         let id1 = Uuid::new_v4();
@@ -4,6 +4,8 @@ import Header from './components/Header';
 import UsersList from './pages/UsersList';
 import Login from './pages/Login';
 import Register from './pages/Register';
+import OAuthAuthorize from './pages/OAuthAuthorize';
+import MagicLink from './pages/MagicLink';
 
 const App = () => {
   return (
@@ -16,6 +18,8 @@ const App = () => {
               <Route path="/" element={<UsersList />} />
               <Route path="/login" element={<Login />} />
               <Route path="/register" element={<Register />} />
+              <Route path="/magic-link" element={<MagicLink />} />
+              <Route path="/oauth/authorize" element={<OAuthAuthorize />} />
             </Routes>
           </main>
         </div>
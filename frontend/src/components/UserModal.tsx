@@ -4,7 +4,8 @@ import type { User } from '../types';
 interface UserModalProps {
   user: User;
   onClose: () => void;
-  onDelete: (userId: string) => void;
+  // Omitted when the current account is not allowed to delete users
+  onDelete?: (userId: string) => void;
 }
 
 const UserModal = ({ user, onClose, onDelete }: UserModalProps) => {
@@ -31,7 +32,7 @@ const UserModal = ({ user, onClose, onDelete }: UserModalProps) => {
   };
 
   const handleDelete = () => {
-    if (window.confirm(`Are you sure you want to delete ${user.name}?`)) {
+    if (onDelete && window.confirm(`Are you sure you want to delete ${user.name}?`)) {
       onDelete(user.id);
     }
   };
@@ -172,12 +173,16 @@ const UserModal = ({ user, onClose, onDelete }: UserModalProps) => {
         </div>
 
         <div className="sticky bottom-0 bg-gray-50 border-t border-gray-200 px-6 py-4 flex justify-between">
-          <button
-            onClick={handleDelete}
-            className="bg-red-500 hover:bg-red-600 text-white font-medium py-2 px-4 rounded-lg transition-colors"
-          >
-            Delete User
-          </button>
+          {onDelete ? (
+            <button
+              onClick={handleDelete}
+              className="bg-red-500 hover:bg-red-600 text-white font-medium py-2 px-4 rounded-lg transition-colors"
+            >
+              Delete User
+            </button>
+          ) : (
+            <span />
+          )}
           <button
             onClick={onClose}
             className="btn-secondary"
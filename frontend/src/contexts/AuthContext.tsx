@@ -6,8 +6,13 @@ import { authApi } from '../services/api';
 interface AuthContextType {
   user: AuthUser | null;
   token: string | null;
-  login: (credentials: LoginRequest) => Promise<void>;
-  register: (userData: RegisterRequest) => Promise<void>;
+  /** Resolves to an MFA token when the login has to be finished with `verifyMfa`. */
+  login: (credentials: LoginRequest) => Promise<string | null>;
+  verifyMfa: (mfaToken: string, code: string) => Promise<void>;
+  /** Logs in with a sign-in link token; resolves like `login`. */
+  consumeMagicLink: (token: string) => Promise<string | null>;
+  /** Resolves to `false` when the account has to verify its email before logging in. */
+  register: (userData: RegisterRequest) => Promise<boolean>;
   logout: () => void;
   isLoading: boolean;
 }
@@ -36,32 +41,77 @@ export const AuthProvider = ({ children }: AuthProviderProps) => {
     const storedToken = localStorage.getItem('token');
     const storedUser = localStorage.getItem('user');
     
-    if (storedToken && storedUser) {
-      setToken(storedToken);
+    if (!storedToken) {
+      setIsLoading(false);
+      return;
+    }
+
+    setToken(storedToken);
+    if (storedUser) {
       setUser(JSON.parse(storedUser));
     }
-    setIsLoading(false);
+
+    // Refresh the cached profile from the server
+    authApi
+      .me()
+      .then((currentUser) => {
+        setUser(currentUser);
+        setToken(localStorage.getItem('token'));
+        localStorage.setItem('user', JSON.stringify(currentUser));
+      })
+      .catch(() => {
+        // The session could not be refreshed, so the stored one is gone
+        if (!localStorage.getItem('token')) {
+          setUser(null);
+          setToken(null);
+          localStorage.removeItem('user');
+        }
+      })
+      .finally(() => setIsLoading(false));
   }, []);
 
+  const storeSession = (response: AuthResponse) => {
+    setUser(response.user);
+    setToken(response.token);
+    localStorage.setItem('token', response.token);
+    localStorage.setItem('refresh_token', response.refresh_token);
+    localStorage.setItem('user', JSON.stringify(response.user));
+  };
+
   const handleLogin = async (credentials: LoginRequest) => {
-    try {
-      const response: AuthResponse = await authApi.login(credentials);
-      setUser(response.user);
-      setToken(response.token);
-      localStorage.setItem('token', response.token);
-      localStorage.setItem('user', JSON.stringify(response.user));
-    } catch (error) {
-      throw error;
+    const response = await authApi.login(credentials);
+    if ('mfa_required' in response) {
+      return response.mfa_token;
+    }
+    storeSession(response);
+    return null;
+  };
+
+  const handleConsumeMagicLink = async (magicToken: string) => {
+    const response = await authApi.consumeMagicLink({ token: magicToken });
+    if ('mfa_required' in response) {
+      return response.mfa_token;
     }
+    storeSession(response);
+    return null;
+  };
+
+  const handleVerifyMfa = async (mfaToken: string, code: string) => {
+    storeSession(await authApi.verifyMfa({ mfa_token: mfaToken, code }));
   };
 
   const handleRegister = async (userData: RegisterRequest) => {
     try {
-      const response: AuthResponse = await authApi.register(userData);
+      const response = await authApi.register(userData);
+      if (!('token' in response)) {
+        return false;
+      }
       setUser(response.user);
       setToken(response.token);
       localStorage.setItem('token', response.token);
+      localStorage.setItem('refresh_token', response.refresh_token);
       localStorage.setItem('user', JSON.stringify(response.user));
+      return true;
     } catch (error) {
       throw error;
     }
@@ -71,6 +121,7 @@ export const AuthProvider = ({ children }: AuthProviderProps) => {
     setUser(null);
     setToken(null);
     localStorage.removeItem('token');
+    localStorage.removeItem('refresh_token');
     localStorage.removeItem('user');
   };
 
@@ -78,6 +129,8 @@ export const AuthProvider = ({ children }: AuthProviderProps) => {
     user,
     token,
     login: handleLogin,
+    verifyMfa: handleVerifyMfa,
+    consumeMagicLink: handleConsumeMagicLink,
     register: handleRegister,
     logout: handleLogout,
     isLoading,
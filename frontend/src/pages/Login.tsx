@@ -1,15 +1,26 @@
 import { useState } from 'react';
-import { Link, useNavigate } from 'react-router-dom';
+import { Link, useLocation, useNavigate, useSearchParams } from 'react-router-dom';
 import { useAuth } from '../contexts/AuthContext';
+import { authApi } from '../services/api';
 import type { LoginRequest } from '../types';
 
 const Login = () => {
-  const { login } = useAuth();
+  const { login, verifyMfa } = useAuth();
   const navigate = useNavigate();
+  // Pages that require a session (e.g. the OAuth consent screen) send users back here
+  const [searchParams] = useSearchParams();
+  const next = searchParams.get('next')?.startsWith('/') ? searchParams.get('next')! : '/';
   const [formData, setFormData] = useState<LoginRequest>({
     email: '',
     password: '',
   });
+  // The sign-in link page hands over its MFA challenge to finish here
+  const location = useLocation();
+  const [mfaToken, setMfaToken] = useState<string | null>(
+    (location.state as { mfaToken?: string } | null)?.mfaToken ?? null,
+  );
+  const [magicLinkSent, setMagicLinkSent] = useState(false);
+  const [code, setCode] = useState('');
   const [error, setError] = useState('');
   const [isLoading, setIsLoading] = useState(false);
 
@@ -26,8 +37,12 @@ const Login = () => {
     setIsLoading(true);
 
     try {
-      await login(formData);
-      navigate('/');
+      const pendingMfaToken = await login(formData);
+      if (pendingMfaToken) {
+        setMfaToken(pendingMfaToken);
+        return;
+      }
+      navigate(next);
     } catch {
       setError('Invalid email or password');
     } finally {
@@ -35,56 +50,135 @@ const Login = () => {
     }
   };
 
+  const handleMagicLink = async () => {
+    if (!formData.email) {
+      setError('Enter your email to get a sign-in link');
+      return;
+    }
+    setError('');
+    setIsLoading(true);
+
+    try {
+      await authApi.requestMagicLink({ email: formData.email });
+      setMagicLinkSent(true);
+    } catch {
+      setError('Failed to send a sign-in link');
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  const handleMfaSubmit = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!mfaToken) return;
+    setError('');
+    setIsLoading(true);
+
+    try {
+      await verifyMfa(mfaToken, code.trim());
+      navigate(next);
+    } catch {
+      setError('Invalid code');
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
   return (
     <div className="max-w-md mx-auto">
       <div className="card p-6">
         <h1 className="text-2xl font-bold text-center mb-6">Login</h1>
         
+        {magicLinkSent && (
+          <div className="bg-green-100 border border-green-400 text-green-700 px-4 py-3 rounded mb-4">
+            If an account exists for {formData.email}, we've emailed it a sign-in link.
+          </div>
+        )}
+
         {error && (
           <div className="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
             {error}
           </div>
         )}
 
-        <form onSubmit={handleSubmit} className="space-y-4">
-          <div>
-            <label htmlFor="email" className="block text-sm font-medium text-gray-700 mb-1">
-              Email
-            </label>
-            <input
-              type="email"
-              id="email"
-              name="email"
-              value={formData.email}
-              onChange={handleChange}
-              className="input-field"
-              required
-            />
-          </div>
+        {mfaToken ? (
+          <form onSubmit={handleMfaSubmit} className="space-y-4">
+            <div>
+              <label htmlFor="code" className="block text-sm font-medium text-gray-700 mb-1">
+                Authentication code
+              </label>
+              <input
+                type="text"
+                id="code"
+                name="code"
+                value={code}
+                onChange={(e) => setCode(e.target.value)}
+                className="input-field"
+                autoComplete="one-time-code"
+                placeholder="123456 or a recovery code"
+                autoFocus
+                required
+              />
+            </div>
 
-          <div>
-            <label htmlFor="password" className="block text-sm font-medium text-gray-700 mb-1">
-              Password
-            </label>
-            <input
-              type="password"
-              id="password"
-              name="password"
-              value={formData.password}
-              onChange={handleChange}
-              className="input-field"
-              required
-            />
-          </div>
+            <button
+              type="submit"
+              disabled={isLoading}
+              className="w-full btn-primary disabled:opacity-50 disabled:cursor-not-allowed"
+            >
+              {isLoading ? 'Verifying...' : 'Verify'}
+            </button>
+          </form>
+        ) : (
+          <form onSubmit={handleSubmit} className="space-y-4">
+            <div>
+              <label htmlFor="email" className="block text-sm font-medium text-gray-700 mb-1">
+                Email
+              </label>
+              <input
+                type="email"
+                id="email"
+                name="email"
+                value={formData.email}
+                onChange={handleChange}
+                className="input-field"
+                required
+              />
+            </div>
 
-          <button
-            type="submit"
-            disabled={isLoading}
-            className="w-full btn-primary disabled:opacity-50 disabled:cursor-not-allowed"
-          >
-            {isLoading ? 'Logging in...' : 'Login'}
-          </button>
-        </form>
+            <div>
+              <label htmlFor="password" className="block text-sm font-medium text-gray-700 mb-1">
+                Password
+              </label>
+              <input
+                type="password"
+                id="password"
+                name="password"
+                value={formData.password}
+                onChange={handleChange}
+                className="input-field"
+                required
+              />
+            </div>
+
+            <button
+              type="submit"
+              disabled={isLoading}
+              className="w-full btn-primary disabled:opacity-50 disabled:cursor-not-allowed"
+            >
+              {isLoading ? 'Logging in...' : 'Login'}
+            </button>
+
+            <button
+              type="button"
+              onClick={handleMagicLink}
+              disabled={isLoading}
+              className="w-full btn-secondary disabled:opacity-50 disabled:cursor-not-allowed"
+            >
+              Email me a sign-in link
+            </button>
+          </form>
+        )}
 
         <p className="text-center mt-4 text-sm text-gray-600">
           Don't have an account?{' '}
@@ -0,0 +1,55 @@
+import { useEffect, useRef, useState } from 'react';
+import { Link, useNavigate, useSearchParams } from 'react-router-dom';
+import { useAuth } from '../contexts/AuthContext';
+
+// Target of the sign-in link emailed by POST /auth/magic-link
+const MagicLink = () => {
+  const { consumeMagicLink } = useAuth();
+  const navigate = useNavigate();
+  const [searchParams] = useSearchParams();
+  const [error, setError] = useState('');
+  // The token is single-use, so it must not be sent twice (e.g. by StrictMode)
+  const consumed = useRef(false);
+
+  useEffect(() => {
+    const token = searchParams.get('token');
+    if (!token) {
+      setError('This sign-in link is invalid');
+      return;
+    }
+    if (consumed.current) return;
+    consumed.current = true;
+
+    consumeMagicLink(token)
+      .then((mfaToken) => {
+        if (mfaToken) {
+          navigate('/login', { replace: true, state: { mfaToken } });
+        } else {
+          navigate('/', { replace: true });
+        }
+      })
+      .catch(() => setError('This sign-in link is invalid or has expired'));
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [searchParams]);
+
+  return (
+    <div className="max-w-md mx-auto">
+      <div className="card p-6 text-center">
+        {error ? (
+          <>
+            <div className="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
+              {error}
+            </div>
+            <Link to="/login" className="text-primary-600 hover:text-primary-700">
+              Back to login
+            </Link>
+          </>
+        ) : (
+          <p className="text-gray-600">Signing you in...</p>
+        )}
+      </div>
+    </div>
+  );
+};
+
+export default MagicLink;
@@ -0,0 +1,117 @@
+import { useEffect, useState } from 'react';
+import { Navigate, useLocation, useSearchParams } from 'react-router-dom';
+import { useAuth } from '../contexts/AuthContext';
+import { oauthApi } from '../services/api';
+import type { AuthorizationPrompt, AuthorizeDecisionRequest, Scope } from '../types';
+
+const scopeDescriptions: Record<Scope, string> = {
+  'users:read': 'View users',
+  'users:write': 'Create, update and delete users',
+  admin: 'Manage roles and view login attempts',
+};
+
+// Consent screen for OAuth client applications, reached with the parameters of /oauth/authorize
+const OAuthAuthorize = () => {
+  const { user, isLoading: authLoading } = useAuth();
+  const location = useLocation();
+  const [searchParams] = useSearchParams();
+  const [prompt, setPrompt] = useState<AuthorizationPrompt | null>(null);
+  const [error, setError] = useState('');
+  const [isSubmitting, setIsSubmitting] = useState(false);
+
+  const request = (approve: boolean): AuthorizeDecisionRequest => ({
+    response_type: searchParams.get('response_type') ?? '',
+    client_id: searchParams.get('client_id') ?? '',
+    redirect_uri: searchParams.get('redirect_uri') ?? '',
+    scope: searchParams.get('scope') ?? undefined,
+    state: searchParams.get('state') ?? undefined,
+    code_challenge: searchParams.get('code_challenge') ?? undefined,
+    code_challenge_method: searchParams.get('code_challenge_method') ?? undefined,
+    approve,
+  });
+
+  const decide = async (approve: boolean) => {
+    setIsSubmitting(true);
+    try {
+      window.location.assign(await oauthApi.decide(request(approve)));
+    } catch {
+      setError('Failed to complete the authorization request');
+      setIsSubmitting(false);
+    }
+  };
+
+  useEffect(() => {
+    if (!user) return;
+
+    oauthApi
+      .getAuthorization(searchParams.toString())
+      .then((prompt) => {
+        // Scopes the user already allowed are not asked for again
+        if (prompt.consent_required) {
+          setPrompt(prompt);
+        } else {
+          decide(true);
+        }
+      })
+      .catch((err) => {
+        setError(err.response?.data?.message ?? 'Invalid authorization request');
+      });
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [user, searchParams]);
+
+  if (authLoading) {
+    return null;
+  }
+  if (!user) {
+    const next = encodeURIComponent(location.pathname + location.search);
+    return <Navigate to={`/login?next=${next}`} replace />;
+  }
+
+  return (
+    <div className="max-w-md mx-auto">
+      <div className="card p-6">
+        {error ? (
+          <div className="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded">
+            {error}
+          </div>
+        ) : !prompt ? (
+          <p className="text-center text-gray-600">Loading...</p>
+        ) : (
+          <>
+            <h1 className="text-2xl font-bold text-center mb-2">Authorize {prompt.client_name}</h1>
+            <p className="text-center text-gray-600 mb-6">
+              {prompt.client_name} wants to access your account ({user.email}) to:
+            </p>
+
+            <ul className="list-disc list-inside space-y-1 mb-6">
+              {prompt.scopes.map((scope) => (
+                <li key={scope}>{scopeDescriptions[scope]}</li>
+              ))}
+            </ul>
+
+            <div className="flex gap-3">
+              <button
+                type="button"
+                onClick={() => decide(false)}
+                disabled={isSubmitting}
+                className="flex-1 btn-secondary disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                Deny
+              </button>
+              <button
+                type="button"
+                onClick={() => decide(true)}
+                disabled={isSubmitting}
+                className="flex-1 btn-primary disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                {isSubmitting ? 'Redirecting...' : 'Allow'}
+              </button>
+            </div>
+          </>
+        )}
+      </div>
+    </div>
+  );
+};
+
+export default OAuthAuthorize;
@@ -12,6 +12,7 @@ const Register = () => {
     password: '',
   });
   const [error, setError] = useState('');
+  const [notice, setNotice] = useState('');
   const [isLoading, setIsLoading] = useState(false);
 
   const handleChange = (e: React.ChangeEvent<HTMLInputElement>) => {
@@ -24,11 +25,15 @@ const Register = () => {
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
     setError('');
+    setNotice('');
     setIsLoading(true);
 
     try {
-      await register(formData);
-      navigate('/');
+      if (await register(formData)) {
+        navigate('/');
+      } else {
+        setNotice(`We sent a confirmation link to ${formData.email}. Verify your email, then log in.`);
+      }
     } catch {
       setError('Registration failed. Please try again.');
     } finally {
@@ -41,6 +46,12 @@ const Register = () => {
       <div className="card p-6">
         <h1 className="text-2xl font-bold text-center mb-6">Register</h1>
         
+        {notice && (
+          <div className="bg-green-100 border border-green-400 text-green-700 px-4 py-3 rounded mb-4">
+            {notice}
+          </div>
+        )}
+
         {error && (
           <div className="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
             {error}
@@ -112,7 +112,7 @@ const UsersList = () => {
         <UserModal
           user={selectedUser}
           onClose={handleCloseModal}
-          onDelete={handleDeleteUser}
+          onDelete={currentUser?.role === 'admin' ? handleDeleteUser : undefined}
         />
       )}
     </div>
@@ -1,5 +1,5 @@
-import axios from 'axios';
-import type { AuthResponse, LoginRequest, RegisterRequest, User, CreateUserRequest } from '../types';
+import axios, { AxiosError, type InternalAxiosRequestConfig } from 'axios';
+import type { AuthResponse, AuthUser, AuthorizationPrompt, AuthorizeDecisionRequest, VerificationPendingResponse, LoginRequest, MagicLinkConsumeRequest, MagicLinkRequest, MfaChallengeResponse, MfaVerifyRequest, RegisterRequest, User, CreateUserRequest } from '../types';
 
 const API_BASE_URL = import.meta.env.VITE_API_URL || 'http://localhost:8080';
 
@@ -19,17 +19,100 @@ apiClient.interceptors.request.use((config) => {
   return config;
 });
 
+// Access tokens are short-lived: on a 401, exchange the refresh token once and retry
+let refreshing: Promise<string | null> | null = null;
+
+const refreshAccessToken = async (): Promise<string | null> => {
+  const refreshToken = localStorage.getItem('refresh_token');
+  if (!refreshToken) {
+    return null;
+  }
+
+  try {
+    const response = await axios.post<AuthResponse>(`${API_BASE_URL}/auth/refresh`, {
+      refresh_token: refreshToken,
+    });
+    localStorage.setItem('token', response.data.token);
+    localStorage.setItem('refresh_token', response.data.refresh_token);
+    return response.data.token;
+  } catch {
+    localStorage.removeItem('token');
+    localStorage.removeItem('refresh_token');
+    return null;
+  }
+};
+
+apiClient.interceptors.response.use(
+  (response) => response,
+  async (error: AxiosError) => {
+    const request = error.config as (InternalAxiosRequestConfig & { _retried?: boolean }) | undefined;
+    if (error.response?.status !== 401 || !request || request._retried) {
+      return Promise.reject(error);
+    }
+
+    // Concurrent 401s share one refresh, since each refresh token is single-use
+    refreshing = refreshing ?? refreshAccessToken().finally(() => {
+      refreshing = null;
+    });
+    const token = await refreshing;
+    if (!token) {
+      return Promise.reject(error);
+    }
+
+    request._retried = true;
+    request.headers.Authorization = `Bearer ${token}`;
+    return apiClient(request);
+  },
+);
+
 // Auth API
 export const authApi = {
-  login: async (credentials: LoginRequest): Promise<AuthResponse> => {
+  login: async (credentials: LoginRequest): Promise<AuthResponse | MfaChallengeResponse> => {
     const response = await apiClient.post('/auth/login', credentials);
     return response.data;
   },
 
-  register: async (userData: RegisterRequest): Promise<AuthResponse> => {
+  requestMagicLink: async (request: MagicLinkRequest): Promise<void> => {
+    await apiClient.post('/auth/magic-link', request);
+  },
+
+  consumeMagicLink: async (
+    request: MagicLinkConsumeRequest,
+  ): Promise<AuthResponse | MfaChallengeResponse> => {
+    const response = await apiClient.post('/auth/magic-link/consume', request);
+    return response.data;
+  },
+
+  verifyMfa: async (request: MfaVerifyRequest): Promise<AuthResponse> => {
+    const response = await apiClient.post('/auth/mfa/verify', request);
+    return response.data;
+  },
+
+  register: async (
+    userData: RegisterRequest,
+  ): Promise<AuthResponse | VerificationPendingResponse> => {
     const response = await apiClient.post('/auth/register', userData);
     return response.data;
   },
+
+  me: async (): Promise<AuthUser> => {
+    const response = await apiClient.get('/auth/me');
+    return response.data;
+  },
+};
+
+// OAuth consent screen
+export const oauthApi = {
+  // `query` is the query string the client application sent the user with
+  getAuthorization: async (query: string): Promise<AuthorizationPrompt> => {
+    const response = await apiClient.get(`/oauth/authorize?${query}`);
+    return response.data;
+  },
+
+  decide: async (request: AuthorizeDecisionRequest): Promise<string> => {
+    const response = await apiClient.post('/oauth/authorize', request);
+    return response.data.redirect_to;
+  },
 };
 
 // Users API
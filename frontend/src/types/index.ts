@@ -9,6 +9,7 @@ export interface User {
   company?: Company;
   created_at: string;
   updated_at: string;
+  owner_id: string | null;
 }
 
 export interface Address {
@@ -34,23 +35,76 @@ export interface Company {
   bs?: string;
 }
 
+export type Role = 'admin' | 'editor' | 'viewer';
+
 export interface AuthUser {
   id: string;
   name: string;
   email: string;
+  email_verified_at: string | null;
+  role: Role;
   created_at: string;
 }
 
 export interface AuthResponse {
   token: string;
+  refresh_token: string;
+  expires_in: number;
   user: AuthUser;
 }
 
+// Returned by /auth/register when the account must verify its email first
+export interface VerificationPendingResponse {
+  user: AuthUser;
+  email_verification_required: true;
+}
+
+// Returned by /auth/login when the account has two-factor authentication enabled
+export interface MfaChallengeResponse {
+  mfa_required: true;
+  mfa_token: string;
+  expires_in: number;
+}
+
 export interface LoginRequest {
   email: string;
   password: string;
 }
 
+export interface MagicLinkRequest {
+  email: string;
+}
+
+export interface MagicLinkConsumeRequest {
+  token: string;
+}
+
+export interface MfaVerifyRequest {
+  mfa_token: string;
+  code: string;
+}
+
+export type Scope = 'users:read' | 'users:write' | 'admin';
+
+// What the consent screen shows for a valid /oauth/authorize request
+export interface AuthorizationPrompt {
+  client_id: string;
+  client_name: string;
+  scopes: Scope[];
+  consent_required: boolean;
+}
+
+export interface AuthorizeDecisionRequest {
+  response_type: string;
+  client_id: string;
+  redirect_uri: string;
+  scope?: string;
+  state?: string;
+  code_challenge?: string;
+  code_challenge_method?: string;
+  approve: boolean;
+}
+
 export interface RegisterRequest {
   name: string;
   email: string;